@@ -99,6 +99,12 @@ pub struct JupiterSwapResult {
 /// This is a placeholder - actual discriminator depends on Jupiter's instruction format
 const JUPITER_SWAP_DISCRIMINATOR: u8 = 0x9a; // Placeholder - needs to match Jupiter's actual discriminator
 
+/// Protocol fee timelock until the authority lengthens it (48 hours)
+pub const DEFAULT_PROTOCOL_FEE_TIMELOCK: i64 = 172_800;
+
+/// Longest protocol fee timelock (30 days), so fee changes can't be frozen by a timelock that never expires
+pub const MAX_PROTOCOL_FEE_TIMELOCK: i64 = 2_592_000;
+
 #[program]
 pub mod flow {
     use super::*;
@@ -131,6 +137,9 @@ pub mod flow {
         config.compliance_mode = ComplianceMode::Enhanced;
         config.created_at = clock.unix_timestamp;
         config.updated_at = clock.unix_timestamp;
+        config.pending_authority = None;
+        config.pending_fee_change = None;
+        config.fee_timelock = DEFAULT_PROTOCOL_FEE_TIMELOCK;
 
         msg!("Protocol config initialized by: {}", ctx.accounts.authority.key());
         Ok(())
@@ -760,6 +769,203 @@ pub mod flow {
         msg!("Rebalance decision approved by: {}", ctx.accounts.approver.key());
         Ok(())
     }
+
+    /// Queue a protocol fee change, applied by `apply_protocol_fee_change` once the
+    /// fee timelock has passed (authority only). Replaces any change already queued.
+    pub fn queue_protocol_fee_change(
+        ctx: Context<UpdateProtocolConfig>,
+        performance_fee_bps: u16,
+        protocol_fee_bps: u16,
+    ) -> Result<()> {
+        require!(
+            performance_fee_bps <= 10_000 && protocol_fee_bps <= 10_000,
+            XLiquidityEngineError::InvalidFeeBps
+        );
+
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let effective_at = clock
+            .unix_timestamp
+            .checked_add(config.fee_timelock)
+            .ok_or(XLiquidityEngineError::ArithmeticOverflow)?;
+
+        config.pending_fee_change = Some(PendingProtocolFeeChange {
+            performance_fee_bps,
+            protocol_fee_bps,
+            effective_at,
+        });
+        config.updated_at = clock.unix_timestamp;
+
+        msg!(
+            "Protocol fee change queued: performance {} -> {} bps, protocol {} -> {} bps, effective at {}",
+            config.performance_fee_bps,
+            performance_fee_bps,
+            config.protocol_fee_bps,
+            protocol_fee_bps,
+            effective_at
+        );
+        Ok(())
+    }
+
+    /// Apply the queued protocol fee change once its effective time has passed (callable by anyone)
+    pub fn apply_protocol_fee_change(ctx: Context<ApplyProtocolFeeChange>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let pending = config
+            .pending_fee_change
+            .ok_or(XLiquidityEngineError::NoPendingFeeChange)?;
+        require!(
+            clock.unix_timestamp >= pending.effective_at,
+            XLiquidityEngineError::FeeChangeNotReady
+        );
+
+        config.performance_fee_bps = pending.performance_fee_bps;
+        config.protocol_fee_bps = pending.protocol_fee_bps;
+        config.pending_fee_change = None;
+        config.updated_at = clock.unix_timestamp;
+
+        msg!(
+            "Protocol fees updated: performance {} bps, protocol {} bps",
+            pending.performance_fee_bps,
+            pending.protocol_fee_bps
+        );
+        Ok(())
+    }
+
+    /// Drop the queued protocol fee change (authority only)
+    pub fn cancel_protocol_fee_change(ctx: Context<UpdateProtocolConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            config.pending_fee_change.is_some(),
+            XLiquidityEngineError::NoPendingFeeChange
+        );
+        config.pending_fee_change = None;
+        config.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("Protocol fee change cancelled");
+        Ok(())
+    }
+
+    /// Lengthen the delay between queueing and applying a protocol fee change, up to
+    /// `MAX_PROTOCOL_FEE_TIMELOCK` (authority only)
+    pub fn set_protocol_fee_timelock(
+        ctx: Context<UpdateProtocolConfig>,
+        fee_timelock: i64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(
+            fee_timelock >= config.fee_timelock,
+            XLiquidityEngineError::TimelockDecrease
+        );
+        require!(
+            fee_timelock <= MAX_PROTOCOL_FEE_TIMELOCK,
+            XLiquidityEngineError::TimelockTooLong
+        );
+        config.fee_timelock = fee_timelock;
+        config.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("Protocol fee timelock set to {} seconds", fee_timelock);
+        Ok(())
+    }
+
+    /// Propose a new protocol authority, or withdraw the proposal with `None` (authority only).
+    /// The authority changes once the proposed key calls `accept_protocol_authority`.
+    pub fn propose_protocol_authority(
+        ctx: Context<UpdateProtocolConfig>,
+        new_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.pending_authority = new_authority;
+        config.updated_at = Clock::get()?.unix_timestamp;
+
+        match new_authority {
+            Some(new_authority) => msg!("Protocol authority proposed: {}", new_authority),
+            None => msg!("Protocol authority proposal withdrawn"),
+        }
+        Ok(())
+    }
+
+    /// Become the protocol authority, as the key proposed by `propose_protocol_authority`
+    pub fn accept_protocol_authority(ctx: Context<AcceptProtocolAuthority>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.authority = ctx.accounts.new_authority.key();
+        config.pending_authority = None;
+        config.updated_at = Clock::get()?.unix_timestamp;
+
+        msg!("Protocol authority transferred to: {}", config.authority);
+        Ok(())
+    }
+
+    /// Grow a protocol config created before the governance fields to the current layout,
+    /// with no pending changes and the default fee timelock (authority only)
+    pub fn migrate_protocol_config(ctx: Context<MigrateProtocolConfig>) -> Result<()> {
+        let config_info = ctx.accounts.config.to_account_info();
+        let legacy = {
+            let data = config_info.try_borrow_data()?;
+            require!(
+                data.len() == 8 + LegacyProtocolConfig::LEN,
+                XLiquidityEngineError::AlreadyMigrated
+            );
+            require!(
+                data[..8] == *ProtocolConfig::DISCRIMINATOR,
+                XLiquidityEngineError::AlreadyMigrated
+            );
+            LegacyProtocolConfig::deserialize(&mut &data[8..])?
+        };
+        require_keys_eq!(
+            legacy.authority,
+            ctx.accounts.authority.key(),
+            XLiquidityEngineError::Unauthorized
+        );
+
+        let new_len = 8 + ProtocolConfig::LEN;
+        let rent_due = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(config_info.lamports());
+        if rent_due > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: config_info.clone(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+        config_info.resize(new_len)?;
+
+        let config = ProtocolConfig {
+            authority: legacy.authority,
+            config_bump: legacy.config_bump,
+            performance_fee_bps: legacy.performance_fee_bps,
+            protocol_fee_bps: legacy.protocol_fee_bps,
+            fee_recipient: legacy.fee_recipient,
+            x402_facilitator: legacy.x402_facilitator,
+            x402_min_payment: legacy.x402_min_payment,
+            x402_api_base_url: legacy.x402_api_base_url,
+            min_rebalance_interval: legacy.min_rebalance_interval,
+            max_rebalance_frequency: legacy.max_rebalance_frequency,
+            default_slippage_tolerance_bps: legacy.default_slippage_tolerance_bps,
+            max_position_size: legacy.max_position_size,
+            max_single_trade_size: legacy.max_single_trade_size,
+            require_human_approval_threshold: legacy.require_human_approval_threshold,
+            default_ai_model_version: legacy.default_ai_model_version,
+            ai_model_registry: legacy.ai_model_registry,
+            audit_log_enabled: legacy.audit_log_enabled,
+            compliance_mode: legacy.compliance_mode,
+            created_at: legacy.created_at,
+            updated_at: Clock::get()?.unix_timestamp,
+            pending_authority: None,
+            pending_fee_change: None,
+            fee_timelock: DEFAULT_PROTOCOL_FEE_TIMELOCK,
+        };
+        config.try_serialize(&mut &mut config_info.try_borrow_mut_data()?[..])?;
+
+        msg!("Protocol config migrated to the governance layout");
+        Ok(())
+    }
 }
 
 // ============================================================================
@@ -2133,6 +2339,44 @@ pub struct ProtocolConfig {
     // Timestamps
     pub created_at: i64,
     pub updated_at: i64,
+    
+    // Governance (appended, so configs created before it can be migrated in place)
+    pub pending_authority: Option<Pubkey>,
+    pub pending_fee_change: Option<PendingProtocolFeeChange>,
+    pub fee_timelock: i64,
+}
+
+/// A queued protocol fee change
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PendingProtocolFeeChange {
+    pub performance_fee_bps: u16,
+    pub protocol_fee_bps: u16,
+    pub effective_at: i64,
+}
+
+/// `ProtocolConfig` as created before the governance fields, read by `migrate_protocol_config`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyProtocolConfig {
+    pub authority: Pubkey,
+    pub config_bump: u8,
+    pub performance_fee_bps: u16,
+    pub protocol_fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub x402_facilitator: Option<Pubkey>,
+    pub x402_min_payment: u64,
+    pub x402_api_base_url: String,
+    pub min_rebalance_interval: u32,
+    pub max_rebalance_frequency: u32,
+    pub default_slippage_tolerance_bps: u16,
+    pub max_position_size: u64,
+    pub max_single_trade_size: u64,
+    pub require_human_approval_threshold: u64,
+    pub default_ai_model_version: String,
+    pub ai_model_registry: Vec<Pubkey>,
+    pub audit_log_enabled: bool,
+    pub compliance_mode: ComplianceMode,
+    pub created_at: i64,
+    pub updated_at: i64,
 }
 
 /// User-defined strategy parameters and preferences
@@ -2240,6 +2484,24 @@ pub enum XLiquidityEngineError {
     ApprovalNotRequired,
     #[msg("Arithmetic overflow")]
     ArithmeticOverflow,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Fee exceeds 10000 basis points")]
+    InvalidFeeBps,
+    #[msg("No fee change is queued")]
+    NoPendingFeeChange,
+    #[msg("Fee change timelock has not passed")]
+    FeeChangeNotReady,
+    #[msg("Fee timelock can only be lengthened")]
+    TimelockDecrease,
+    #[msg("Fee timelock can be at most 30 days")]
+    TimelockTooLong,
+    #[msg("Signer is not the proposed authority")]
+    NotPendingAuthority,
+    #[msg("Protocol config already uses the current layout")]
+    AlreadyMigrated,
+    #[msg("Protocol config must be migrated with migrate_protocol_config first")]
+    NotMigrated,
 }
 
 // ============================================================================
@@ -2266,6 +2528,24 @@ pub struct InitializeProtocolConfig<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct MigrateProtocolConfig<'info> {
+    /// CHECK: still in the legacy layout, so it can't be deserialized as `ProtocolConfig`;
+    /// the discriminator, size and authority are checked by the instruction
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"protocol_config"],
+        bump
+    )]
+    pub config: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 #[instruction(position_index: u8)]
 pub struct CreateLiquidityPosition<'info> {
@@ -2562,6 +2842,47 @@ pub struct ApproveRebalance<'info> {
     pub audit_log: AccountInfo<'info>,
 }
 
+/// Governance contexts require the migrated layout: a legacy config has zero padding where
+/// the governance fields go, so it would deserialize with no timelock.
+#[derive(Accounts)]
+pub struct UpdateProtocolConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        has_one = authority @ XLiquidityEngineError::Unauthorized,
+        constraint = ProtocolConfig::is_migrated(&config.to_account_info()) @ XLiquidityEngineError::NotMigrated
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyProtocolFeeChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = ProtocolConfig::is_migrated(&config.to_account_info()) @ XLiquidityEngineError::NotMigrated
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptProtocolAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"protocol_config"],
+        bump = config.config_bump,
+        constraint = ProtocolConfig::is_migrated(&config.to_account_info()) @ XLiquidityEngineError::NotMigrated,
+        constraint = config.pending_authority == Some(new_authority.key()) @ XLiquidityEngineError::NotPendingAuthority
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+    
+    pub new_authority: Signer<'info>,
+}
+
 // ============================================================================
 // ACCOUNT SIZE CONSTANTS
 // ============================================================================
@@ -2586,7 +2907,24 @@ impl ProtocolConfig {
         1 + // audit_log_enabled
         1 + // compliance_mode
         8 + // created_at
-        8; // updated_at
+        8 + // updated_at
+        1 + 32 + // pending_authority (Option<Pubkey>)
+        1 + 2 + 2 + 8 + // pending_fee_change (Option<PendingProtocolFeeChange>)
+        8; // fee_timelock
+
+    /// Whether the account was created or migrated with the governance fields, rather than
+    /// being a legacy config whose padding merely deserializes as them
+    pub fn is_migrated(config: &AccountInfo) -> bool {
+        config.data_len() == 8 + ProtocolConfig::LEN
+    }
+}
+
+impl LegacyProtocolConfig {
+    /// `ProtocolConfig::LEN` without the governance fields
+    pub const LEN: usize = ProtocolConfig::LEN -
+        (1 + 32) - // pending_authority
+        (1 + 2 + 2 + 8) - // pending_fee_change
+        8; // fee_timelock
 }
 
 impl LiquidityPosition {
@@ -2705,3 +3043,99 @@ impl AuditLog {
         8 + // created_at
         8; // slot
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn legacy_config(authority: Pubkey, config_bump: u8) -> LegacyProtocolConfig {
+        LegacyProtocolConfig {
+            authority,
+            config_bump,
+            performance_fee_bps: 500,
+            protocol_fee_bps: 100,
+            fee_recipient: Pubkey::new_unique(),
+            x402_facilitator: None,
+            x402_min_payment: 1000,
+            x402_api_base_url: "https://api.x-liquidity-engine.com".to_string(),
+            min_rebalance_interval: 3600,
+            max_rebalance_frequency: 24,
+            default_slippage_tolerance_bps: 50,
+            max_position_size: 1_000_000_000_000,
+            max_single_trade_size: 100_000_000_000,
+            require_human_approval_threshold: 500_000_000_000,
+            default_ai_model_version: "v1.0.0".to_string(),
+            ai_model_registry: Vec::new(),
+            audit_log_enabled: true,
+            compliance_mode: ComplianceMode::Enhanced,
+            created_at: 1_700_000_000,
+            updated_at: 1_700_000_000,
+        }
+    }
+
+    /// Config account data allocated at `len`, zero-padded after the serialized fields
+    fn config_data(config: &LegacyProtocolConfig, len: usize) -> Vec<u8> {
+        let mut data = ProtocolConfig::DISCRIMINATOR.to_vec();
+        config.serialize(&mut data).unwrap();
+        data.resize(len, 0);
+        data
+    }
+
+    fn error_code(result: Result<()>) -> Option<u32> {
+        match result {
+            Err(Error::AnchorError(error)) => Some(error.error_code_number),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_governance_requires_migrated_config() {
+        let (config_key, bump) = Pubkey::find_program_address(&[b"protocol_config"], &crate::ID);
+        let authority_key = Pubkey::new_unique();
+        let legacy = legacy_config(authority_key, bump);
+
+        for (len, migrated) in [(8 + LegacyProtocolConfig::LEN, false), (8 + ProtocolConfig::LEN, true)] {
+            let mut data = config_data(&legacy, len);
+            // The legacy padding reads as no pending change and no timelock
+            let read = ProtocolConfig::try_deserialize(&mut &data[..]).unwrap();
+            assert!(read.pending_fee_change.is_none());
+            assert_eq!(read.fee_timelock, 0);
+
+            let (mut config_lamports, mut authority_lamports) = (1_000_000_000, 1_000_000_000);
+            let mut authority_data = [];
+            let system_program = anchor_lang::system_program::ID;
+            let accounts = [
+                AccountInfo::new(&config_key, false, true, &mut config_lamports, &mut data, &crate::ID, false, 0),
+                AccountInfo::new(
+                    &authority_key,
+                    true,
+                    false,
+                    &mut authority_lamports,
+                    &mut authority_data,
+                    &system_program,
+                    false,
+                    0,
+                ),
+            ];
+
+            let queue = UpdateProtocolConfig::try_accounts(
+                &crate::ID,
+                &mut &accounts[..],
+                &[],
+                &mut Default::default(),
+                &mut BTreeSet::new(),
+            );
+            let apply = ApplyProtocolFeeChange::try_accounts(
+                &crate::ID,
+                &mut &accounts[..1],
+                &[],
+                &mut Default::default(),
+                &mut BTreeSet::new(),
+            );
+            let expected = (!migrated).then(|| u32::from(XLiquidityEngineError::NotMigrated));
+            assert_eq!(error_code(queue.map(|_| ())), expected);
+            assert_eq!(error_code(apply.map(|_| ())), expected);
+        }
+    }
+}
@@ -967,6 +967,108 @@ describe("flow", () => {
         });
     });
 
+    describe("protocol governance", () => {
+        it("Queues protocol fee changes behind the timelock", async () => {
+            const before = await program.account.protocolConfig.fetch(
+                protocolConfig
+            );
+
+            await program.methods
+                .queueProtocolFeeChange(800, 150)
+                .accounts({ authority: authority.publicKey })
+                .signers([authority])
+                .rpc();
+
+            const queued = await program.account.protocolConfig.fetch(
+                protocolConfig
+            );
+            expect(queued.performanceFeeBps).to.equal(before.performanceFeeBps);
+            expect(queued.pendingFeeChange.performanceFeeBps).to.equal(800);
+            expect(queued.pendingFeeChange.protocolFeeBps).to.equal(150);
+
+            try {
+                await program.methods.applyProtocolFeeChange().rpc();
+                expect.fail("Should have failed");
+            } catch (err) {
+                expect(err.toString()).to.include("FeeChangeNotReady");
+            }
+
+            await program.methods
+                .cancelProtocolFeeChange()
+                .accounts({ authority: authority.publicKey })
+                .signers([authority])
+                .rpc();
+            const cancelled = await program.account.protocolConfig.fetch(
+                protocolConfig
+            );
+            expect(cancelled.pendingFeeChange).to.be.null;
+        });
+
+        it("Transfers the protocol authority in two steps", async () => {
+            const newAuthority = Keypair.generate();
+
+            await program.methods
+                .proposeProtocolAuthority(newAuthority.publicKey)
+                .accounts({ authority: authority.publicKey })
+                .signers([authority])
+                .rpc();
+
+            try {
+                await program.methods
+                    .acceptProtocolAuthority()
+                    .accounts({ newAuthority: owner.publicKey })
+                    .signers([owner])
+                    .rpc();
+                expect.fail("Should have failed");
+            } catch (err) {
+                expect(err.toString()).to.include("NotPendingAuthority");
+            }
+
+            await program.methods
+                .acceptProtocolAuthority()
+                .accounts({ newAuthority: newAuthority.publicKey })
+                .signers([newAuthority])
+                .rpc();
+            let configAccount = await program.account.protocolConfig.fetch(
+                protocolConfig
+            );
+            expect(configAccount.authority.toString()).to.equal(
+                newAuthority.publicKey.toString()
+            );
+
+            // Hand it back so later tests keep the original authority
+            await program.methods
+                .proposeProtocolAuthority(authority.publicKey)
+                .accounts({ authority: newAuthority.publicKey })
+                .signers([newAuthority])
+                .rpc();
+            await program.methods
+                .acceptProtocolAuthority()
+                .accounts({ newAuthority: authority.publicKey })
+                .signers([authority])
+                .rpc();
+            configAccount = await program.account.protocolConfig.fetch(
+                protocolConfig
+            );
+            expect(configAccount.authority.toString()).to.equal(
+                authority.publicKey.toString()
+            );
+        });
+
+        it("Only migrates configs in the legacy layout", async () => {
+            try {
+                await program.methods
+                    .migrateProtocolConfig()
+                    .accounts({ authority: authority.publicKey })
+                    .signers([authority])
+                    .rpc();
+                expect.fail("Should have failed");
+            } catch (err) {
+                expect(err.toString()).to.include("AlreadyMigrated");
+            }
+        });
+    });
+
     describe("approve_rebalance", () => {
         let approvalPosition: PublicKey;
         let approvalPositionIndex: number;
@@ -0,0 +1,231 @@
+use crate::detector::PatternId;
+use crate::store::{AlertQuery, AlertStatus, AlertStore};
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+pub type SharedStore = Arc<Mutex<AlertStore>>;
+
+/// Local REST API over the alert store:
+///
+/// - `GET /warnings?status=&severity=&patternId=&limit=` recent warnings, newest first
+/// - `GET /warnings/{id}` one warning with its full transaction
+/// - `POST /warnings/{id}/acknowledge`, `POST /warnings/{id}/resolve` with an optional `{"note": ...}`
+/// - `GET /suppressions`, `POST /suppressions`, `DELETE /suppressions/{id}`
+pub fn router(store: SharedStore) -> Router {
+    Router::new()
+        .route("/warnings", get(list_warnings))
+        .route("/warnings/:id", get(get_warning))
+        .route("/warnings/:id/acknowledge", post(acknowledge))
+        .route("/warnings/:id/resolve", post(resolve))
+        .route("/suppressions", get(list_suppressions).post(add_suppression))
+        .route("/suppressions/:id", delete(remove_suppression))
+        .with_state(store)
+}
+
+pub async fn serve(address: SocketAddr, store: SharedStore) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to bind API on {}: {}", address, e))?;
+    axum::serve(listener, router(store)).await?;
+    Ok(())
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+    }
+}
+
+fn not_found(what: &str, id: i64) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, format!("{} {} not found", what, id))
+}
+
+fn lock(store: &SharedStore) -> std::sync::MutexGuard<'_, AlertStore> {
+    // A panic while holding the lock cannot leave SQLite in a partial state
+    store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+async fn list_warnings(
+    State(store): State<SharedStore>,
+    Query(query): Query<AlertQuery>,
+) -> Result<Json<Value>, ApiError> {
+    Ok(Json(json!({ "warnings": lock(&store).alerts(&query)? })))
+}
+
+async fn get_warning(State(store): State<SharedStore>, Path(id): Path<i64>) -> Result<Json<Value>, ApiError> {
+    let store = lock(&store);
+    let warning = store.alert(id)?.ok_or_else(|| not_found("Warning", id))?;
+    let transaction = store.transaction(&warning.signature)?;
+    Ok(Json(json!({ "warning": warning, "transaction": transaction })))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StatusUpdate {
+    note: Option<String>,
+}
+
+async fn acknowledge(
+    State(store): State<SharedStore>,
+    Path(id): Path<i64>,
+    body: Option<Json<StatusUpdate>>,
+) -> Result<Json<Value>, ApiError> {
+    update_status(&store, id, AlertStatus::Acknowledged, body)
+}
+
+async fn resolve(
+    State(store): State<SharedStore>,
+    Path(id): Path<i64>,
+    body: Option<Json<StatusUpdate>>,
+) -> Result<Json<Value>, ApiError> {
+    update_status(&store, id, AlertStatus::Resolved, body)
+}
+
+fn update_status(
+    store: &SharedStore,
+    id: i64,
+    status: AlertStatus,
+    body: Option<Json<StatusUpdate>>,
+) -> Result<Json<Value>, ApiError> {
+    let note = body.and_then(|Json(update)| update.note);
+    let warning = lock(store)
+        .set_status(id, status, note.as_deref())?
+        .ok_or_else(|| not_found("Warning", id))?;
+    Ok(Json(json!({ "warning": warning })))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NewSuppression {
+    pattern_id: Option<PatternId>,
+    affected_account: Option<String>,
+    reason: String,
+    duration_seconds: u64,
+}
+
+async fn list_suppressions(State(store): State<SharedStore>) -> Result<Json<Value>, ApiError> {
+    Ok(Json(json!({ "suppressions": lock(&store).suppressions(true)? })))
+}
+
+async fn add_suppression(
+    State(store): State<SharedStore>,
+    Json(request): Json<NewSuppression>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    if request.duration_seconds == 0 {
+        return Err(ApiError(StatusCode::BAD_REQUEST, "durationSeconds must be positive".to_string()));
+    }
+    let suppression = lock(&store)
+        .add_suppression(
+            request.pattern_id,
+            request.affected_account.as_deref(),
+            &request.reason,
+            request.duration_seconds,
+        )
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok((StatusCode::CREATED, Json(json!({ "suppression": suppression }))))
+}
+
+async fn remove_suppression(State(store): State<SharedStore>, Path(id): Path<i64>) -> Result<StatusCode, ApiError> {
+    if lock(&store).remove_suppression(id)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found("Suppression", id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detector::{Severity, Warning};
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use solana_sdk::pubkey::Pubkey;
+    use tower::ServiceExt;
+
+    async fn call(store: &SharedStore, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(body.map_or(Body::empty(), |body| Body::from(body.to_string())))
+            .unwrap();
+        let response = router(store.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_acknowledge_and_suppress() {
+        let store: SharedStore = Arc::new(Mutex::new(AlertStore::open_in_memory().unwrap()));
+        let tx = crate::fixture::read_transaction(std::path::Path::new("tests/fixtures/p104_close_account.json")).unwrap();
+        let account = Pubkey::new_unique();
+        lock(&store)
+            .record(
+                &Pubkey::new_unique(),
+                "sig",
+                1,
+                &tx,
+                vec![Warning {
+                    pattern_id: PatternId::P104DangerousClose,
+                    severity: Severity::Warning,
+                    message: "test".to_string(),
+                    affected_account: Some(account),
+                }],
+            )
+            .unwrap();
+
+        let (status, body) = call(&store, "GET", "/warnings?status=open&severity=warning", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let id = body["warnings"][0]["id"].as_i64().unwrap();
+        assert_eq!(body["warnings"][0]["patternId"], "P-104");
+
+        let (_, body) = call(&store, "GET", &format!("/warnings/{}", id), None).await;
+        assert!(body["transaction"]["transaction"].is_object());
+
+        let (status, body) = call(
+            &store,
+            "POST",
+            &format!("/warnings/{}/resolve", id),
+            Some(json!({ "note": "closed by ops" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["warning"]["status"], "resolved");
+        assert_eq!(body["warning"]["note"], "closed by ops");
+        assert_eq!(call(&store, "POST", "/warnings/999/acknowledge", None).await.0, StatusCode::NOT_FOUND);
+
+        let (status, body) = call(
+            &store,
+            "POST",
+            "/suppressions",
+            Some(json!({ "patternId": "P-104", "affectedAccount": account.to_string(), "reason": "migration", "durationSeconds": 600 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let suppression_id = body["suppression"]["id"].as_i64().unwrap();
+        let (_, body) = call(&store, "GET", "/suppressions", None).await;
+        assert_eq!(body["suppressions"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            call(&store, "DELETE", &format!("/suppressions/{}", suppression_id), None).await.0,
+            StatusCode::NO_CONTENT
+        );
+    }
+}
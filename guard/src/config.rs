@@ -0,0 +1,75 @@
+use crate::fabrknt::FabrkntRules;
+use crate::idl::{Idl, IdlRegistry};
+use crate::nonce::NonceRules;
+use crate::rules::{self, IdlRule};
+use crate::treasury::TreasuryRules;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Detector configuration, loaded from the file passed with `--config`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GuardConfig {
+    /// Rules for Fabrknt's own programs
+    pub fabrknt: FabrkntRules,
+    /// Thresholds for durable nonce transactions
+    pub nonce: NonceRules,
+    /// Treasury accounts and outflow limits
+    pub treasury: TreasuryRules,
+    /// Anchor IDLs used to decode third-party program instructions
+    pub idls: Vec<IdlSource>,
+    /// Rules matched against IDL-decoded instructions
+    pub rules: Vec<IdlRule>,
+    #[serde(skip)]
+    pub idl_registry: IdlRegistry,
+}
+
+/// An IDL file, relative to the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdlSource {
+    pub path: PathBuf,
+    /// Program ID, required when the IDL does not record its address
+    pub program_id: Option<String>,
+}
+
+impl GuardConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config: Self = serde_json::from_str(
+            &fs::read_to_string(path)
+                .with_context(|| format!("Failed to read config {}", path.display()))?,
+        )
+        .with_context(|| format!("Invalid config {}", path.display()))?;
+
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        for source in &config.idls {
+            let idl = Idl::load(&base_dir.join(&source.path))?;
+            let program_id = source
+                .program_id
+                .clone()
+                .or_else(|| idl.program_id.clone())
+                .ok_or_else(|| {
+                    anyhow!("IDL {} has no address; set programId in the config", source.path.display())
+                })?;
+            config.idl_registry.insert(program_id, idl);
+        }
+        if let Some(store) = config.treasury.first_seen_store.take() {
+            config.treasury.first_seen_store = Some(base_dir.join(store));
+        }
+        rules::validate(&config.rules, &config.idl_registry)
+            .with_context(|| format!("Invalid rules in {}", path.display()))?;
+
+        Ok(config)
+    }
+
+    /// Loads the config if the file exists, otherwise returns the defaults
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if path.exists() {
+            Self::load(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
@@ -1,31 +1,131 @@
+use crate::config::GuardConfig;
+use crate::fabrknt::{self, TransactionContext};
+use crate::nonce;
+use crate::rules;
+use crate::token_extensions;
+use crate::treasury;
 use anyhow::Result;
 use colored::Colorize;
+use serde::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
 use solana_transaction_status::{
-    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction,
-    UiMessage, UiParsedInstruction,
+    option_serializer::OptionSerializer,
+    parse_accounts::{ParsedAccount, ParsedAccountSource},
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInnerInstructions,
+    UiInstruction, UiMessage, UiParsedInstruction,
 };
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-/// Warning severity levels
-#[derive(Debug, Clone, PartialEq)]
+/// Warning severity levels, ordered from least to most severe
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
 pub enum Severity {
-    Critical,
-    Warning,
     Alert,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    /// Process exit code used when this is the highest severity found in CI gate mode.
+    ///
+    /// Codes start at 10: 1 is left for runtime errors and 2 for clap's usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Severity::Alert => 10,
+            Severity::Warning => 11,
+            Severity::Critical => 12,
+        }
+    }
+
+    /// Icon and label used in terminal output
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Critical => "🚨 CRITICAL",
+            Severity::Warning => "⚠️  WARNING",
+            Severity::Alert => "⚠️  ALERT",
+        }
+    }
 }
 
 /// Detection pattern IDs from the spec
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PatternId {
+    #[serde(rename = "P-101")]
     P101MintKill,
+    #[serde(rename = "P-102")]
     P102FreezeKill,
+    #[serde(rename = "P-103")]
     P103SignerMismatch,
+    #[serde(rename = "P-104")]
     P104DangerousClose,
+    #[serde(rename = "P-105")]
+    P105AuthorityDrift,
+    #[serde(rename = "P-106")]
+    P106LookupTableTarget,
+    #[serde(rename = "P-107")]
+    P107DurableNonce,
+    #[serde(rename = "P-108")]
+    P108TreasuryOutflow,
+    #[serde(rename = "P-301")]
+    P301GatewayFeeChange,
+    #[serde(rename = "P-302")]
+    P302UnlistedApprover,
+    #[serde(rename = "P-303")]
+    P303PoolMatured,
+    #[serde(rename = "P-304")]
+    P304UnbackedLiquidity,
+    #[serde(rename = "P-305")]
+    P305LargeLiquidation,
+    #[serde(rename = "P-401")]
+    P401IdlRule,
+}
+
+impl PatternId {
+    /// Spec ID, e.g. `P-101`
+    pub fn code(&self) -> &'static str {
+        match self {
+            PatternId::P101MintKill => "P-101",
+            PatternId::P102FreezeKill => "P-102",
+            PatternId::P103SignerMismatch => "P-103",
+            PatternId::P104DangerousClose => "P-104",
+            PatternId::P105AuthorityDrift => "P-105",
+            PatternId::P106LookupTableTarget => "P-106",
+            PatternId::P107DurableNonce => "P-107",
+            PatternId::P108TreasuryOutflow => "P-108",
+            PatternId::P301GatewayFeeChange => "P-301",
+            PatternId::P302UnlistedApprover => "P-302",
+            PatternId::P303PoolMatured => "P-303",
+            PatternId::P304UnbackedLiquidity => "P-304",
+            PatternId::P305LargeLiquidation => "P-305",
+            PatternId::P401IdlRule => "P-401",
+        }
+    }
+
+    /// Human-readable pattern name, including the spec ID
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatternId::P101MintKill => "Mint Authority Kill (P-101)",
+            PatternId::P102FreezeKill => "Freeze Authority Kill (P-102)",
+            PatternId::P103SignerMismatch => "Signer Mismatch (P-103)",
+            PatternId::P104DangerousClose => "Dangerous Account Close (P-104)",
+            PatternId::P105AuthorityDrift => "Authority Drift (P-105)",
+            PatternId::P106LookupTableTarget => "Lookup Table Target (P-106)",
+            PatternId::P107DurableNonce => "Durable Nonce (P-107)",
+            PatternId::P108TreasuryOutflow => "Treasury Outflow (P-108)",
+            PatternId::P301GatewayFeeChange => "Gateway Fee Change (P-301)",
+            PatternId::P302UnlistedApprover => "Unlisted Rebalance Approver (P-302)",
+            PatternId::P303PoolMatured => "Yield Pool Matured (P-303)",
+            PatternId::P304UnbackedLiquidity => "Unbacked Liquidity (P-304)",
+            PatternId::P305LargeLiquidation => "Large Liquidation (P-305)",
+            PatternId::P401IdlRule => "IDL Rule Match (P-401)",
+        }
+    }
 }
 
 /// A warning detected in a transaction
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Warning {
     pub pattern_id: PatternId,
     pub severity: Severity,
@@ -37,19 +137,11 @@ impl Warning {
     /// Format the warning for terminal output
     pub fn format_terminal(&self) -> String {
         let severity_icon = match self.severity {
-            Severity::Critical => "🚨 CRITICAL".red().bold(),
-            Severity::Warning => "⚠️  WARNING".yellow().bold(),
-            Severity::Alert => "⚠️  ALERT".yellow().bold(),
+            Severity::Critical => self.severity.label().red().bold(),
+            Severity::Warning | Severity::Alert => self.severity.label().yellow().bold(),
         };
 
-        let pattern_name = match self.pattern_id {
-            PatternId::P101MintKill => "Mint Authority Kill (P-101)",
-            PatternId::P102FreezeKill => "Freeze Authority Kill (P-102)",
-            PatternId::P103SignerMismatch => "Signer Mismatch (P-103)",
-            PatternId::P104DangerousClose => "Dangerous Account Close (P-104)",
-        };
-
-        let mut output = format!("{}: {}\n", severity_icon, pattern_name.bold());
+        let mut output = format!("{}: {}\n", severity_icon, self.pattern_id.name().bold());
         output.push_str(&format!("  {}\n", self.message));
         if let Some(account) = self.affected_account {
             output.push_str(&format!("  Affected Account: {}\n", account.to_string().cyan()));
@@ -59,9 +151,17 @@ impl Warning {
     }
 }
 
-/// Analyzes a transaction and detects dangerous patterns
+/// Account data fetched alongside a transaction, keyed by address
+pub type AccountStates = HashMap<Pubkey, Vec<u8>>;
+
+/// Analyzes a transaction and detects dangerous patterns.
+///
+/// `account_states` holds the data of accounts the transaction touches, where known,
+/// so checks can look past the instruction itself.
 pub fn analyze_transaction(
     tx: &EncodedConfirmedTransactionWithStatusMeta,
+    config: &GuardConfig,
+    account_states: &AccountStates,
 ) -> Result<Vec<Warning>> {
     let mut warnings = Vec::new();
 
@@ -83,46 +183,155 @@ pub fn analyze_transaction(
         }
     };
 
-    // Extract signers from account keys
-    let signers: HashSet<Pubkey> = account_keys
+    let accounts = TransactionAccounts::resolve(account_keys, tx);
+    let signers = &accounts.signers;
+
+    let inner: Vec<&UiInstruction> = inner_instructions(tx)
         .iter()
-        .filter_map(|key| {
-            // Check if this account is a signer
-            if key.signer {
-                key.pubkey.parse::<Pubkey>().ok()
-            } else {
-                None
-            }
-        })
+        .flat_map(|inner| inner.instructions.iter())
         .collect();
+    let context = TransactionContext {
+        has_token_transfer: has_token_transfer(instructions, &inner),
+    };
 
     // Analyze each instruction
     for instruction in instructions {
-        if let UiInstruction::Parsed(parsed_ix) = instruction {
-            match parsed_ix {
-                UiParsedInstruction::Parsed(parsed) => {
-                    // Check for SPL Token program
-                    if parsed.program == "spl-token" || parsed.program == "spl-token-2022" {
-                        // Analyze based on instruction type
-                        match parsed.parsed["type"].as_str() {
-                            Some("setAuthority") => {
-                                warnings.extend(analyze_set_authority(&parsed, &signers)?);
-                            }
-                            Some("closeAccount") => {
-                                warnings.extend(analyze_close_account(&parsed)?);
-                            }
-                            _ => {}
-                        }
+        match instruction {
+            // Check for SPL Token program
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed))
+                if parsed.program == "spl-token" || parsed.program == "spl-token-2022" =>
+            {
+                // Analyze based on instruction type
+                match parsed.parsed["type"].as_str() {
+                    Some("setAuthority") => {
+                        warnings.extend(analyze_set_authority(parsed, signers)?);
+                    }
+                    Some("closeAccount") => {
+                        warnings.extend(analyze_close_account(parsed, account_states)?);
                     }
+                    _ => {}
                 }
-                _ => {}
             }
+            UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(partial)) => {
+                // Programs the RPC cannot parse, such as Fabrknt's own Anchor programs
+                warnings.extend(fabrknt::analyze_instruction(partial, &context, &config.fabrknt));
+                if let Some(decoded) = config.idl_registry.decode(partial) {
+                    warnings.extend(rules::evaluate(&decoded, signers, &config.rules));
+                }
+            }
+            _ => {}
         }
     }
 
+    // Fabrknt and IDL instructions run through CPI too, e.g. when a multisig executes an
+    // admin change. IDL rules still check signers against the outer transaction's.
+    for instruction in &inner {
+        if let UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(partial)) = instruction {
+            warnings.extend(fabrknt::analyze_instruction(partial, &context, &config.fabrknt));
+            if let Some(decoded) = config.idl_registry.decode(partial) {
+                warnings.extend(rules::evaluate(&decoded, signers, &config.rules));
+            }
+        }
+    }
+
+    warnings.extend(nonce::analyze(instructions, &inner, signers, &config.nonce));
+    warnings.extend(treasury::analyze(&treasury::outflows(tx, &config.treasury), &config.treasury));
+
+    let lookup_table_warnings = analyze_lookup_table_targets(&warnings, &accounts);
+    warnings.extend(lookup_table_warnings);
+
     Ok(warnings)
 }
 
+/// Accounts referenced by a transaction, including v0 address lookup table loads
+struct TransactionAccounts {
+    signers: HashSet<Pubkey>,
+    /// Accounts loaded from address lookup tables, and whether each was loaded writable
+    lookup_table: HashMap<Pubkey, bool>,
+}
+
+impl TransactionAccounts {
+    /// Merges the message's account keys with `meta.loadedAddresses`.
+    ///
+    /// jsonParsed responses already list lookup table accounts in the message with
+    /// `source: lookupTable`, but saved or older responses may only carry them in the meta.
+    fn resolve(account_keys: &[ParsedAccount], tx: &EncodedConfirmedTransactionWithStatusMeta) -> Self {
+        let signers = account_keys
+            .iter()
+            .filter(|key| key.signer)
+            .filter_map(|key| key.pubkey.parse::<Pubkey>().ok())
+            .collect();
+
+        let mut lookup_table: HashMap<Pubkey, bool> = account_keys
+            .iter()
+            .filter(|key| key.source == Some(ParsedAccountSource::LookupTable))
+            .filter_map(|key| Some((key.pubkey.parse::<Pubkey>().ok()?, key.writable)))
+            .collect();
+        if let Some(OptionSerializer::Some(loaded)) =
+            tx.transaction.meta.as_ref().map(|meta| &meta.loaded_addresses)
+        {
+            let loaded_writable = loaded.writable.iter().map(|key| (key, true));
+            let loaded_readonly = loaded.readonly.iter().map(|key| (key, false));
+            for (key, writable) in loaded_writable.chain(loaded_readonly) {
+                if let Ok(key) = key.parse::<Pubkey>() {
+                    lookup_table.entry(key).or_insert(writable);
+                }
+            }
+        }
+
+        Self { signers, lookup_table }
+    }
+}
+
+/// P-106: Lookup Table Target
+///
+/// Flags each account targeted by another warning that the transaction loaded from an
+/// address lookup table instead of listing it, which hides the target from most wallet previews.
+fn analyze_lookup_table_targets(warnings: &[Warning], accounts: &TransactionAccounts) -> Vec<Warning> {
+    let mut flagged = HashSet::new();
+    warnings
+        .iter()
+        .filter_map(|warning| {
+            let account = warning.affected_account?;
+            let writable = *accounts.lookup_table.get(&account)?;
+            flagged.insert(account).then(|| Warning {
+                pattern_id: PatternId::P106LookupTableTarget,
+                severity: Severity::Warning,
+                message: format!(
+                    "Target of {} was loaded {} from an address lookup table rather than listed in the transaction. Drainers use lookup tables to obscure the accounts they touch.",
+                    warning.pattern_id.code(),
+                    if writable { "writable" } else { "read-only" }
+                ),
+                affected_account: Some(account),
+            })
+        })
+        .collect()
+}
+
+/// Instructions the transaction ran through CPI, grouped by top-level instruction
+fn inner_instructions(tx: &EncodedConfirmedTransactionWithStatusMeta) -> &[UiInnerInstructions] {
+    match tx.transaction.meta.as_ref().map(|meta| &meta.inner_instructions) {
+        Some(OptionSerializer::Some(inner)) => inner.as_slice(),
+        _ => &[],
+    }
+}
+
+/// Whether an SPL token transfer ran anywhere in the transaction, including CPIs
+fn has_token_transfer(instructions: &[UiInstruction], inner: &[&UiInstruction]) -> bool {
+    let is_transfer = |instruction: &UiInstruction| match instruction {
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => {
+            (parsed.program == "spl-token" || parsed.program == "spl-token-2022")
+                && matches!(
+                    parsed.parsed["type"].as_str(),
+                    Some("transfer") | Some("transferChecked")
+                )
+        }
+        _ => false,
+    };
+
+    instructions.iter().any(is_transfer) || inner.iter().copied().any(is_transfer)
+}
+
 /// P-101: Mint Authority Kill
 /// P-102: Freeze Authority Kill
 /// P-103: Signer Mismatch
@@ -217,8 +426,12 @@ fn analyze_set_authority(
 }
 
 /// P-104: Dangerous Close Account
+///
+/// When the account's data is known, Token-2022 accounts are checked for balances and
+/// extension state the close would strand. Otherwise every close gets a generic warning.
 fn analyze_close_account(
     parsed: &solana_transaction_status::parse_instruction::ParsedInstruction,
+    account_states: &AccountStates,
 ) -> Result<Vec<Warning>> {
     let mut warnings = Vec::new();
 
@@ -227,9 +440,23 @@ fn analyze_close_account(
         .as_str()
         .and_then(|s| s.parse::<Pubkey>().ok());
 
-    // Note: In a real implementation, we would need to fetch the account data
-    // to check if it has remaining balance. For now, we'll issue a warning
-    // for all close account instructions as they are potentially dangerous.
+    let data = account
+        .filter(|_| parsed.program == "spl-token-2022")
+        .and_then(|account| account_states.get(&account));
+    if let Some(data) = data {
+        for risk in token_extensions::close_risks(data) {
+            warnings.push(Warning {
+                pattern_id: PatternId::P104DangerousClose,
+                severity: risk.severity,
+                message: risk.message,
+                affected_account: account,
+            });
+        }
+        if !warnings.is_empty() {
+            return Ok(warnings);
+        }
+    }
+
     warnings.push(Warning {
         pattern_id: PatternId::P104DangerousClose,
         severity: Severity::Warning,
@@ -240,6 +467,30 @@ fn analyze_close_account(
     Ok(warnings)
 }
 
+/// Token-2022 accounts closed by a transaction, whose data should be fetched before analysis
+pub fn token_2022_close_targets(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Vec<Pubkey> {
+    let EncodedTransaction::Json(ui_transaction) = &tx.transaction.transaction else {
+        return Vec::new();
+    };
+    let UiMessage::Parsed(message) = &ui_transaction.message else {
+        return Vec::new();
+    };
+
+    message
+        .instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed))
+                if parsed.program == "spl-token-2022"
+                    && parsed.parsed["type"].as_str() == Some("closeAccount") =>
+            {
+                parsed.parsed["info"]["account"].as_str()?.parse::<Pubkey>().ok()
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,4 +508,40 @@ mod tests {
         assert!(formatted.contains("CRITICAL"));
         assert!(formatted.contains("Test warning message"));
     }
+
+    #[test]
+    fn test_severity_ordering() {
+        assert!(Severity::Critical > Severity::Warning);
+        assert!(Severity::Warning > Severity::Alert);
+        assert_eq!(
+            [Severity::Warning, Severity::Critical, Severity::Alert].iter().max(),
+            Some(&Severity::Critical)
+        );
+        assert_eq!(
+            [Severity::Alert, Severity::Warning, Severity::Critical].map(|severity| severity.exit_code()),
+            [10, 11, 12]
+        );
+    }
+
+    #[test]
+    fn test_lookup_table_targets() {
+        let listed = Pubkey::new_unique();
+        let loaded = Pubkey::new_unique();
+        let accounts = TransactionAccounts {
+            signers: HashSet::new(),
+            lookup_table: HashMap::from([(loaded, true)]),
+        };
+        let close = |account| Warning {
+            pattern_id: PatternId::P104DangerousClose,
+            severity: Severity::Warning,
+            message: String::new(),
+            affected_account: Some(account),
+        };
+
+        let warnings = analyze_lookup_table_targets(&[close(listed), close(loaded), close(loaded)], &accounts);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].pattern_id, PatternId::P106LookupTableTarget);
+        assert_eq!(warnings[0].affected_account, Some(loaded));
+        assert!(warnings[0].message.contains("P-104"));
+    }
 }
@@ -0,0 +1,408 @@
+use crate::detector::{PatternId, Severity, Warning};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    account::Account, bpf_loader_upgradeable, bpf_loader_upgradeable::UpgradeableLoaderState,
+    program_utils::limited_deserialize, pubkey::Pubkey,
+};
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account as TokenAccount, Mint},
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Maximum accounts per `getMultipleAccounts` request
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// What kind of account a snapshot describes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AccountKind {
+    Mint,
+    TokenAccount,
+    Program,
+    Other,
+    Missing,
+}
+
+/// Authority-relevant state of a single account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSnapshot {
+    pub address: String,
+    pub kind: AccountKind,
+    /// Program that owns the account
+    pub owner: Option<String>,
+    pub mint_authority: Option<String>,
+    pub freeze_authority: Option<String>,
+    /// Owner field of an SPL token account
+    pub token_owner: Option<String>,
+    pub close_authority: Option<String>,
+    /// ProgramData account of an upgradeable program
+    pub programdata_address: Option<String>,
+    pub upgrade_authority: Option<String>,
+}
+
+/// Baseline file written by `guard snapshot`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Baseline {
+    pub created_at: i64,
+    pub accounts: Vec<AccountSnapshot>,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> Result<Self> {
+        serde_json::from_str(
+            &fs::read_to_string(path)
+                .with_context(|| format!("Failed to read baseline {}", path.display()))?,
+        )
+        .with_context(|| format!("Invalid baseline {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write baseline {}", path.display()))
+    }
+
+    /// Addresses covered by the baseline
+    pub fn addresses(&self) -> Result<Vec<Pubkey>> {
+        self.accounts
+            .iter()
+            .map(|account| {
+                account
+                    .address
+                    .parse::<Pubkey>()
+                    .map_err(|e| anyhow::anyhow!("Invalid address {} in baseline: {}", account.address, e))
+            })
+            .collect()
+    }
+}
+
+/// Fetches and snapshots the given accounts.
+///
+/// Upgradeable programs are followed to their ProgramData account so the
+/// upgrade authority is captured.
+pub fn snapshot_accounts(rpc_client: &RpcClient, addresses: &[Pubkey]) -> Result<Vec<AccountSnapshot>> {
+    let accounts = get_multiple_accounts(rpc_client, addresses)?;
+    let mut snapshots: Vec<AccountSnapshot> = addresses
+        .iter()
+        .zip(accounts.iter())
+        .map(|(address, account)| snapshot_account(address, account.as_ref()))
+        .collect();
+
+    let programdata_addresses = programdata_addresses(&snapshots);
+    if programdata_addresses.is_empty() {
+        return Ok(snapshots);
+    }
+
+    let programdata_accounts = get_multiple_accounts(rpc_client, &programdata_addresses)?;
+    for (programdata_address, account) in programdata_addresses.iter().zip(programdata_accounts) {
+        let upgrade_authority = account.as_ref().and_then(upgrade_authority);
+        for snapshot in snapshots.iter_mut() {
+            if snapshot.programdata_address.as_deref() == Some(&programdata_address.to_string()) {
+                snapshot.upgrade_authority = upgrade_authority.clone();
+            }
+        }
+    }
+
+    Ok(snapshots)
+}
+
+/// ProgramData addresses of any upgradeable programs in the snapshots
+pub fn programdata_addresses(snapshots: &[AccountSnapshot]) -> Vec<Pubkey> {
+    snapshots
+        .iter()
+        .filter_map(|snapshot| snapshot.programdata_address.as_ref()?.parse().ok())
+        .collect()
+}
+
+fn get_multiple_accounts(rpc_client: &RpcClient, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        accounts.extend(
+            rpc_client
+                .get_multiple_accounts(chunk)
+                .map_err(|e| anyhow::anyhow!("Failed to fetch accounts: {}", e))?,
+        );
+    }
+    Ok(accounts)
+}
+
+/// Snapshots a single account from its raw data
+pub fn snapshot_account(address: &Pubkey, account: Option<&Account>) -> AccountSnapshot {
+    let mut snapshot = AccountSnapshot {
+        address: address.to_string(),
+        kind: AccountKind::Missing,
+        owner: None,
+        mint_authority: None,
+        freeze_authority: None,
+        token_owner: None,
+        close_authority: None,
+        programdata_address: None,
+        upgrade_authority: None,
+    };
+
+    let Some(account) = account else {
+        return snapshot;
+    };
+    snapshot.kind = AccountKind::Other;
+    snapshot.owner = Some(account.owner.to_string());
+
+    if account.owner == spl_token::id() || account.owner == spl_token_2022::id() {
+        // Token accounts are tried first: a Token-2022 mint with extensions is
+        // rejected by the account parser because of its account type byte.
+        if let Ok(token_account) = StateWithExtensions::<TokenAccount>::unpack(&account.data) {
+            snapshot.kind = AccountKind::TokenAccount;
+            snapshot.token_owner = Some(token_account.base.owner.to_string());
+            snapshot.close_authority =
+                Option::<Pubkey>::from(token_account.base.close_authority).map(|key| key.to_string());
+        } else if let Ok(mint) = StateWithExtensions::<Mint>::unpack(&account.data) {
+            snapshot.kind = AccountKind::Mint;
+            snapshot.mint_authority =
+                Option::<Pubkey>::from(mint.base.mint_authority).map(|key| key.to_string());
+            snapshot.freeze_authority =
+                Option::<Pubkey>::from(mint.base.freeze_authority).map(|key| key.to_string());
+        }
+    } else if account.owner == bpf_loader_upgradeable::id() {
+        if let Ok(UpgradeableLoaderState::Program { programdata_address }) =
+            limited_deserialize(&account.data)
+        {
+            snapshot.kind = AccountKind::Program;
+            snapshot.programdata_address = Some(programdata_address.to_string());
+        }
+    }
+
+    snapshot
+}
+
+/// Upgrade authority stored in a ProgramData account
+fn upgrade_authority(account: &Account) -> Option<String> {
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    match limited_deserialize(account.data.get(..metadata_len)?) {
+        Ok(UpgradeableLoaderState::ProgramData {
+            upgrade_authority_address,
+            ..
+        }) => upgrade_authority_address.map(|key| key.to_string()),
+        _ => None,
+    }
+}
+
+/// Compares a current snapshot against its baseline and returns one warning per changed field
+pub fn diff(baseline: &AccountSnapshot, current: &AccountSnapshot) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let affected_account = current.address.parse::<Pubkey>().ok();
+
+    if baseline.kind != current.kind {
+        warnings.push(Warning {
+            pattern_id: PatternId::P105AuthorityDrift,
+            severity: Severity::Critical,
+            message: format!(
+                "Account type changed from {:?} to {:?}. The account may have been closed or reassigned.",
+                baseline.kind, current.kind
+            ),
+            affected_account,
+        });
+        return warnings;
+    }
+
+    let fields = [
+        ("Owner program", &baseline.owner, &current.owner, Severity::Critical),
+        ("Mint authority", &baseline.mint_authority, &current.mint_authority, Severity::Critical),
+        ("Freeze authority", &baseline.freeze_authority, &current.freeze_authority, Severity::Warning),
+        ("Token account owner", &baseline.token_owner, &current.token_owner, Severity::Critical),
+        ("Close authority", &baseline.close_authority, &current.close_authority, Severity::Warning),
+        ("Upgrade authority", &baseline.upgrade_authority, &current.upgrade_authority, Severity::Critical),
+    ];
+
+    for (label, before, after, severity) in fields {
+        if before == after {
+            continue;
+        }
+        warnings.push(Warning {
+            pattern_id: PatternId::P105AuthorityDrift,
+            severity,
+            message: format!(
+                "{} changed from {} to {}.",
+                label,
+                before.as_deref().unwrap_or("none"),
+                after.as_deref().unwrap_or("none (revoked)")
+            ),
+            affected_account,
+        });
+    }
+
+    warnings
+}
+
+/// A change in an account's drift relative to the baseline
+pub struct DriftChange {
+    pub address: String,
+    /// Drift from the baseline; empty when the account returned to its baseline state
+    pub warnings: Vec<Warning>,
+}
+
+/// Tracks the last observed state of each baseline account so every change is reported once
+pub struct DriftTracker {
+    baseline: HashMap<String, AccountSnapshot>,
+    last_seen: HashMap<String, AccountSnapshot>,
+}
+
+impl DriftTracker {
+    pub fn new(baseline: &Baseline) -> Self {
+        let baseline: HashMap<String, AccountSnapshot> = baseline
+            .accounts
+            .iter()
+            .map(|account| (account.address.clone(), account.clone()))
+            .collect();
+
+        Self {
+            last_seen: baseline.clone(),
+            baseline,
+        }
+    }
+
+    /// Records the current snapshots and returns the accounts whose state changed since the last update
+    pub fn update(&mut self, current: Vec<AccountSnapshot>) -> Vec<DriftChange> {
+        let mut changes = Vec::new();
+        for snapshot in current {
+            let Some(baseline) = self.baseline.get(&snapshot.address) else {
+                continue;
+            };
+            if self.last_seen.get(&snapshot.address) == Some(&snapshot) {
+                continue;
+            }
+
+            changes.push(DriftChange {
+                address: snapshot.address.clone(),
+                warnings: diff(baseline, &snapshot),
+            });
+            self.last_seen.insert(snapshot.address.clone(), snapshot);
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::program_option::COption;
+    use solana_sdk::program_pack::Pack;
+
+    fn mint_account(mint_authority: Option<Pubkey>, freeze_authority: Option<Pubkey>) -> Account {
+        let mint = spl_token::state::Mint {
+            mint_authority: mint_authority.map_or(COption::None, COption::Some),
+            supply: 1_000_000,
+            decimals: 6,
+            is_initialized: true,
+            freeze_authority: freeze_authority.map_or(COption::None, COption::Some),
+        };
+        let mut data = vec![0u8; spl_token::state::Mint::LEN];
+        mint.pack_into_slice(&mut data);
+
+        Account {
+            lamports: 1_461_600,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_mint() {
+        let address = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let snapshot = snapshot_account(&address, Some(&mint_account(Some(authority), None)));
+        assert_eq!(snapshot.kind, AccountKind::Mint);
+        assert_eq!(snapshot.mint_authority, Some(authority.to_string()));
+        assert_eq!(snapshot.freeze_authority, None);
+        assert_eq!(snapshot.owner, Some(spl_token::id().to_string()));
+
+        let missing = snapshot_account(&address, None);
+        assert_eq!(missing.kind, AccountKind::Missing);
+    }
+
+    #[test]
+    fn test_snapshot_program_and_upgrade_authority() {
+        let address = Pubkey::new_unique();
+        let programdata_address = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let program = Account {
+            lamports: 1,
+            data: bincode::serialize(&UpgradeableLoaderState::Program { programdata_address }).unwrap(),
+            owner: bpf_loader_upgradeable::id(),
+            executable: true,
+            rent_epoch: 0,
+        };
+        let snapshot = snapshot_account(&address, Some(&program));
+        assert_eq!(snapshot.kind, AccountKind::Program);
+        assert_eq!(snapshot.programdata_address, Some(programdata_address.to_string()));
+        assert_eq!(programdata_addresses(&[snapshot]), vec![programdata_address]);
+
+        let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+            slot: 42,
+            upgrade_authority_address: Some(authority),
+        })
+        .unwrap();
+        data.extend_from_slice(&[0u8; 64]);
+        let programdata = Account {
+            lamports: 1,
+            data,
+            owner: bpf_loader_upgradeable::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        assert_eq!(upgrade_authority(&programdata), Some(authority.to_string()));
+    }
+
+    #[test]
+    fn test_diff_detects_revoked_mint_authority() {
+        let address = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let baseline = snapshot_account(&address, Some(&mint_account(Some(authority), Some(authority))));
+        assert!(diff(&baseline, &baseline).is_empty());
+
+        let current = snapshot_account(&address, Some(&mint_account(None, Some(authority))));
+        let warnings = diff(&baseline, &current);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].pattern_id, PatternId::P105AuthorityDrift);
+        assert_eq!(warnings[0].severity, Severity::Critical);
+        assert!(warnings[0].message.starts_with("Mint authority changed"));
+        assert_eq!(warnings[0].affected_account, Some(address));
+
+        let closed = snapshot_account(&address, None);
+        let warnings = diff(&baseline, &closed);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.contains("Missing"));
+    }
+
+    #[test]
+    fn test_tracker_reports_each_change_once() {
+        let address = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let original = snapshot_account(&address, Some(&mint_account(Some(authority), None)));
+        let revoked = snapshot_account(&address, Some(&mint_account(None, None)));
+
+        let mut tracker = DriftTracker::new(&Baseline {
+            created_at: 0,
+            accounts: vec![original.clone()],
+        });
+        assert!(tracker.update(vec![original.clone()]).is_empty());
+
+        let changes = tracker.update(vec![revoked.clone()]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].warnings.len(), 1);
+        assert!(tracker.update(vec![revoked]).is_empty());
+
+        let changes = tracker.update(vec![original]);
+        assert_eq!(changes.len(), 1);
+        assert!(changes[0].warnings.is_empty());
+    }
+}
@@ -0,0 +1,296 @@
+use crate::detector::{PatternId, Severity, Warning};
+use crate::idl::discriminator;
+use serde::Deserialize;
+use solana_sdk::{bs58, pubkey::Pubkey};
+use solana_transaction_status::UiPartiallyDecodedInstruction;
+
+/// Program IDs declared by the programs in this repository
+pub const RISK_PROGRAM_ID: &str = "84zwY58ivSmpGY8gsenAc2c4XpwBUyh1thF9XXx3LhfD";
+pub const FLOW_PROGRAM_ID: &str = "5FBd3aTWH5b62DgFoAWjnnogzCptKf952ZUvgEnmzsRk";
+pub const YIELD_SPLITTER_PROGRAM_ID: &str = "9tGdavqZd29sZzkWo2kSjytFZtS4VzArwcshf9zvEMVg";
+pub const ATOMLIQ_PROGRAM_ID: &str = "F3xVeUGPhuyMjtWf433WDAyhcA1SbQJjAsySw2d1Pf9G";
+
+/// Thresholds and program IDs for the Fabrknt program rules
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FabrkntRules {
+    pub risk_program_id: String,
+    pub flow_program_id: String,
+    pub yield_splitter_program_id: String,
+    pub atomliq_program_id: String,
+
+    /// Expected gateway fee; without it every fee change is reported as an alert
+    pub gateway_fee: Option<u64>,
+    /// Largest fee change, in basis points of `gateway_fee`, that is not reported
+    pub max_fee_change_bps: u64,
+    /// Keys allowed to approve flow rebalances; empty disables the check
+    pub rebalance_approvers: Vec<String>,
+    /// Largest `add_liquidity` amount (PT or YT) that is not reported
+    pub max_add_liquidity: Option<u64>,
+    /// Largest `execute_liquidation` amount that is not reported
+    pub max_liquidation_amount: Option<u64>,
+}
+
+impl Default for FabrkntRules {
+    fn default() -> Self {
+        Self {
+            risk_program_id: RISK_PROGRAM_ID.to_string(),
+            flow_program_id: FLOW_PROGRAM_ID.to_string(),
+            yield_splitter_program_id: YIELD_SPLITTER_PROGRAM_ID.to_string(),
+            atomliq_program_id: ATOMLIQ_PROGRAM_ID.to_string(),
+            gateway_fee: None,
+            max_fee_change_bps: 2_000,
+            rebalance_approvers: Vec::new(),
+            max_add_liquidity: None,
+            max_liquidation_amount: None,
+        }
+    }
+}
+
+/// Context from the rest of the transaction that some rules need
+pub struct TransactionContext {
+    /// Whether any SPL token transfer ran in the transaction, including inner instructions
+    pub has_token_transfer: bool,
+}
+
+/// Applies the Fabrknt program rules to an instruction the RPC could not parse.
+///
+/// Instructions for other programs, or with unknown discriminators, produce no warnings.
+pub fn analyze_instruction(
+    ix: &UiPartiallyDecodedInstruction,
+    context: &TransactionContext,
+    rules: &FabrkntRules,
+) -> Vec<Warning> {
+    let Ok(data) = bs58::decode(&ix.data).into_vec() else {
+        return Vec::new();
+    };
+    if data.len() < 8 {
+        return Vec::new();
+    }
+    let (ix_discriminator, args) = data.split_at(8);
+    let is = |name: &str| ix_discriminator == discriminator(name);
+    let account = |index: usize| ix.accounts.get(index).and_then(|key| key.parse::<Pubkey>().ok());
+
+    let program_id = ix.program_id.as_str();
+    if program_id == rules.risk_program_id && is("update_fee") {
+        // accounts: gateway, authority
+        return read_u64(args, 0)
+            .map(|new_fee| analyze_update_fee(new_fee, account(0), rules))
+            .unwrap_or_default();
+    }
+    if program_id == rules.flow_program_id && is("approve_rebalance") {
+        // accounts: decision, position, approver, audit_log
+        return analyze_approve_rebalance(ix.accounts.get(2), account(0), rules);
+    }
+    if program_id == rules.yield_splitter_program_id && is("mark_matured") {
+        // accounts: amm
+        return vec![Warning {
+            pattern_id: PatternId::P303PoolMatured,
+            severity: Severity::Warning,
+            message: "Yield pool marked as matured. Swaps and new liquidity are permanently disabled for this pool.".to_string(),
+            affected_account: account(0),
+        }];
+    }
+    if program_id == rules.yield_splitter_program_id && is("add_liquidity") {
+        // accounts: amm, user
+        return match (read_u64(args, 0), read_u64(args, 8)) {
+            (Some(pt_amount), Some(yt_amount)) => {
+                analyze_add_liquidity(pt_amount, yt_amount, account(0), context, rules)
+            }
+            _ => Vec::new(),
+        };
+    }
+    if program_id == rules.atomliq_program_id && is("execute_liquidation") {
+        // accounts: pool, user_account, price_update, liquidator
+        return read_u64(args, 0)
+            .map(|amount| analyze_liquidation(amount, account(1), rules))
+            .unwrap_or_default();
+    }
+
+    Vec::new()
+}
+
+/// P-301: Gateway Fee Change
+fn analyze_update_fee(new_fee: u64, gateway: Option<Pubkey>, rules: &FabrkntRules) -> Vec<Warning> {
+    let Some(reference_fee) = rules.gateway_fee else {
+        return vec![Warning {
+            pattern_id: PatternId::P301GatewayFeeChange,
+            severity: Severity::Alert,
+            message: format!("Risk gateway fee change to {} queued.", new_fee),
+            affected_account: gateway,
+        }];
+    };
+
+    let change = new_fee.abs_diff(reference_fee) as u128;
+    let allowed = reference_fee as u128 * rules.max_fee_change_bps as u128 / 10_000;
+    if change <= allowed {
+        return Vec::new();
+    }
+
+    vec![Warning {
+        pattern_id: PatternId::P301GatewayFeeChange,
+        severity: Severity::Critical,
+        message: format!(
+            "Risk gateway fee change from {} to {} queued, more than the allowed {}%. Paying users are charged the new fee once the timelock passes.",
+            reference_fee,
+            new_fee,
+            rules.max_fee_change_bps as f64 / 100.0
+        ),
+        affected_account: gateway,
+    }]
+}
+
+/// P-302: Unlisted Rebalance Approver
+fn analyze_approve_rebalance(
+    approver: Option<&String>,
+    decision: Option<Pubkey>,
+    rules: &FabrkntRules,
+) -> Vec<Warning> {
+    if rules.rebalance_approvers.is_empty() {
+        return Vec::new();
+    }
+    let Some(approver) = approver else {
+        return Vec::new();
+    };
+    if rules.rebalance_approvers.contains(approver) {
+        return Vec::new();
+    }
+
+    vec![Warning {
+        pattern_id: PatternId::P302UnlistedApprover,
+        severity: Severity::Critical,
+        message: format!(
+            "Rebalance approved by {}, which is not on the approver list. The flow program accepts any signer as approver.",
+            approver
+        ),
+        affected_account: decision,
+    }]
+}
+
+/// P-304: Unbacked Liquidity
+fn analyze_add_liquidity(
+    pt_amount: u64,
+    yt_amount: u64,
+    amm: Option<Pubkey>,
+    context: &TransactionContext,
+    rules: &FabrkntRules,
+) -> Vec<Warning> {
+    if !context.has_token_transfer {
+        return vec![Warning {
+            pattern_id: PatternId::P304UnbackedLiquidity,
+            severity: Severity::Critical,
+            message: format!(
+                "Pool reserves credited with {} PT and {} YT, but no token transfer ran in this transaction.",
+                pt_amount, yt_amount
+            ),
+            affected_account: amm,
+        }];
+    }
+
+    match rules.max_add_liquidity {
+        Some(max) if pt_amount > max || yt_amount > max => vec![Warning {
+            pattern_id: PatternId::P304UnbackedLiquidity,
+            severity: Severity::Warning,
+            message: format!(
+                "Large liquidity addition: {} PT and {} YT (threshold {}).",
+                pt_amount, yt_amount, max
+            ),
+            affected_account: amm,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+/// P-305: Large Liquidation
+fn analyze_liquidation(amount: u64, user_account: Option<Pubkey>, rules: &FabrkntRules) -> Vec<Warning> {
+    match rules.max_liquidation_amount {
+        Some(max) if amount > max => vec![Warning {
+            pattern_id: PatternId::P305LargeLiquidation,
+            severity: Severity::Warning,
+            message: format!(
+                "Liquidation of {} exceeds the configured threshold of {}.",
+                amount, max
+            ),
+            affected_account: user_account,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(program_id: &str, name: &str, args: &[u8], accounts: &[Pubkey]) -> UiPartiallyDecodedInstruction {
+        let mut data = discriminator(name).to_vec();
+        data.extend_from_slice(args);
+        UiPartiallyDecodedInstruction {
+            program_id: program_id.to_string(),
+            accounts: accounts.iter().map(|key| key.to_string()).collect(),
+            data: bs58::encode(data).into_string(),
+            stack_height: None,
+        }
+    }
+
+    const WITH_TRANSFER: TransactionContext = TransactionContext {
+        has_token_transfer: true,
+    };
+
+    #[test]
+    fn test_update_fee_threshold() {
+        let gateway = Pubkey::new_unique();
+        let rules = FabrkntRules {
+            gateway_fee: Some(1_000_000),
+            ..FabrkntRules::default()
+        };
+
+        let small = instruction(RISK_PROGRAM_ID, "update_fee", &1_100_000u64.to_le_bytes(), &[gateway]);
+        assert!(analyze_instruction(&small, &WITH_TRANSFER, &rules).is_empty());
+
+        let large = instruction(RISK_PROGRAM_ID, "update_fee", &5_000_000u64.to_le_bytes(), &[gateway]);
+        let warnings = analyze_instruction(&large, &WITH_TRANSFER, &rules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].pattern_id, PatternId::P301GatewayFeeChange);
+        assert_eq!(warnings[0].severity, Severity::Critical);
+        assert_eq!(warnings[0].affected_account, Some(gateway));
+
+        let other_program = instruction(FLOW_PROGRAM_ID, "update_fee", &5_000_000u64.to_le_bytes(), &[gateway]);
+        assert!(analyze_instruction(&other_program, &WITH_TRANSFER, &rules).is_empty());
+    }
+
+    #[test]
+    fn test_approve_rebalance_approver_list() {
+        let listed = Pubkey::new_unique();
+        let accounts = |approver| [Pubkey::new_unique(), Pubkey::new_unique(), approver, Pubkey::new_unique()];
+        let rules = FabrkntRules {
+            rebalance_approvers: vec![listed.to_string()],
+            ..FabrkntRules::default()
+        };
+
+        let ok = instruction(FLOW_PROGRAM_ID, "approve_rebalance", &0u32.to_le_bytes(), &accounts(listed));
+        assert!(analyze_instruction(&ok, &WITH_TRANSFER, &rules).is_empty());
+
+        let bad = instruction(FLOW_PROGRAM_ID, "approve_rebalance", &0u32.to_le_bytes(), &accounts(Pubkey::new_unique()));
+        let warnings = analyze_instruction(&bad, &WITH_TRANSFER, &rules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].pattern_id, PatternId::P302UnlistedApprover);
+    }
+
+    #[test]
+    fn test_add_liquidity_without_transfer() {
+        let mut args = 10u64.to_le_bytes().to_vec();
+        args.extend_from_slice(&20u64.to_le_bytes());
+        let ix = instruction(YIELD_SPLITTER_PROGRAM_ID, "add_liquidity", &args, &[Pubkey::new_unique()]);
+        let rules = FabrkntRules::default();
+
+        assert!(analyze_instruction(&ix, &WITH_TRANSFER, &rules).is_empty());
+        let warnings = analyze_instruction(&ix, &TransactionContext { has_token_transfer: false }, &rules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Critical);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::config::GuardConfig;
+use crate::detector::{self, AccountStates, PatternId, Severity, Warning};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File suffix for recorded transactions
+const FIXTURE_SUFFIX: &str = ".json";
+
+/// File suffix for expected warning snapshots
+const SNAPSHOT_SUFFIX: &str = ".expected.json";
+
+/// File suffix for account data captured alongside a transaction
+const ACCOUNTS_SUFFIX: &str = ".accounts.json";
+
+/// Optional detector config applied to every fixture in a directory
+const CONFIG_FILE: &str = "guard.json";
+
+/// A warning as stored in a snapshot file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarningSnapshot {
+    pub pattern_id: PatternId,
+    pub severity: Severity,
+    pub message: String,
+    pub affected_account: Option<String>,
+}
+
+impl From<&Warning> for WarningSnapshot {
+    fn from(warning: &Warning) -> Self {
+        Self {
+            pattern_id: warning.pattern_id,
+            severity: warning.severity,
+            message: warning.message.clone(),
+            affected_account: warning.affected_account.map(|account| account.to_string()),
+        }
+    }
+}
+
+/// A recorded transaction together with the warnings it is expected to produce
+pub struct Fixture {
+    pub name: String,
+    pub transaction: EncodedConfirmedTransactionWithStatusMeta,
+    pub accounts: AccountStates,
+    pub expected: Vec<WarningSnapshot>,
+}
+
+/// Result of running the detector against a fixture
+pub struct FixtureOutcome {
+    pub name: String,
+    pub expected: Vec<WarningSnapshot>,
+    pub actual: Vec<WarningSnapshot>,
+}
+
+impl FixtureOutcome {
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+impl Fixture {
+    /// Run the detector against the recorded transaction
+    pub fn run(&self, config: &GuardConfig) -> Result<FixtureOutcome> {
+        let warnings = detector::analyze_transaction(&self.transaction, config, &self.accounts)
+            .with_context(|| format!("Failed to analyze fixture {}", self.name))?;
+
+        Ok(FixtureOutcome {
+            name: self.name.clone(),
+            expected: self.expected.clone(),
+            actual: warnings.iter().map(WarningSnapshot::from).collect(),
+        })
+    }
+}
+
+/// Loads every fixture in a directory, sorted by name.
+///
+/// Each `<name>.json` holds an `EncodedConfirmedTransactionWithStatusMeta` as
+/// returned by `getTransaction`, and `<name>.expected.json` holds the
+/// warnings the detector should produce for it. An optional `<name>.accounts.json`
+/// maps addresses to base64 account data captured when the fixture was recorded.
+/// A `guard.json` in the same directory is used as the detector config.
+pub fn load_dir(dir: &Path) -> Result<Vec<Fixture>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)
+        .with_context(|| format!("Failed to read fixture directory {}", dir.display()))?
+    {
+        let file_name = entry?.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.ends_with(SNAPSHOT_SUFFIX)
+            || file_name.ends_with(ACCOUNTS_SUFFIX)
+            || file_name == CONFIG_FILE
+        {
+            continue;
+        }
+        if let Some(name) = file_name.strip_suffix(FIXTURE_SUFFIX) {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+
+    names.iter().map(|name| load(dir, name)).collect()
+}
+
+/// Loads a single fixture and its snapshot
+pub fn load(dir: &Path, name: &str) -> Result<Fixture> {
+    let (fixture_path, snapshot_path) = paths(dir, name);
+
+    let transaction = read_transaction(&fixture_path)?;
+    let accounts = read_accounts(&accounts_path(dir, name))?;
+
+    let expected = serde_json::from_str(
+        &fs::read_to_string(&snapshot_path)
+            .with_context(|| format!("Missing snapshot {}", snapshot_path.display()))?,
+    )
+    .with_context(|| format!("Invalid snapshot {}", snapshot_path.display()))?;
+
+    Ok(Fixture {
+        name: name.to_string(),
+        transaction,
+        accounts,
+        expected,
+    })
+}
+
+/// Reads a transaction saved in `getTransaction` JSON form
+pub fn read_transaction(path: &Path) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    serde_json::from_str(
+        &fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?,
+    )
+    .with_context(|| format!("Invalid transaction fixture {}", path.display()))
+}
+
+/// Reads the account data saved next to a transaction file as `<name>.accounts.json`,
+/// or None when there is none
+pub fn read_transaction_accounts(transaction_path: &Path) -> Result<Option<AccountStates>> {
+    let name = transaction_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_suffix(FIXTURE_SUFFIX));
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    let path = accounts_path(transaction_path.parent().unwrap_or(Path::new("")), name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    read_accounts(&path).map(Some)
+}
+
+/// Reads captured account data, treating a missing file as no accounts
+fn read_accounts(path: &Path) -> Result<AccountStates> {
+    if !path.exists() {
+        return Ok(AccountStates::new());
+    }
+    let encoded: BTreeMap<String, String> = serde_json::from_str(
+        &fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?,
+    )
+    .with_context(|| format!("Invalid account data {}", path.display()))?;
+
+    encoded
+        .iter()
+        .map(|(address, data)| {
+            let address = address
+                .parse::<Pubkey>()
+                .with_context(|| format!("Invalid address {} in {}", address, path.display()))?;
+            let data = BASE64
+                .decode(data)
+                .with_context(|| format!("Invalid base64 for {} in {}", address, path.display()))?;
+            Ok((address, data))
+        })
+        .collect()
+}
+
+/// Saves a transaction as a fixture, snapshotting the warnings it currently produces.
+///
+/// Account data is written to a sidecar file when any was captured. Returns the
+/// snapshot that was written so the caller can show it for review.
+pub fn save(
+    dir: &Path,
+    name: &str,
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    accounts: &AccountStates,
+) -> Result<Vec<WarningSnapshot>> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create fixture directory {}", dir.display()))?;
+
+    let config = load_config(dir)?;
+    let warnings = detector::analyze_transaction(transaction, &config, accounts)?;
+    let snapshot: Vec<WarningSnapshot> = warnings.iter().map(WarningSnapshot::from).collect();
+
+    let (fixture_path, snapshot_path) = paths(dir, name);
+    fs::write(&fixture_path, serde_json::to_string_pretty(transaction)? + "\n")
+        .with_context(|| format!("Failed to write {}", fixture_path.display()))?;
+    write_snapshot(&snapshot_path, &snapshot)?;
+
+    if !accounts.is_empty() {
+        let encoded: BTreeMap<String, String> = accounts
+            .iter()
+            .map(|(address, data)| (address.to_string(), BASE64.encode(data)))
+            .collect();
+        let path = accounts_path(dir, name);
+        fs::write(&path, serde_json::to_string_pretty(&encoded)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    Ok(snapshot)
+}
+
+/// Runs every fixture in a directory and returns the outcomes.
+///
+/// With `update` set, snapshots that no longer match are rewritten with the
+/// detector's current output, for use after an intentional detector change.
+pub fn verify_dir(dir: &Path, update: bool) -> Result<Vec<FixtureOutcome>> {
+    let config = load_config(dir)?;
+    let mut outcomes = Vec::new();
+    for fixture in load_dir(dir)? {
+        let outcome = fixture.run(&config)?;
+        if update && !outcome.passed() {
+            let (_, snapshot_path) = paths(dir, &outcome.name);
+            write_snapshot(&snapshot_path, &outcome.actual)?;
+        }
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+fn load_config(dir: &Path) -> Result<GuardConfig> {
+    GuardConfig::load_or_default(&dir.join(CONFIG_FILE))
+}
+
+fn write_snapshot(path: &Path, snapshot: &[WarningSnapshot]) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(snapshot)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn paths(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    (
+        dir.join(format!("{}{}", name, FIXTURE_SUFFIX)),
+        dir.join(format!("{}{}", name, SNAPSHOT_SUFFIX)),
+    )
+}
+
+fn accounts_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}{}", name, ACCOUNTS_SUFFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+    }
+
+    /// Runs every recorded fixture through the detector.
+    ///
+    /// Set `GUARD_UPDATE_SNAPSHOTS=1` to rewrite the expected snapshots
+    /// after an intentional detector change.
+    #[test]
+    fn test_fixtures_match_snapshots() {
+        let dir = fixture_dir();
+        let update = std::env::var("GUARD_UPDATE_SNAPSHOTS").is_ok_and(|v| v == "1");
+        let outcomes = verify_dir(&dir, update).expect("fixtures should load and analyze");
+        assert!(!outcomes.is_empty(), "no fixtures found in {}", dir.display());
+
+        if update {
+            return;
+        }
+
+        let failures: Vec<String> = outcomes
+            .iter()
+            .filter(|outcome| !outcome.passed())
+            .map(|outcome| {
+                format!(
+                    "{}\n  expected: {}\n  actual:   {}",
+                    outcome.name,
+                    serde_json::to_string(&outcome.expected).unwrap(),
+                    serde_json::to_string(&outcome.actual).unwrap(),
+                )
+            })
+            .collect();
+
+        assert!(
+            failures.is_empty(),
+            "{} fixture(s) did not match their snapshots:\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+
+    /// An unsent transaction has no meta, and its saved account data is the state
+    /// the close would run against
+    #[test]
+    fn test_unsent_transaction_with_saved_accounts() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/unsent/token_2022_close.json");
+        let transaction = read_transaction(&path).unwrap();
+        assert!(transaction.transaction.meta.is_none());
+        let accounts = read_transaction_accounts(&path).unwrap().expect("saved account data");
+
+        let config = GuardConfig::default();
+        let warnings = detector::analyze_transaction(&transaction, &config, &accounts).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].severity, Severity::Critical);
+        assert!(warnings[0].message.contains("withheld transfer fees"));
+
+        // Without the pre-close state only the generic warning is left
+        let warnings = detector::analyze_transaction(&transaction, &config, &AccountStates::new()).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Warning);
+
+        let missing = std::env::temp_dir().join("guard-no-accounts.json");
+        assert!(read_transaction_accounts(&missing).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let source = load(&fixture_dir(), "p104_token_2022_withheld_fees").unwrap();
+        let dir = std::env::temp_dir().join(format!("guard-fixture-{}", std::process::id()));
+
+        let snapshot = save(&dir, "round_trip", &source.transaction, &source.accounts).unwrap();
+        let loaded = load(&dir, "round_trip").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.transaction, source.transaction);
+        assert_eq!(loaded.accounts, source.accounts);
+        assert_eq!(loaded.expected, snapshot);
+        assert!(loaded.run(&GuardConfig::default()).unwrap().passed());
+    }
+}
@@ -0,0 +1,554 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value};
+use solana_sdk::{bs58, hash::hash};
+use solana_transaction_status::UiPartiallyDecodedInstruction;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Anchor instruction discriminator: the first 8 bytes of `sha256("global:<name>")`
+pub fn discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+/// Borsh-encoded type as described by an Anchor IDL
+#[derive(Debug, Clone, PartialEq)]
+enum IdlType {
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    String,
+    Bytes,
+    Pubkey,
+    Vec(Box<IdlType>),
+    Option(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+    Defined(String),
+}
+
+#[derive(Debug, Clone)]
+enum IdlFields {
+    Named(Vec<(String, IdlType)>),
+    Tuple(Vec<IdlType>),
+    None,
+}
+
+#[derive(Debug, Clone)]
+enum IdlTypeDef {
+    Struct(IdlFields),
+    Enum(Vec<(String, IdlFields)>),
+}
+
+#[derive(Debug, Clone)]
+struct IdlInstruction {
+    name: String,
+    discriminator: Vec<u8>,
+    /// Flattened account names, in the order the program expects them
+    accounts: Vec<String>,
+    args: Vec<(String, IdlType)>,
+}
+
+/// An Anchor IDL, in either the 0.30+ spec format or the legacy format.
+///
+/// Instruction, account and argument names are normalized to snake_case so
+/// rules can use the names from the program source regardless of IDL version.
+#[derive(Debug, Clone)]
+pub struct Idl {
+    pub program_id: Option<String>,
+    pub name: String,
+    instructions: Vec<IdlInstruction>,
+    types: HashMap<String, IdlTypeDef>,
+}
+
+/// An instruction decoded with an IDL
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedInstruction {
+    pub program_id: String,
+    pub name: String,
+    pub args: Map<String, Value>,
+    /// Account name to address, for the accounts named in the IDL
+    pub accounts: Map<String, Value>,
+}
+
+impl Idl {
+    pub fn load(path: &Path) -> Result<Self> {
+        let value: Value = serde_json::from_str(
+            &fs::read_to_string(path).with_context(|| format!("Failed to read IDL {}", path.display()))?,
+        )
+        .with_context(|| format!("Invalid IDL JSON {}", path.display()))?;
+        Self::parse(&value).with_context(|| format!("Unsupported IDL {}", path.display()))
+    }
+
+    pub fn parse(value: &Value) -> Result<Self> {
+        let program_id = value["address"]
+            .as_str()
+            .or_else(|| value["metadata"]["address"].as_str())
+            .map(str::to_string);
+        let name = value["metadata"]["name"]
+            .as_str()
+            .or_else(|| value["name"].as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let mut types = HashMap::new();
+        for ty in value["types"].as_array().into_iter().flatten() {
+            let type_name = ty["name"].as_str().ok_or_else(|| anyhow!("Type without a name"))?;
+            types.insert(type_name.to_string(), parse_type_def(&ty["type"])?);
+        }
+
+        let mut instructions = Vec::new();
+        for ix in value["instructions"]
+            .as_array()
+            .ok_or_else(|| anyhow!("IDL has no instructions"))?
+        {
+            let name = to_snake_case(ix["name"].as_str().ok_or_else(|| anyhow!("Instruction without a name"))?);
+            let discriminator = match ix["discriminator"].as_array() {
+                Some(bytes) => bytes
+                    .iter()
+                    .map(|byte| byte.as_u64().map(|b| b as u8))
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or_else(|| anyhow!("Invalid discriminator for {}", name))?,
+                None => discriminator(&name).to_vec(),
+            };
+
+            let mut accounts = Vec::new();
+            flatten_accounts(&ix["accounts"], &mut accounts);
+
+            let args = ix["args"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|arg| {
+                    let arg_name = arg["name"].as_str().ok_or_else(|| anyhow!("Argument without a name"))?;
+                    Ok((to_snake_case(arg_name), parse_type(&arg["type"])?))
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("Invalid arguments for {}", name))?;
+
+            instructions.push(IdlInstruction {
+                name,
+                discriminator,
+                accounts,
+                args,
+            });
+        }
+
+        Ok(Self {
+            program_id,
+            name,
+            instructions,
+            types,
+        })
+    }
+
+    /// Whether the IDL defines an instruction with this (snake_case or camelCase) name
+    pub fn has_instruction(&self, name: &str) -> bool {
+        let name = to_snake_case(name);
+        self.instructions.iter().any(|ix| ix.name == name)
+    }
+
+    /// Decodes an instruction's arguments and accounts, if its discriminator matches one in the IDL
+    pub fn decode(&self, ix: &UiPartiallyDecodedInstruction) -> Option<DecodedInstruction> {
+        let data = bs58::decode(&ix.data).into_vec().ok()?;
+        let instruction = self
+            .instructions
+            .iter()
+            .find(|candidate| data.starts_with(&candidate.discriminator))?;
+
+        let mut reader = Reader {
+            data: &data[instruction.discriminator.len()..],
+        };
+        let mut args = Map::new();
+        for (name, ty) in &instruction.args {
+            args.insert(name.clone(), self.read(&mut reader, ty, 0)?);
+        }
+
+        let accounts = instruction
+            .accounts
+            .iter()
+            .zip(ix.accounts.iter())
+            .map(|(name, address)| (name.clone(), Value::String(address.clone())))
+            .collect();
+
+        Some(DecodedInstruction {
+            program_id: ix.program_id.clone(),
+            name: instruction.name.clone(),
+            args,
+            accounts,
+        })
+    }
+
+    fn read(&self, reader: &mut Reader, ty: &IdlType, depth: usize) -> Option<Value> {
+        // Guards against self-referential type definitions
+        if depth > 32 {
+            return None;
+        }
+
+        Some(match ty {
+            IdlType::Bool => Value::Bool(reader.take(1)?[0] != 0),
+            IdlType::U8 => reader.take(1)?[0].into(),
+            IdlType::U16 => u16::from_le_bytes(reader.array()?).into(),
+            IdlType::U32 => u32::from_le_bytes(reader.array()?).into(),
+            IdlType::U64 => u64::from_le_bytes(reader.array()?).into(),
+            IdlType::I8 => (reader.take(1)?[0] as i8).into(),
+            IdlType::I16 => i16::from_le_bytes(reader.array()?).into(),
+            IdlType::I32 => i32::from_le_bytes(reader.array()?).into(),
+            IdlType::I64 => i64::from_le_bytes(reader.array()?).into(),
+            // JSON numbers cannot hold the full 128-bit range
+            IdlType::U128 => Value::String(u128::from_le_bytes(reader.array()?).to_string()),
+            IdlType::I128 => Value::String(i128::from_le_bytes(reader.array()?).to_string()),
+            IdlType::F32 => f32::from_le_bytes(reader.array()?).into(),
+            IdlType::F64 => f64::from_le_bytes(reader.array()?).into(),
+            IdlType::String => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                Value::String(String::from_utf8(reader.take(len)?.to_vec()).ok()?)
+            }
+            IdlType::Bytes => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                reader.take(len)?.to_vec().into()
+            }
+            IdlType::Pubkey => Value::String(bs58::encode(reader.take(32)?).into_string()),
+            IdlType::Vec(inner) => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(self.read(reader, inner, depth + 1)?);
+                }
+                Value::Array(items)
+            }
+            IdlType::Array(inner, len) => {
+                let mut items = Vec::with_capacity(*len);
+                for _ in 0..*len {
+                    items.push(self.read(reader, inner, depth + 1)?);
+                }
+                Value::Array(items)
+            }
+            IdlType::Option(inner) => match reader.take(1)?[0] {
+                0 => Value::Null,
+                _ => self.read(reader, inner, depth + 1)?,
+            },
+            IdlType::Defined(name) => match self.types.get(name)? {
+                IdlTypeDef::Struct(fields) => self.read_fields(reader, fields, depth + 1)?,
+                IdlTypeDef::Enum(variants) => {
+                    let (variant, fields) = variants.get(reader.take(1)?[0] as usize)?;
+                    match fields {
+                        IdlFields::None => Value::String(variant.clone()),
+                        _ => {
+                            let mut object = Map::new();
+                            object.insert(variant.clone(), self.read_fields(reader, fields, depth + 1)?);
+                            Value::Object(object)
+                        }
+                    }
+                }
+            },
+        })
+    }
+
+    fn read_fields(&self, reader: &mut Reader, fields: &IdlFields, depth: usize) -> Option<Value> {
+        Some(match fields {
+            IdlFields::Named(fields) => {
+                let mut object = Map::new();
+                for (name, ty) in fields {
+                    object.insert(name.clone(), self.read(reader, ty, depth)?);
+                }
+                Value::Object(object)
+            }
+            IdlFields::Tuple(types) => {
+                let mut items = Vec::new();
+                for ty in types {
+                    items.push(self.read(reader, ty, depth)?);
+                }
+                Value::Array(items)
+            }
+            IdlFields::None => Value::Null,
+        })
+    }
+}
+
+/// IDLs keyed by program ID
+#[derive(Debug, Clone, Default)]
+pub struct IdlRegistry {
+    idls: HashMap<String, Idl>,
+}
+
+impl IdlRegistry {
+    pub fn insert(&mut self, program_id: String, idl: Idl) {
+        self.idls.insert(program_id, idl);
+    }
+
+    pub fn get(&self, program_id: &str) -> Option<&Idl> {
+        self.idls.get(program_id)
+    }
+
+    /// Decodes an instruction with the IDL registered for its program, if any
+    pub fn decode(&self, ix: &UiPartiallyDecodedInstruction) -> Option<DecodedInstruction> {
+        self.get(&ix.program_id)?.decode(ix)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Some(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+}
+
+/// Flattens nested account groups (composite `Accounts` structs) into a positional list
+fn flatten_accounts(accounts: &Value, out: &mut Vec<String>) {
+    for account in accounts.as_array().into_iter().flatten() {
+        if account["accounts"].is_array() {
+            flatten_accounts(&account["accounts"], out);
+        } else if let Some(name) = account["name"].as_str() {
+            out.push(to_snake_case(name));
+        }
+    }
+}
+
+fn parse_type(value: &Value) -> Result<IdlType> {
+    if let Some(name) = value.as_str() {
+        return Ok(match name {
+            "bool" => IdlType::Bool,
+            "u8" => IdlType::U8,
+            "u16" => IdlType::U16,
+            "u32" => IdlType::U32,
+            "u64" => IdlType::U64,
+            "u128" => IdlType::U128,
+            "i8" => IdlType::I8,
+            "i16" => IdlType::I16,
+            "i32" => IdlType::I32,
+            "i64" => IdlType::I64,
+            "i128" => IdlType::I128,
+            "f32" => IdlType::F32,
+            "f64" => IdlType::F64,
+            "string" => IdlType::String,
+            "bytes" => IdlType::Bytes,
+            "pubkey" | "publicKey" => IdlType::Pubkey,
+            other => return Err(anyhow!("Unsupported type {}", other)),
+        });
+    }
+
+    if let Some(inner) = value.get("vec") {
+        return Ok(IdlType::Vec(Box::new(parse_type(inner)?)));
+    }
+    if let Some(inner) = value.get("option") {
+        return Ok(IdlType::Option(Box::new(parse_type(inner)?)));
+    }
+    if let Some(array) = value.get("array").and_then(Value::as_array) {
+        let len = array
+            .get(1)
+            .and_then(Value::as_u64)
+            .ok_or_else(|| anyhow!("Unsupported array length in {}", value))?;
+        let inner = array.first().ok_or_else(|| anyhow!("Array without a type"))?;
+        return Ok(IdlType::Array(Box::new(parse_type(inner)?), len as usize));
+    }
+    if let Some(defined) = value.get("defined") {
+        // Legacy IDLs use `{"defined": "Name"}`, the 0.30+ spec `{"defined": {"name": "Name"}}`
+        let name = defined
+            .as_str()
+            .or_else(|| defined["name"].as_str())
+            .ok_or_else(|| anyhow!("Invalid defined type {}", defined))?;
+        return Ok(IdlType::Defined(name.to_string()));
+    }
+
+    Err(anyhow!("Unsupported type {}", value))
+}
+
+fn parse_fields(value: &Value) -> Result<IdlFields> {
+    let Some(fields) = value.as_array() else {
+        return Ok(IdlFields::None);
+    };
+    if fields.iter().all(|field| field.get("name").is_some()) {
+        fields
+            .iter()
+            .map(|field| {
+                let name = field["name"].as_str().unwrap_or_default();
+                Ok((to_snake_case(name), parse_type(&field["type"])?))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(IdlFields::Named)
+    } else {
+        fields.iter().map(parse_type).collect::<Result<Vec<_>>>().map(IdlFields::Tuple)
+    }
+}
+
+fn parse_type_def(value: &Value) -> Result<IdlTypeDef> {
+    match value["kind"].as_str() {
+        Some("struct") => Ok(IdlTypeDef::Struct(parse_fields(&value["fields"])?)),
+        Some("enum") => value["variants"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|variant| {
+                let name = variant["name"].as_str().unwrap_or_default().to_string();
+                Ok((name, parse_fields(&variant["fields"])?))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(IdlTypeDef::Enum),
+        other => Err(anyhow!("Unsupported type kind {:?}", other)),
+    }
+}
+
+/// Converts `camelCase` names from legacy IDLs to `snake_case`
+pub fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn instruction(program_id: &str, data: Vec<u8>, accounts: &[Pubkey]) -> UiPartiallyDecodedInstruction {
+        UiPartiallyDecodedInstruction {
+            program_id: program_id.to_string(),
+            accounts: accounts.iter().map(|key| key.to_string()).collect(),
+            data: bs58::encode(data).into_string(),
+            stack_height: None,
+        }
+    }
+
+    #[test]
+    fn test_discriminator_matches_anchor() {
+        // Discriminator Anchor generates for risk::update_fee
+        assert_eq!(discriminator("update_fee"), [232, 253, 195, 247, 148, 212, 73, 222]);
+    }
+
+    #[test]
+    fn test_decode_spec_idl() {
+        let idl = Idl::parse(&json!({
+            "address": "84zwY58ivSmpGY8gsenAc2c4XpwBUyh1thF9XXx3LhfD",
+            "metadata": { "name": "risk", "version": "0.1.0", "spec": "0.1.0" },
+            "instructions": [{
+                "name": "update_fee",
+                "discriminator": [232, 253, 195, 247, 148, 212, 73, 222],
+                "accounts": [
+                    { "name": "gateway", "writable": true },
+                    { "name": "authority", "signer": true }
+                ],
+                "args": [{ "name": "new_fee", "type": "u64" }]
+            }]
+        }))
+        .unwrap();
+
+        let gateway = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let mut data = discriminator("update_fee").to_vec();
+        data.extend_from_slice(&42u64.to_le_bytes());
+
+        let decoded = idl
+            .decode(&instruction(&idl.program_id.clone().unwrap(), data, &[gateway, authority]))
+            .unwrap();
+        assert_eq!(decoded.name, "update_fee");
+        assert_eq!(decoded.args["new_fee"], json!(42));
+        assert_eq!(decoded.accounts["authority"], json!(authority.to_string()));
+    }
+
+    #[test]
+    fn test_decode_legacy_idl_with_defined_types() {
+        let idl = Idl::parse(&json!({
+            "version": "0.1.0",
+            "name": "admin_program",
+            "instructions": [{
+                "name": "setAdmin",
+                "accounts": [
+                    { "name": "config", "isMut": true, "isSigner": false },
+                    { "name": "admin", "isMut": false, "isSigner": true }
+                ],
+                "args": [
+                    { "name": "newAdmin", "type": "publicKey" },
+                    { "name": "params", "type": { "defined": "AdminParams" } }
+                ]
+            }],
+            "types": [
+                {
+                    "name": "AdminParams",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "delaySeconds", "type": { "option": "i64" } },
+                            { "name": "role", "type": { "defined": "Role" } },
+                            { "name": "tags", "type": { "vec": "string" } }
+                        ]
+                    }
+                },
+                {
+                    "name": "Role",
+                    "type": { "kind": "enum", "variants": [{ "name": "Owner" }, { "name": "Operator" }] }
+                }
+            ],
+            "metadata": { "address": "11111111111111111111111111111111" }
+        }))
+        .unwrap();
+        assert!(idl.has_instruction("setAdmin"));
+        assert!(idl.has_instruction("set_admin"));
+
+        let new_admin = Pubkey::new_unique();
+        let mut data = discriminator("set_admin").to_vec();
+        data.extend_from_slice(new_admin.as_ref());
+        data.push(1);
+        data.extend_from_slice(&3600i64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(b"ops");
+
+        let decoded = idl
+            .decode(&instruction("11111111111111111111111111111111", data, &[Pubkey::new_unique()]))
+            .unwrap();
+        assert_eq!(decoded.name, "set_admin");
+        assert_eq!(decoded.args["new_admin"], json!(new_admin.to_string()));
+        assert_eq!(
+            decoded.args["params"],
+            json!({ "delay_seconds": 3600, "role": "Operator", "tags": ["ops"] })
+        );
+        // Only the accounts actually passed are named
+        assert_eq!(decoded.accounts.len(), 1);
+    }
+
+    #[test]
+    fn test_decode_rejects_truncated_data() {
+        let idl = Idl::parse(&json!({
+            "instructions": [{ "name": "update_fee", "accounts": [], "args": [{ "name": "new_fee", "type": "u64" }] }]
+        }))
+        .unwrap();
+
+        let data = discriminator("update_fee").to_vec();
+        assert!(idl.decode(&instruction("11111111111111111111111111111111", data, &[])).is_none());
+    }
+}
@@ -1,33 +1,58 @@
+mod api;
+mod config;
 mod detector;
+mod drift;
+mod fabrknt;
+mod fixture;
+mod idl;
+mod nonce;
+mod pipeline;
+mod replay;
+mod report;
+mod rules;
+mod store;
+mod token_extensions;
+mod treasury;
+mod tui;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use log::{info, error, warn};
+use futures::StreamExt;
 use solana_client::{
+    nonblocking::pubsub_client::PubsubClient,
     rpc_client::RpcClient,
     rpc_config::RpcTransactionConfig,
 };
 use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
-use solana_transaction_status::UiTransactionEncoding;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiTransactionEncoding,
+};
 use std::str::FromStr;
-use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
 
 #[derive(Parser)]
 #[command(name = "guard")]
 #[command(author, version, about = "Guard - Prevent operational disasters on Solana", long_about = None)]
 struct Cli {
+    /// Detector config file (rule thresholds and program settings)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Watch a program for dangerous operations
+    /// Watch programs for dangerous operations
     Watch {
-        /// Program ID to monitor
-        #[arg(long, value_name = "PUBKEY")]
-        program_id: String,
+        /// Program ID to monitor (repeat to watch several)
+        #[arg(long = "program-id", value_name = "PUBKEY", required = true)]
+        program_ids: Vec<String>,
 
         /// Solana cluster environment
         #[arg(long, value_name = "ENV", default_value = "mainnet")]
@@ -44,57 +69,455 @@ enum Commands {
         /// Poll interval in seconds
         #[arg(long, value_name = "SECONDS", default_value = "5")]
         poll_interval: u64,
+
+        /// Show an interactive terminal dashboard instead of log output
+        #[arg(long)]
+        tui: bool,
+
+        /// SQLite database recording warnings, acknowledgments and suppressions
+        #[arg(long, value_name = "PATH")]
+        db: Option<PathBuf>,
+
+        /// Serve the local HTTP API on this address (requires --db)
+        #[arg(long, value_name = "ADDR", requires = "db")]
+        api: Option<std::net::SocketAddr>,
+    },
+
+    /// Analyze specific transactions once, for CI gating
+    Analyze {
+        /// Transaction signatures to fetch and analyze
+        #[arg(value_name = "SIGNATURE")]
+        signatures: Vec<String>,
+
+        /// Transaction JSON files (`getTransaction` output, JSON-parsed encoding) to analyze.
+        /// Unsent transactions in the same form, without `meta`, are checked before they run.
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<PathBuf>,
+
+        /// Solana cluster environment
+        #[arg(long, value_name = "ENV", default_value = "mainnet")]
+        env: String,
+
+        /// RPC URL (optional, will use default for environment if not specified)
+        #[arg(long, value_name = "URL")]
+        rpc_url: Option<String>,
+
+        /// Exit non-zero when a warning of this severity or higher is found
+        /// (exit code 10 = alert, 11 = warning, 12 = critical)
+        #[arg(long, value_enum, value_name = "SEVERITY")]
+        fail_on: Option<detector::Severity>,
+
+        /// Write a machine-readable report to this path
+        #[arg(long, value_name = "PATH")]
+        report: Option<PathBuf>,
+
+        /// Report format
+        #[arg(long, value_enum, value_name = "FORMAT", default_value = "junit")]
+        report_format: report::ReportFormat,
+    },
+
+    /// Snapshot account authorities into a baseline file for drift monitoring
+    Snapshot {
+        /// Mint, token account or upgradeable program to include (repeatable)
+        #[arg(long = "account", value_name = "PUBKEY", required = true)]
+        accounts: Vec<String>,
+
+        /// Baseline file to write
+        #[arg(long, value_name = "PATH", default_value = "guard-baseline.json")]
+        out: PathBuf,
+
+        /// Solana cluster environment
+        #[arg(long, value_name = "ENV", default_value = "mainnet")]
+        env: String,
+
+        /// RPC URL (optional, will use default for environment if not specified)
+        #[arg(long, value_name = "URL")]
+        rpc_url: Option<String>,
+    },
+
+    /// Watch baseline accounts for authority or owner drift
+    Drift {
+        /// Baseline file written by `guard snapshot`
+        #[arg(long, value_name = "PATH", default_value = "guard-baseline.json")]
+        baseline: PathBuf,
+
+        /// Solana cluster environment
+        #[arg(long, value_name = "ENV", default_value = "mainnet")]
+        env: String,
+
+        /// RPC URL (optional, will use default for environment if not specified)
+        #[arg(long, value_name = "URL")]
+        rpc_url: Option<String>,
+
+        /// Also react to `accountSubscribe` notifications instead of only polling
+        #[arg(long)]
+        subscribe: bool,
+
+        /// WebSocket URL for `--subscribe` (optional, derived from the RPC URL if not specified)
+        #[arg(long, value_name = "URL")]
+        ws_url: Option<String>,
+
+        /// Poll interval in seconds
+        #[arg(long, value_name = "SECONDS", default_value = "30")]
+        poll_interval: u64,
+    },
+
+    /// Record a transaction as a detector regression fixture
+    Record {
+        /// Transaction signature to record
+        #[arg(value_name = "SIGNATURE")]
+        signature: String,
+
+        /// Solana cluster environment
+        #[arg(long, value_name = "ENV", default_value = "mainnet")]
+        env: String,
+
+        /// RPC URL (optional, will use default for environment if not specified)
+        #[arg(long, value_name = "URL")]
+        rpc_url: Option<String>,
+
+        /// Directory to write the fixture and its snapshot into
+        #[arg(long, value_name = "DIR", default_value = "tests/fixtures")]
+        out_dir: PathBuf,
+
+        /// Fixture name (defaults to the signature)
+        #[arg(long, value_name = "NAME")]
+        name: Option<String>,
+    },
+
+    /// Run the detector against recorded fixtures and compare with their snapshots
+    Fixtures {
+        /// Directory containing recorded fixtures
+        #[arg(long, value_name = "DIR", default_value = "tests/fixtures")]
+        dir: PathBuf,
+
+        /// Rewrite snapshots that no longer match the detector output
+        #[arg(long)]
+        update: bool,
+    },
+
+    /// Replay recorded transactions through the watch pipeline, for offline end-to-end tests
+    ///
+    /// Covers the detector, treasury correlation, suppressions and the alert store. Notifiers are
+    /// not invoked; warnings are printed to the terminal. Account data for close checks is the
+    /// validator's current state, not the state at the replayed slot.
+    Replay {
+        /// JSON-lines file of `{"signature": ..., "transaction": ...}` entries
+        #[arg(long, value_name = "PATH", required_unless_present = "validator", conflicts_with = "validator")]
+        file: Option<PathBuf>,
+
+        /// RPC URL of a local validator serving the ledger to replay
+        /// (e.g. `solana-test-validator --ledger <DIR>`). Account data is read at its current slot.
+        #[arg(long, value_name = "URL")]
+        validator: Option<String>,
+
+        /// First slot to replay from the validator
+        #[arg(long, value_name = "SLOT", default_value = "0", requires = "validator")]
+        start_slot: u64,
+
+        /// Last slot to replay from the validator (defaults to its current slot)
+        #[arg(long, value_name = "SLOT", requires = "validator")]
+        end_slot: Option<u64>,
+
+        /// Only replay transactions invoking this program (repeatable)
+        #[arg(long = "program-id", value_name = "PUBKEY")]
+        program_ids: Vec<String>,
+
+        /// Replay speed as a multiple of the original block times (0 = no pauses)
+        #[arg(long, value_name = "FACTOR", default_value = "100")]
+        speed: f64,
+
+        /// SQLite database recording warnings, acknowledgments and suppressions
+        #[arg(long, value_name = "PATH")]
+        db: Option<PathBuf>,
+
+        /// Exit non-zero when a warning of this severity or higher is raised
+        /// (exit code 10 = alert, 11 = warning, 12 = critical)
+        #[arg(long, value_enum, value_name = "SEVERITY")]
+        fail_on: Option<detector::Severity>,
     },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logger
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-
     let cli = Cli::parse();
 
+    // Initialize logger; log lines would draw over the dashboard
+    let default_filter = match cli.command {
+        Commands::Watch { tui: true, .. } => "off",
+        _ => "info",
+    };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_filter)).init();
+
+    let config = match &cli.config {
+        Some(path) => config::GuardConfig::load(path)?,
+        None => config::GuardConfig::default(),
+    };
+
     match cli.command {
         Commands::Watch {
-            program_id,
+            program_ids,
             env,
             rpc_url,
             discord_webhook,
             poll_interval,
+            tui,
+            db,
+            api,
         } => {
-            // Parse and validate program ID
-            let pubkey = Pubkey::from_str(&program_id)
-                .map_err(|e| anyhow::anyhow!("Invalid program ID: {}", e))?;
+            // Parse and validate program IDs
+            let targets = program_ids
+                .iter()
+                .map(|program_id| {
+                    Pubkey::from_str(program_id)
+                        .map_err(|e| anyhow::anyhow!("Invalid program ID {}: {}", program_id, e))
+                })
+                .collect::<Result<Vec<_>>>()?;
 
             // Determine RPC URL
             let rpc_endpoint = rpc_url.unwrap_or_else(|| get_default_rpc_url(&env));
 
             info!("🛡️  {}", "Guard Starting...".bold().green());
-            info!("📡 Monitoring Program ID: {}", pubkey.to_string().cyan());
+            for target in &targets {
+                info!("📡 Monitoring Program ID: {}", target.to_string().cyan());
+            }
             info!("🌐 Environment: {}", env.yellow());
             info!("🔗 RPC Endpoint: {}", rpc_endpoint.blue());
             if discord_webhook.is_some() {
                 info!("📢 Discord Webhook: {}", "Configured".green());
             }
             info!("⏱️  Poll Interval: {}s", poll_interval);
+
+            let store = match &db {
+                Some(path) => {
+                    info!("🗄️  Alert Store: {}", path.display().to_string().blue());
+                    Some(Arc::new(Mutex::new(store::AlertStore::open(path)?)))
+                }
+                None => None,
+            };
+            if let (Some(address), Some(store)) = (api, &store) {
+                info!("🌐 HTTP API: {}", format!("http://{}", address).blue());
+                let store = store.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = api::serve(address, store).await {
+                        error!("❌ HTTP API stopped: {}", e);
+                    }
+                });
+            }
             info!("");
 
+            if tui {
+                let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+                let app = tui::App::new(&targets, tui::notifier_health(discord_webhook.is_some()), store.clone());
+                let watcher = tokio::spawn(run_watch(
+                    targets,
+                    rpc_endpoint,
+                    discord_webhook,
+                    poll_interval,
+                    config,
+                    store,
+                    Some(sender),
+                ));
+                let result = tokio::task::spawn_blocking(move || tui::run(app, receiver)).await?;
+                watcher.abort();
+                return result;
+            }
+
             // Run the watch command
-            if let Err(e) = run_watch(pubkey, rpc_endpoint, discord_webhook, poll_interval).await {
+            if let Err(e) = run_watch(targets, rpc_endpoint, discord_webhook, poll_interval, config, store, None).await {
                 error!("❌ {}: {}", "Fatal Error".red().bold(), e);
                 return Err(e);
             }
         }
+        Commands::Analyze {
+            signatures,
+            files,
+            env,
+            rpc_url,
+            fail_on,
+            report,
+            report_format,
+        } => {
+            if signatures.is_empty() && files.is_empty() {
+                return Err(anyhow::anyhow!("Provide at least one signature or --file"));
+            }
+            let rpc_endpoint = rpc_url.unwrap_or_else(|| get_default_rpc_url(&env));
+
+            let reports = match run_analyze(&signatures, &files, rpc_endpoint, &config) {
+                Ok(reports) => reports,
+                Err(e) => {
+                    error!("❌ {}: {}", "Fatal Error".red().bold(), e);
+                    return Err(e);
+                }
+            };
+
+            if let Some(path) = report {
+                std::fs::write(&path, report::render(report_format, &reports, fail_on))
+                    .map_err(|e| anyhow::anyhow!("Failed to write report {}: {}", path.display(), e))?;
+                info!("📝 Report written: {}", path.display().to_string().blue());
+            }
+
+            let code = report::exit_code(&reports, fail_on);
+            if code != 0 {
+                if let Some(highest) = report::highest_severity(&reports) {
+                    error!(
+                        "🚫 {} (highest severity: {:?}, exit code {})",
+                        "CI gate failed".red().bold(),
+                        highest,
+                        code
+                    );
+                }
+                std::process::exit(code);
+            }
+        }
+        Commands::Snapshot {
+            accounts,
+            out,
+            env,
+            rpc_url,
+        } => {
+            let addresses = accounts
+                .iter()
+                .map(|account| {
+                    Pubkey::from_str(account)
+                        .map_err(|e| anyhow::anyhow!("Invalid account {}: {}", account, e))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let rpc_endpoint = rpc_url.unwrap_or_else(|| get_default_rpc_url(&env));
+
+            if let Err(e) = run_snapshot(&addresses, rpc_endpoint, &out) {
+                error!("❌ {}: {}", "Fatal Error".red().bold(), e);
+                return Err(e);
+            }
+        }
+        Commands::Drift {
+            baseline,
+            env,
+            rpc_url,
+            subscribe,
+            ws_url,
+            poll_interval,
+        } => {
+            let rpc_endpoint = rpc_url.unwrap_or_else(|| get_default_rpc_url(&env));
+            let ws_endpoint = subscribe.then(|| ws_url.unwrap_or_else(|| get_default_ws_url(&rpc_endpoint)));
+
+            info!("🛡️  {}", "Guard Drift Monitor Starting...".bold().green());
+            info!("📄 Baseline: {}", baseline.display().to_string().cyan());
+            info!("🌐 Environment: {}", env.yellow());
+            info!("🔗 RPC Endpoint: {}", rpc_endpoint.blue());
+            if let Some(ws_endpoint) = &ws_endpoint {
+                info!("🔌 Subscriptions: {}", ws_endpoint.blue());
+            }
+            info!("⏱️  Poll Interval: {}s", poll_interval);
+            info!("");
+
+            if let Err(e) = run_drift(&baseline, rpc_endpoint, ws_endpoint, poll_interval).await {
+                error!("❌ {}: {}", "Fatal Error".red().bold(), e);
+                return Err(e);
+            }
+        }
+        Commands::Record {
+            signature,
+            env,
+            rpc_url,
+            out_dir,
+            name,
+        } => {
+            let signature = Signature::from_str(&signature)
+                .map_err(|e| anyhow::anyhow!("Invalid signature: {}", e))?;
+            let rpc_endpoint = rpc_url.unwrap_or_else(|| get_default_rpc_url(&env));
+            let name = name.unwrap_or_else(|| signature.to_string());
+
+            if let Err(e) = run_record(&signature, rpc_endpoint, &out_dir, &name) {
+                error!("❌ {}: {}", "Fatal Error".red().bold(), e);
+                return Err(e);
+            }
+        }
+        Commands::Fixtures { dir, update } => {
+            if let Err(e) = run_fixtures(&dir, update) {
+                error!("❌ {}: {}", "Fatal Error".red().bold(), e);
+                return Err(e);
+            }
+        }
+        Commands::Replay {
+            file,
+            validator,
+            start_slot,
+            end_slot,
+            program_ids,
+            speed,
+            db,
+            fail_on,
+        } => {
+            let programs = program_ids
+                .iter()
+                .map(|program_id| {
+                    Pubkey::from_str(program_id)
+                        .map_err(|e| anyhow::anyhow!("Invalid program ID {}: {}", program_id, e))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            info!("🛡️  {}", "Guard Replay Starting...".bold().green());
+            let (transactions, rpc_client) = match (&file, validator) {
+                (Some(path), _) => {
+                    info!("📄 Replay File: {}", path.display().to_string().cyan());
+                    (replay::read_jsonl(path)?, None)
+                }
+                (None, Some(url)) => {
+                    info!("🔗 Validator: {}", url.blue());
+                    let rpc_client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+                    (replay::read_validator(&rpc_client, start_slot, end_slot)?, Some(rpc_client))
+                }
+                (None, None) => unreachable!("clap requires --file or --validator"),
+            };
+            for program in &programs {
+                info!("📡 Program ID: {}", program.to_string().cyan());
+            }
+            info!("⏩ Speed: {}", if speed > 0.0 { format!("{}x", speed) } else { "unpaced".to_string() });
+
+            let store = match &db {
+                Some(path) => {
+                    info!("🗄️  Alert Store: {}", path.display().to_string().blue());
+                    Some(Arc::new(Mutex::new(store::AlertStore::open(path)?)))
+                }
+                None => None,
+            };
+            info!("");
+
+            let reports = match run_replay(transactions, &programs, speed, rpc_client.as_ref(), &config, store).await {
+                Ok(reports) => reports,
+                Err(e) => {
+                    error!("❌ {}: {}", "Fatal Error".red().bold(), e);
+                    return Err(e);
+                }
+            };
+
+            let code = report::exit_code(&reports, fail_on);
+            if code != 0 {
+                if let Some(highest) = report::highest_severity(&reports) {
+                    error!(
+                        "🚫 {} (highest severity: {:?}, exit code {})",
+                        "Replay raised warnings".red().bold(),
+                        highest,
+                        code
+                    );
+                }
+                std::process::exit(code);
+            }
+        }
     }
 
     Ok(())
 }
 
 async fn run_watch(
-    program_id: Pubkey,
+    targets: Vec<Pubkey>,
     rpc_url: String,
     _discord_webhook: Option<String>,
     poll_interval: u64,
+    config: config::GuardConfig,
+    store: Option<api::SharedStore>,
+    events: Option<UnboundedSender<tui::WatchEvent>>,
 ) -> Result<()> {
     info!("🔍 Starting transaction monitoring...");
     info!("ℹ️  Press Ctrl+C to stop");
@@ -110,31 +533,52 @@ async fn run_watch(
         }
         Err(e) => {
             error!("❌ Failed to connect to RPC: {}", e);
+            for target in &targets {
+                send_event(&events, tui::WatchEvent::Polled {
+                    target: *target,
+                    slot: None,
+                    error: Some(format!("RPC connection failed: {}", e)),
+                });
+            }
             return Err(anyhow::anyhow!("RPC connection failed: {}", e));
         }
     }
 
-    // Track processed signatures to avoid duplicates
-    let mut processed_signatures: HashSet<String> = HashSet::new();
+    // Dedupes processed signatures across polls
+    let mut pipeline = pipeline::Pipeline::new(&config, store)?;
 
     // Main monitoring loop
     loop {
-        match fetch_and_analyze_transactions(
-            &rpc_client,
-            &program_id,
-            &mut processed_signatures,
-        )
-        .await
-        {
-            Ok(warning_count) => {
-                if warning_count > 0 {
-                    info!("");
-                    info!("📊 Processed batch: {} warnings detected", warning_count);
-                    info!("");
+        for target in &targets {
+            match fetch_and_analyze_transactions(&rpc_client, target, &mut pipeline).await {
+                Ok((slot, transactions)) => {
+                    send_event(&events, tui::WatchEvent::Polled {
+                        target: *target,
+                        slot,
+                        error: None,
+                    });
+                    if events.is_some() {
+                        for tx in transactions {
+                            send_event(&events, tui::WatchEvent::Analyzed(tx));
+                        }
+                        continue;
+                    }
+
+                    let warning_count = print_warnings(&transactions);
+                    if warning_count > 0 {
+                        info!("");
+                        info!("📊 Processed batch: {} warnings detected", warning_count);
+                        info!("");
+                    }
+                }
+                Err(e) => {
+                    warn!("⚠️  Error processing transactions: {}", e);
+                    send_event(&events, tui::WatchEvent::Polled {
+                        target: *target,
+                        slot: None,
+                        error: Some(e.to_string()),
+                    });
                 }
-            }
-            Err(e) => {
-                warn!("⚠️  Error processing transactions: {}", e);
             }
         }
 
@@ -143,12 +587,23 @@ async fn run_watch(
     }
 }
 
+/// Forwards a watch event to the dashboard, if one is running
+fn send_event(events: &Option<UnboundedSender<tui::WatchEvent>>, event: tui::WatchEvent) {
+    if let Some(events) = events {
+        // The dashboard has exited if the receiver is gone; the watch task is about to be aborted
+        let _ = events.send(event);
+    }
+}
+
+/// Analyzes the target's most recent unprocessed transactions.
+///
+/// Returns the latest slot the target was seen in, and every transaction analyzed, with or without warnings.
 async fn fetch_and_analyze_transactions(
     rpc_client: &RpcClient,
     program_id: &Pubkey,
-    processed_signatures: &mut HashSet<String>,
-) -> Result<usize> {
-    let mut warning_count = 0;
+    pipeline: &mut pipeline::Pipeline<'_>,
+) -> Result<(Option<u64>, Vec<tui::AnalyzedTransaction>)> {
+    let mut analyzed = Vec::new();
 
     // Fetch recent signatures for the program
     let signatures = rpc_client
@@ -158,7 +613,7 @@ async fn fetch_and_analyze_transactions(
     // Process each signature
     for sig_info in signatures.iter().take(10) {
         // Skip if already processed
-        if processed_signatures.contains(&sig_info.signature) {
+        if pipeline.seen(&sig_info.signature) {
             continue;
         }
 
@@ -171,46 +626,15 @@ async fn fetch_and_analyze_transactions(
             }
         };
 
-        // Fetch transaction details with versioned transaction support
-        let config = RpcTransactionConfig {
-            encoding: Some(UiTransactionEncoding::JsonParsed),
-            commitment: Some(CommitmentConfig::confirmed()),
-            max_supported_transaction_version: Some(0),
-        };
-
-        match rpc_client.get_transaction_with_config(&signature, config) {
+        match fetch_transaction(rpc_client, &signature) {
             Ok(tx) => {
-                // Analyze transaction
-                match detector::analyze_transaction(&tx) {
-                    Ok(warnings) => {
-                        if !warnings.is_empty() {
-                            info!(
-                                "🔍 Transaction: {}",
-                                format!(
-                                    "https://solscan.io/tx/{}",
-                                    sig_info.signature
-                                )
-                                .blue()
-                                .underline()
-                            );
-
-                            for warning in warnings {
-                                print!("{}", warning.format_terminal());
-                                warning_count += 1;
-                            }
-                        }
-                    }
-                    Err(e) => {
+                let accounts = fetch_account_states(rpc_client, &tx);
+                match pipeline.process(program_id, &sig_info.signature, sig_info.slot, &tx, &accounts) {
+                    Some(Ok(transaction)) => analyzed.push(transaction),
+                    Some(Err(e)) => {
                         warn!("Failed to analyze transaction {}: {}", sig_info.signature, e);
                     }
-                }
-
-                // Mark as processed
-                processed_signatures.insert(sig_info.signature.clone());
-
-                // Limit cache size
-                if processed_signatures.len() > 1000 {
-                    processed_signatures.clear();
+                    None => {}
                 }
             }
             Err(e) => {
@@ -219,7 +643,335 @@ async fn fetch_and_analyze_transactions(
         }
     }
 
-    Ok(warning_count)
+    Ok((signatures.first().map(|sig_info| sig_info.slot), analyzed))
+}
+
+/// Prints the warnings of a batch of analyzed transactions, returning how many there were
+fn print_warnings(transactions: &[tui::AnalyzedTransaction]) -> usize {
+    let mut warning_count = 0;
+    for tx in transactions.iter().filter(|tx| !tx.warnings.is_empty()) {
+        info!(
+            "🔍 Transaction: {}",
+            format!("https://solscan.io/tx/{}", tx.signature)
+                .blue()
+                .underline()
+        );
+        for warning in &tx.warnings {
+            print!("{}", warning.format_terminal());
+            warning_count += 1;
+        }
+    }
+    warning_count
+}
+
+/// Feeds recorded transactions through the watch pipeline in order, pacing them by block time.
+/// Warnings are printed and stored but not sent to notifiers.
+///
+/// Account data for close checks is fetched when replaying from a validator, at its current
+/// slot rather than the replayed one, so accounts changed since can be flagged differently.
+async fn run_replay(
+    transactions: Vec<replay::ReplayTransaction>,
+    programs: &[Pubkey],
+    speed: f64,
+    rpc_client: Option<&RpcClient>,
+    config: &config::GuardConfig,
+    store: Option<api::SharedStore>,
+) -> Result<Vec<report::TransactionReport>> {
+    let mut pipeline = pipeline::Pipeline::new(config, store)?;
+    let mut reports = Vec::new();
+    let mut duplicates = 0;
+    let mut previous_block_time = None;
+
+    for entry in &transactions {
+        let Some(target) = replay::target(&entry.transaction, programs) else {
+            continue;
+        };
+        tokio::time::sleep(replay::delay(previous_block_time, entry.transaction.block_time, speed)).await;
+        previous_block_time = entry.transaction.block_time.or(previous_block_time);
+
+        let accounts = match rpc_client {
+            Some(rpc_client) => fetch_account_states(rpc_client, &entry.transaction),
+            None => detector::AccountStates::new(),
+        };
+        match pipeline.process(&target, &entry.signature, entry.transaction.slot, &entry.transaction, &accounts) {
+            Some(Ok(analyzed)) => {
+                print_warnings(std::slice::from_ref(&analyzed));
+                reports.push(report::TransactionReport {
+                    signature: analyzed.signature,
+                    warnings: analyzed.warnings,
+                });
+            }
+            Some(Err(e)) => warn!("Failed to analyze transaction {}: {}", entry.signature, e),
+            None => duplicates += 1,
+        }
+    }
+
+    let warning_count: usize = reports.iter().map(|report| report.warnings.len()).sum();
+    info!("");
+    info!(
+        "📊 Replayed {} transaction(s), {} duplicate(s) skipped: {} warnings detected",
+        reports.len(),
+        duplicates,
+        warning_count
+    );
+
+    Ok(reports)
+}
+
+/// Fetch a transaction in the JSON-parsed encoding the detector expects
+fn fetch_transaction(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    // Fetch transaction details with versioned transaction support
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::JsonParsed),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+
+    Ok(rpc_client.get_transaction_with_config(signature, config)?)
+}
+
+/// Fetch the current data of Token-2022 accounts the transaction closes.
+///
+/// For an unsent transaction this is the state the close will run against. Once a close
+/// has executed the account is gone, so it is missing from the result and the detector
+/// falls back to its generic warning. Fetch errors are logged, not fatal.
+fn fetch_account_states(
+    rpc_client: &RpcClient,
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+) -> detector::AccountStates {
+    let addresses = detector::token_2022_close_targets(tx);
+    if addresses.is_empty() {
+        return detector::AccountStates::new();
+    }
+
+    match rpc_client.get_multiple_accounts_with_commitment(&addresses, CommitmentConfig::processed()) {
+        Ok(response) => addresses
+            .into_iter()
+            .zip(response.value)
+            .filter_map(|(address, account)| Some((address, account?.data)))
+            .collect(),
+        Err(e) => {
+            warn!("Failed to fetch account data for close checks: {}", e);
+            detector::AccountStates::new()
+        }
+    }
+}
+
+fn run_analyze(
+    signatures: &[String],
+    files: &[PathBuf],
+    rpc_url: String,
+    config: &config::GuardConfig,
+) -> Result<Vec<report::TransactionReport>> {
+    let mut transactions = Vec::new();
+    let rpc_client = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+
+    for path in files {
+        let tx = fixture::read_transaction(path)?;
+        let signature =
+            transaction_signature(&tx).unwrap_or_else(|| path.display().to_string());
+        // Saved `<name>.accounts.json` data wins; otherwise the current state is what an
+        // unsent transaction's closes will see
+        let accounts = match fixture::read_transaction_accounts(path)? {
+            Some(accounts) => accounts,
+            None => fetch_account_states(&rpc_client, &tx),
+        };
+        transactions.push((signature, tx, accounts));
+    }
+
+    if !signatures.is_empty() {
+        for signature in signatures {
+            let parsed = Signature::from_str(signature)
+                .map_err(|e| anyhow::anyhow!("Invalid signature {}: {}", signature, e))?;
+            let tx = fetch_transaction(&rpc_client, &parsed)
+                .map_err(|e| anyhow::anyhow!("Failed to fetch transaction {}: {}", signature, e))?;
+            let accounts = fetch_account_states(&rpc_client, &tx);
+            transactions.push((signature.clone(), tx, accounts));
+        }
+    }
+
+    let mut treasury_monitor = treasury::TreasuryMonitor::new(&config.treasury)?;
+    let mut reports = Vec::new();
+    for (signature, tx, accounts) in transactions {
+        let mut warnings = detector::analyze_transaction(&tx, config, &accounts)?;
+        warnings.extend(treasury_monitor.observe_transaction(&tx)?);
+        if warnings.is_empty() {
+            info!("✅ {}: no warnings", signature);
+        } else {
+            info!("🔍 Transaction: {}", signature.blue());
+            for warning in &warnings {
+                print!("{}", warning.format_terminal());
+            }
+        }
+        reports.push(report::TransactionReport {
+            signature,
+            warnings,
+        });
+    }
+
+    Ok(reports)
+}
+
+/// First signature of a transaction, when it was encoded as JSON
+fn transaction_signature(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Option<String> {
+    match &tx.transaction.transaction {
+        EncodedTransaction::Json(ui_tx) => ui_tx.signatures.first().cloned(),
+        _ => None,
+    }
+}
+
+fn run_snapshot(addresses: &[Pubkey], rpc_url: String, out: &std::path::Path) -> Result<()> {
+    let rpc_client = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+
+    let accounts = drift::snapshot_accounts(&rpc_client, addresses)?;
+    for account in &accounts {
+        info!("📸 {} ({:?})", account.address.cyan(), account.kind);
+        if account.kind == drift::AccountKind::Missing {
+            warn!("⚠️  Account {} does not exist", account.address);
+        }
+    }
+
+    let baseline = drift::Baseline {
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64,
+        accounts,
+    };
+    baseline.save(out)?;
+    info!("💾 Baseline saved: {}", out.display().to_string().blue());
+
+    Ok(())
+}
+
+async fn run_drift(
+    baseline_path: &std::path::Path,
+    rpc_url: String,
+    ws_url: Option<String>,
+    poll_interval: u64,
+) -> Result<()> {
+    let baseline = drift::Baseline::load(baseline_path)?;
+    let addresses = baseline.addresses()?;
+    let mut tracker = drift::DriftTracker::new(&baseline);
+
+    let rpc_client = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+    let current = drift::snapshot_accounts(&rpc_client, &addresses)?;
+    report_drift(tracker.update(current));
+    info!("🔍 Monitoring {} account(s) for drift...", addresses.len());
+    info!("ℹ️  Press Ctrl+C to stop");
+    info!("");
+
+    // Subscribe to the accounts themselves and to any ProgramData accounts,
+    // since upgrade authority changes only touch the latter
+    let pubsub_client = match &ws_url {
+        Some(ws_url) => Some(
+            PubsubClient::new(ws_url)
+                .await
+                .map_err(|e| anyhow::anyhow!("WebSocket connection failed: {}", e))?,
+        ),
+        None => None,
+    };
+    let mut notifications = futures::stream::SelectAll::new();
+    if let Some(pubsub_client) = &pubsub_client {
+        let mut subscribed = addresses.clone();
+        subscribed.extend(drift::programdata_addresses(&baseline.accounts));
+        for address in &subscribed {
+            let (stream, _unsubscribe) = pubsub_client
+                .account_subscribe(address, None)
+                .await
+                .map_err(|e| anyhow::anyhow!("accountSubscribe failed for {}: {}", address, e))?;
+            notifications.push(stream);
+        }
+        info!("🔌 Subscribed to {} account(s)", subscribed.len());
+    }
+
+    loop {
+        tokio::select! {
+            Some(_) = notifications.next(), if !notifications.is_empty() => {}
+            _ = tokio::time::sleep(tokio::time::Duration::from_secs(poll_interval)) => {}
+        }
+
+        match drift::snapshot_accounts(&rpc_client, &addresses) {
+            Ok(current) => report_drift(tracker.update(current)),
+            Err(e) => warn!("⚠️  Error snapshotting accounts: {}", e),
+        }
+    }
+}
+
+fn report_drift(changes: Vec<drift::DriftChange>) {
+    for change in changes {
+        if change.warnings.is_empty() {
+            info!("✅ {} is back to its baseline state", change.address.cyan());
+            continue;
+        }
+
+        info!("🔍 Drift detected: {}", change.address.cyan());
+        for warning in change.warnings {
+            print!("{}", warning.format_terminal());
+        }
+    }
+}
+
+fn run_record(
+    signature: &Signature,
+    rpc_url: String,
+    out_dir: &std::path::Path,
+    name: &str,
+) -> Result<()> {
+    let rpc_client = RpcClient::new_with_commitment(rpc_url, CommitmentConfig::confirmed());
+
+    info!("📥 Recording transaction: {}", signature.to_string().cyan());
+    let tx = fetch_transaction(&rpc_client, signature)
+        .map_err(|e| anyhow::anyhow!("Failed to fetch transaction {}: {}", signature, e))?;
+
+    let accounts = fetch_account_states(&rpc_client, &tx);
+    let snapshot = fixture::save(out_dir, name, &tx, &accounts)?;
+
+    info!("💾 Fixture saved: {}", out_dir.join(format!("{}.json", name)).display().to_string().blue());
+    info!("📸 Snapshot: {} warning(s) recorded as expected", snapshot.len());
+    for warning in &snapshot {
+        info!("   {}: {}", warning.pattern_id.name().bold(), warning.message);
+    }
+    info!("ℹ️  Review the snapshot before committing it");
+
+    Ok(())
+}
+
+fn run_fixtures(dir: &std::path::Path, update: bool) -> Result<()> {
+    let outcomes = fixture::verify_dir(dir, update)?;
+    let failed = outcomes.iter().filter(|outcome| !outcome.passed()).count();
+
+    for outcome in &outcomes {
+        if outcome.passed() {
+            info!("✅ {}", outcome.name);
+        } else if update {
+            info!("📸 {} {}", outcome.name, "(snapshot updated)".yellow());
+        } else {
+            info!("❌ {}", outcome.name.red());
+            info!("   expected: {}", serde_json::to_string(&outcome.expected)?);
+            info!("   actual:   {}", serde_json::to_string(&outcome.actual)?);
+        }
+    }
+
+    info!("");
+    info!("📊 {} fixture(s), {} mismatched", outcomes.len(), failed);
+
+    if failed > 0 && !update {
+        return Err(anyhow::anyhow!("{} fixture(s) did not match their snapshots", failed));
+    }
+
+    Ok(())
+}
+
+/// Derive the WebSocket endpoint that accompanies an RPC endpoint
+fn get_default_ws_url(rpc_url: &str) -> String {
+    let ws_url = rpc_url
+        .replacen("https://", "wss://", 1)
+        .replacen("http://", "ws://", 1);
+    // solana-test-validator serves WebSockets on the RPC port + 1
+    ws_url.replace(":8899", ":8900")
 }
 
 fn get_default_rpc_url(env: &str) -> String {
@@ -0,0 +1,201 @@
+use crate::detector::{PatternId, Severity, Warning};
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{parse_instruction::ParsedInstruction, UiInstruction, UiParsedInstruction};
+use std::collections::HashSet;
+
+/// Thresholds for the durable nonce rules
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NonceRules {
+    /// Smallest SOL transfer, in lamports, treated as large in a durable nonce transaction
+    pub large_transfer_lamports: u64,
+    /// Smallest SPL token transfer, in base units, treated as large; unset disables the check
+    pub large_token_transfer: Option<u64>,
+}
+
+impl Default for NonceRules {
+    fn default() -> Self {
+        Self {
+            large_transfer_lamports: 10_000_000_000,
+            large_token_transfer: None,
+        }
+    }
+}
+
+/// P-107: Durable Nonce
+///
+/// A transaction whose first instruction advances a nonce account stays valid until the
+/// nonce moves, so it can be signed now and submitted at any later time. Such transactions
+/// are reported, and escalated when they also change an authority or move a large amount.
+/// Nonce authority changes are reported whether or not the transaction uses a nonce.
+///
+/// `instructions` are the top-level instructions and `inner` those run through CPI.
+pub fn analyze(
+    instructions: &[UiInstruction],
+    inner: &[&UiInstruction],
+    signers: &HashSet<Pubkey>,
+    rules: &NonceRules,
+) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let parsed: Vec<&ParsedInstruction> = instructions
+        .iter()
+        .chain(inner.iter().copied())
+        .filter_map(|instruction| match instruction {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => Some(parsed),
+            _ => None,
+        })
+        .collect();
+
+    for ix in parsed.iter().filter(|ix| is(ix, "system", "authorizeNonce")) {
+        let info = &ix.parsed["info"];
+        let new_authority = info["newAuthorized"].as_str().unwrap_or_default();
+        let signed = new_authority.parse::<Pubkey>().is_ok_and(|key| signers.contains(&key));
+        warnings.push(Warning {
+            pattern_id: PatternId::P107DurableNonce,
+            severity: if signed { Severity::Warning } else { Severity::Critical },
+            message: format!(
+                "Nonce authority changed to {}{}. The new authority can submit any transaction pre-signed against this nonce.",
+                new_authority,
+                if signed { "" } else { ", a wallet that did not sign this transaction" }
+            ),
+            affected_account: account(info, "nonceAccount"),
+        });
+    }
+
+    let nonce_account = match instructions.first() {
+        Some(UiInstruction::Parsed(UiParsedInstruction::Parsed(first))) if is(first, "system", "advanceNonce") => {
+            account(&first.parsed["info"], "nonceAccount")
+        }
+        _ => return warnings,
+    };
+
+    let mut risks = Vec::new();
+    if parsed.iter().any(|ix| is_authority_change(ix)) {
+        risks.push("changes an authority".to_string());
+    }
+    if let Some(amount) = parsed.iter().filter_map(|ix| large_transfer(ix, rules)).max() {
+        risks.push(format!("transfers {}", amount));
+    }
+
+    warnings.push(if risks.is_empty() {
+        Warning {
+            pattern_id: PatternId::P107DurableNonce,
+            severity: Severity::Alert,
+            message: "Transaction uses a durable nonce and may have been signed long before it was submitted.".to_string(),
+            affected_account: nonce_account,
+        }
+    } else {
+        Warning {
+            pattern_id: PatternId::P107DurableNonce,
+            severity: Severity::Critical,
+            message: format!(
+                "Durable nonce transaction {}. Pre-signed transactions like this are a common social-engineering vector: confirm the signer intended it to run now.",
+                risks.join(" and ")
+            ),
+            affected_account: nonce_account,
+        }
+    });
+
+    warnings
+}
+
+fn is(ix: &ParsedInstruction, program: &str, instruction_type: &str) -> bool {
+    ix.program == program && ix.parsed["type"].as_str() == Some(instruction_type)
+}
+
+fn is_authority_change(ix: &ParsedInstruction) -> bool {
+    let instruction_type = ix.parsed["type"].as_str().unwrap_or_default();
+    match ix.program.as_str() {
+        "spl-token" | "spl-token-2022" => instruction_type == "setAuthority",
+        "system" => matches!(instruction_type, "authorizeNonce" | "assign" | "assignWithSeed"),
+        "bpf-upgradeable-loader" => matches!(instruction_type, "setAuthority" | "setAuthorityChecked"),
+        _ => false,
+    }
+}
+
+/// Describes the transfer if it meets the configured thresholds
+fn large_transfer(ix: &ParsedInstruction, rules: &NonceRules) -> Option<String> {
+    let info = &ix.parsed["info"];
+    match (ix.program.as_str(), ix.parsed["type"].as_str()?) {
+        ("system", "transfer" | "transferWithSeed") => {
+            let lamports = info["lamports"].as_u64()?;
+            (lamports >= rules.large_transfer_lamports)
+                .then(|| format!("{} SOL", lamports as f64 / 1_000_000_000.0))
+        }
+        ("spl-token" | "spl-token-2022", "transfer" | "transferChecked") => {
+            let threshold = rules.large_token_transfer?;
+            let amount = info["amount"]
+                .as_str()
+                .or_else(|| info["tokenAmount"]["amount"].as_str())?
+                .parse::<u64>()
+                .ok()?;
+            (amount >= threshold).then(|| format!("{} token base units", amount))
+        }
+        _ => None,
+    }
+}
+
+fn account(info: &Value, field: &str) -> Option<Pubkey> {
+    info[field].as_str().and_then(|key| key.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parsed(program: &str, instruction_type: &str, info: Value) -> UiInstruction {
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(ParsedInstruction {
+            program: program.to_string(),
+            program_id: Pubkey::new_unique().to_string(),
+            parsed: json!({ "type": instruction_type, "info": info }),
+            stack_height: None,
+        }))
+    }
+
+    fn advance_nonce(nonce: &Pubkey) -> UiInstruction {
+        parsed("system", "advanceNonce", json!({ "nonceAccount": nonce.to_string() }))
+    }
+
+    #[test]
+    fn test_durable_nonce_escalation() {
+        let nonce = Pubkey::new_unique();
+        let rules = NonceRules::default();
+        let small = parsed("system", "transfer", json!({ "lamports": 1_000_000 }));
+        let large = parsed("system", "transfer", json!({ "lamports": 50_000_000_000u64 }));
+
+        let warnings = analyze(&[advance_nonce(&nonce), small.clone()], &[], &HashSet::new(), &rules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Alert);
+        assert_eq!(warnings[0].affected_account, Some(nonce));
+
+        let warnings = analyze(&[advance_nonce(&nonce)], &[&large], &HashSet::new(), &rules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Critical);
+        assert!(warnings[0].message.contains("50 SOL"));
+
+        // Only a nonce advanced by the first instruction makes the transaction durable
+        assert!(analyze(&[small, advance_nonce(&nonce)], &[], &HashSet::new(), &rules).is_empty());
+    }
+
+    #[test]
+    fn test_nonce_authority_change() {
+        let new_authority = Pubkey::new_unique();
+        let authorize = parsed(
+            "system",
+            "authorizeNonce",
+            json!({ "nonceAccount": Pubkey::new_unique().to_string(), "newAuthorized": new_authority.to_string() }),
+        );
+
+        let warnings = analyze(std::slice::from_ref(&authorize), &[], &HashSet::new(), &NonceRules::default());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].pattern_id, PatternId::P107DurableNonce);
+        assert_eq!(warnings[0].severity, Severity::Critical);
+
+        let signers = HashSet::from([new_authority]);
+        let warnings = analyze(&[authorize], &[], &signers, &NonceRules::default());
+        assert_eq!(warnings[0].severity, Severity::Warning);
+    }
+}
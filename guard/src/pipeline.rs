@@ -0,0 +1,102 @@
+use crate::api::SharedStore;
+use crate::config::GuardConfig;
+use crate::detector::{self, AccountStates};
+use crate::treasury::TreasuryMonitor;
+use crate::tui::AnalyzedTransaction;
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::HashSet;
+
+/// Signatures remembered for dedupe before the cache is reset
+const PROCESSED_CAPACITY: usize = 1000;
+
+/// Per-transaction stages shared by `guard watch` and `guard replay`: dedupe, the
+/// detector, treasury correlation across transactions, and the alert store
+pub struct Pipeline<'a> {
+    config: &'a GuardConfig,
+    processed_signatures: HashSet<String>,
+    treasury_monitor: TreasuryMonitor,
+    store: Option<SharedStore>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new(config: &'a GuardConfig, store: Option<SharedStore>) -> Result<Self> {
+        Ok(Self {
+            config,
+            processed_signatures: HashSet::new(),
+            treasury_monitor: TreasuryMonitor::new(&config.treasury)?,
+            store,
+        })
+    }
+
+    /// Whether the signature has already been through the pipeline
+    pub fn seen(&self, signature: &str) -> bool {
+        self.processed_signatures.contains(signature)
+    }
+
+    /// Runs one transaction through every stage.
+    ///
+    /// Returns None for a duplicate. Suppressed warnings are dropped when a store is configured.
+    pub fn process(
+        &mut self,
+        target: &Pubkey,
+        signature: &str,
+        slot: u64,
+        tx: &EncodedConfirmedTransactionWithStatusMeta,
+        accounts: &AccountStates,
+    ) -> Option<Result<AnalyzedTransaction>> {
+        if self.seen(signature) {
+            return None;
+        }
+        if self.processed_signatures.len() >= PROCESSED_CAPACITY {
+            self.processed_signatures.clear();
+        }
+        self.processed_signatures.insert(signature.to_string());
+
+        let analysis = detector::analyze_transaction(tx, self.config, accounts).and_then(|mut warnings| {
+            warnings.extend(self.treasury_monitor.observe_transaction(tx)?);
+            match &self.store {
+                Some(store) => store
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("Alert store lock poisoned"))?
+                    .record(target, signature, slot, tx, warnings),
+                None => Ok(warnings),
+            }
+        });
+
+        Some(analysis.map(|warnings| AnalyzedTransaction {
+            target: *target,
+            signature: signature.to_string(),
+            slot,
+            warnings,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::AlertStore;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_dedupe_and_store() {
+        let tx = crate::fixture::read_transaction(Path::new("tests/fixtures/p104_close_account.json")).unwrap();
+        let config = GuardConfig::default();
+        let store: SharedStore = Arc::new(Mutex::new(AlertStore::open_in_memory().unwrap()));
+        let mut pipeline = Pipeline::new(&config, Some(store.clone())).unwrap();
+        let target = Pubkey::new_unique();
+
+        let analyzed = pipeline
+            .process(&target, "sig", 1, &tx, &AccountStates::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(analyzed.warnings.len(), 1);
+        assert!(pipeline.process(&target, "sig", 1, &tx, &AccountStates::new()).is_none());
+
+        let recorded = store.lock().unwrap().alerts(&Default::default()).unwrap();
+        assert_eq!(recorded.len(), 1);
+    }
+}
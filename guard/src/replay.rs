@@ -0,0 +1,180 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcBlockConfig};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, vote};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, TransactionDetails, UiInstruction,
+    UiMessage, UiParsedInstruction, UiTransactionEncoding,
+};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Longest pause between two replayed transactions, whatever the gap in block time
+const MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// A recorded transaction to feed back through the watch pipeline
+pub struct ReplayTransaction {
+    pub signature: String,
+    pub transaction: EncodedConfirmedTransactionWithStatusMeta,
+}
+
+/// One line of a replay file
+#[derive(Deserialize)]
+struct ReplayLine {
+    signature: Option<String>,
+    transaction: EncodedConfirmedTransactionWithStatusMeta,
+}
+
+/// Reads a JSON-lines file where each line is `{"signature": ..., "transaction": ...}`
+/// and `transaction` is a `getTransaction` response in JSON-parsed encoding.
+///
+/// The signature defaults to the transaction's first signature. Blank lines are skipped.
+pub fn read_jsonl(path: &Path) -> Result<Vec<ReplayTransaction>> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let mut transactions = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line: ReplayLine = serde_json::from_str(line)
+            .with_context(|| format!("Invalid replay entry at {}:{}", path.display(), index + 1))?;
+        let signature = line
+            .signature
+            .or_else(|| first_signature(&line.transaction))
+            .with_context(|| format!("Replay entry at {}:{} has no signature", path.display(), index + 1))?;
+        transactions.push(ReplayTransaction {
+            signature,
+            transaction: line.transaction,
+        });
+    }
+    Ok(transactions)
+}
+
+/// Reads every non-vote transaction in a slot range from a local validator, such as
+/// `solana-test-validator --ledger <DIR>` started on a ledger snapshot.
+///
+/// `end_slot` defaults to the validator's current slot.
+pub fn read_validator(rpc_client: &RpcClient, start_slot: u64, end_slot: Option<u64>) -> Result<Vec<ReplayTransaction>> {
+    let end_slot = match end_slot {
+        Some(slot) => slot,
+        None => rpc_client.get_slot().context("Failed to fetch the validator's slot")?,
+    };
+    let slots = rpc_client
+        .get_blocks(start_slot, Some(end_slot))
+        .with_context(|| format!("Failed to list blocks {}..={}", start_slot, end_slot))?;
+
+    let config = RpcBlockConfig {
+        encoding: Some(UiTransactionEncoding::JsonParsed),
+        transaction_details: Some(TransactionDetails::Full),
+        rewards: Some(false),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+
+    let mut transactions = Vec::new();
+    for slot in slots {
+        let block = rpc_client
+            .get_block_with_config(slot, config)
+            .with_context(|| format!("Failed to fetch block {}", slot))?;
+        for transaction in block.transactions.into_iter().flatten() {
+            let transaction = EncodedConfirmedTransactionWithStatusMeta {
+                slot,
+                transaction,
+                block_time: block.block_time,
+            };
+            if is_vote(&transaction) {
+                continue;
+            }
+            if let Some(signature) = first_signature(&transaction) {
+                transactions.push(ReplayTransaction { signature, transaction });
+            }
+        }
+    }
+    Ok(transactions)
+}
+
+/// The watched program a transaction belongs to: the first top-level instruction's
+/// program that is in `programs`, or the first instruction's program when `programs` is empty
+pub fn target(tx: &EncodedConfirmedTransactionWithStatusMeta, programs: &[Pubkey]) -> Option<Pubkey> {
+    instruction_programs(tx)
+        .into_iter()
+        .find(|program| programs.is_empty() || programs.contains(program))
+}
+
+/// How long to wait before replaying a transaction, given the block times of it and
+/// the one before, compressed by `speed`. A speed of zero replays without pausing.
+pub fn delay(previous: Option<i64>, current: Option<i64>, speed: f64) -> Duration {
+    match (previous, current) {
+        (Some(previous), Some(current)) if speed > 0.0 && current > previous => {
+            Duration::from_secs_f64((current - previous) as f64 / speed).min(MAX_DELAY)
+        }
+        _ => Duration::ZERO,
+    }
+}
+
+fn first_signature(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Option<String> {
+    match &tx.transaction.transaction {
+        EncodedTransaction::Json(ui_tx) => ui_tx.signatures.first().cloned(),
+        _ => None,
+    }
+}
+
+fn is_vote(tx: &EncodedConfirmedTransactionWithStatusMeta) -> bool {
+    let programs = instruction_programs(tx);
+    !programs.is_empty() && programs.iter().all(|program| *program == vote::program::id())
+}
+
+fn instruction_programs(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Vec<Pubkey> {
+    let EncodedTransaction::Json(ui_tx) = &tx.transaction.transaction else {
+        return Vec::new();
+    };
+    let UiMessage::Parsed(message) = &ui_tx.message else {
+        return Vec::new();
+    };
+
+    message
+        .instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => Some(parsed.program_id.as_str()),
+            UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(partial)) => Some(partial.program_id.as_str()),
+            UiInstruction::Compiled(_) => None,
+        })
+        .filter_map(|program_id| program_id.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_jsonl() {
+        let tx = crate::fixture::read_transaction(Path::new("tests/fixtures/p104_close_account.json")).unwrap();
+        let path = std::env::temp_dir().join(format!("guard-replay-{}.jsonl", std::process::id()));
+        let lines = [
+            serde_json::json!({ "transaction": tx }).to_string(),
+            String::new(),
+            serde_json::json!({ "signature": "renamed", "transaction": tx }).to_string(),
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+        let transactions = read_jsonl(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].signature, first_signature(&tx).unwrap());
+        assert_eq!(transactions[1].signature, "renamed");
+        assert_eq!(target(&tx, &[]), Some(spl_token::id()));
+        assert_eq!(target(&tx, &[Pubkey::new_unique()]), None);
+    }
+
+    #[test]
+    fn test_delay() {
+        assert_eq!(delay(Some(100), Some(160), 60.0), Duration::from_secs(1));
+        assert_eq!(delay(Some(100), Some(100_000), 1.0), MAX_DELAY);
+        assert_eq!(delay(Some(100), Some(160), 0.0), Duration::ZERO);
+        assert_eq!(delay(None, Some(160), 60.0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::detector::{PatternId, Severity, Warning};
+use serde_json::json;
+
+/// Machine-readable report formats for CI pipelines
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// JUnit XML, one test case per analyzed transaction
+    Junit,
+    /// SARIF 2.1.0, one result per warning
+    Sarif,
+}
+
+/// Warnings found in a single analyzed transaction
+pub struct TransactionReport {
+    pub signature: String,
+    pub warnings: Vec<Warning>,
+}
+
+/// Highest severity across all reports, if any warning was found
+pub fn highest_severity(reports: &[TransactionReport]) -> Option<Severity> {
+    reports
+        .iter()
+        .flat_map(|report| report.warnings.iter().map(|warning| warning.severity))
+        .max()
+}
+
+/// Exit code for CI gate mode.
+///
+/// Returns 0 unless the highest severity found reaches `fail_on`, in which
+/// case the code identifies that highest severity.
+pub fn exit_code(reports: &[TransactionReport], fail_on: Option<Severity>) -> i32 {
+    match (fail_on, highest_severity(reports)) {
+        (Some(threshold), Some(highest)) if highest >= threshold => highest.exit_code(),
+        _ => 0,
+    }
+}
+
+/// Render reports in the given format.
+///
+/// Warnings at or above `fail_on` are reported as failures; without a
+/// threshold every warning counts.
+pub fn render(format: ReportFormat, reports: &[TransactionReport], fail_on: Option<Severity>) -> String {
+    let threshold = fail_on.unwrap_or(Severity::Alert);
+    match format {
+        ReportFormat::Junit => render_junit(reports, threshold),
+        ReportFormat::Sarif => render_sarif(reports),
+    }
+}
+
+fn render_junit(reports: &[TransactionReport], threshold: Severity) -> String {
+    let failures = reports
+        .iter()
+        .filter(|report| report.warnings.iter().any(|w| w.severity >= threshold))
+        .count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"guard\" tests=\"{}\" failures=\"{}\">\n",
+        reports.len(),
+        failures
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"guard\" tests=\"{}\" failures=\"{}\">\n",
+        reports.len(),
+        failures
+    ));
+
+    for report in reports {
+        let failing: Vec<&Warning> = report
+            .warnings
+            .iter()
+            .filter(|w| w.severity >= threshold)
+            .collect();
+
+        xml.push_str(&format!(
+            "    <testcase classname=\"guard.analyze\" name=\"{}\"",
+            escape_xml(&report.signature)
+        ));
+        if failing.is_empty() {
+            xml.push_str(" />\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        for warning in failing {
+            xml.push_str(&format!(
+                "      <failure type=\"{}\" message=\"{}\">{}</failure>\n",
+                escape_xml(&format!("{:?}", warning.severity).to_uppercase()),
+                escape_xml(warning.pattern_id.name()),
+                escape_xml(&failure_body(warning)),
+            ));
+        }
+        xml.push_str("    </testcase>\n");
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn failure_body(warning: &Warning) -> String {
+    match warning.affected_account {
+        Some(account) => format!("{}\nAffected Account: {}", warning.message, account),
+        None => warning.message.clone(),
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn render_sarif(reports: &[TransactionReport]) -> String {
+    let mut patterns: Vec<&PatternId> = Vec::new();
+    for warning in reports.iter().flat_map(|report| report.warnings.iter()) {
+        if !patterns.contains(&&warning.pattern_id) {
+            patterns.push(&warning.pattern_id);
+        }
+    }
+
+    let rules: Vec<_> = patterns
+        .iter()
+        .map(|pattern| {
+            json!({
+                "id": pattern.code(),
+                "name": pattern.name(),
+                "shortDescription": { "text": pattern.name() },
+            })
+        })
+        .collect();
+
+    let results: Vec<_> = reports
+        .iter()
+        .flat_map(|report| {
+            report.warnings.iter().map(move |warning| {
+                let mut locations = vec![json!({
+                    "name": report.signature,
+                    "kind": "transaction",
+                })];
+                if let Some(account) = warning.affected_account {
+                    locations.push(json!({
+                        "name": account.to_string(),
+                        "kind": "account",
+                    }));
+                }
+
+                json!({
+                    "ruleId": warning.pattern_id.code(),
+                    "level": sarif_level(warning.severity),
+                    "message": { "text": warning.message },
+                    "locations": [{ "logicalLocations": locations }],
+                    "properties": { "signature": report.signature },
+                })
+            })
+        })
+        .collect();
+
+    let sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "guard",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/fabrknt/fabrknt-sdk/tree/main/guard",
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }],
+    });
+
+    serde_json::to_string_pretty(&sarif).unwrap_or_default() + "\n"
+}
+
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "error",
+        Severity::Warning => "warning",
+        Severity::Alert => "note",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(signature: &str, severities: &[Severity]) -> TransactionReport {
+        TransactionReport {
+            signature: signature.to_string(),
+            warnings: severities
+                .iter()
+                .map(|severity| Warning {
+                    pattern_id: PatternId::P104DangerousClose,
+                    severity: *severity,
+                    message: "Closing <account> & \"friends\"".to_string(),
+                    affected_account: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_exit_code_uses_highest_severity() {
+        let reports = vec![
+            report("a", &[Severity::Alert]),
+            report("b", &[Severity::Warning, Severity::Alert]),
+        ];
+
+        assert_eq!(highest_severity(&reports), Some(Severity::Warning));
+        assert_eq!(exit_code(&reports, None), 0);
+        assert_eq!(exit_code(&reports, Some(Severity::Critical)), 0);
+        assert_eq!(exit_code(&reports, Some(Severity::Warning)), 11);
+        assert_eq!(exit_code(&reports, Some(Severity::Alert)), 11);
+        assert_eq!(exit_code(&[report("c", &[])], Some(Severity::Alert)), 0);
+    }
+
+    #[test]
+    fn test_junit_marks_failures_at_threshold() {
+        let reports = vec![report("clean", &[]), report("sig", &[Severity::Warning])];
+
+        let xml = render(ReportFormat::Junit, &reports, Some(Severity::Warning));
+        assert!(xml.contains("tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<testcase classname=\"guard.analyze\" name=\"clean\" />"));
+        assert!(xml.contains("Closing &lt;account&gt; &amp; &quot;friends&quot;"));
+
+        let xml = render(ReportFormat::Junit, &reports, Some(Severity::Critical));
+        assert!(xml.contains("failures=\"0\""));
+    }
+
+    #[test]
+    fn test_sarif_results() {
+        let reports = vec![report("sig", &[Severity::Critical, Severity::Alert])];
+
+        let sarif: serde_json::Value =
+            serde_json::from_str(&render(ReportFormat::Sarif, &reports, None)).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 1);
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "P-104");
+        assert_eq!(run["results"][0]["level"], "error");
+        assert_eq!(run["results"][1]["level"], "note");
+        assert_eq!(run["results"][0]["properties"]["signature"], "sig");
+    }
+}
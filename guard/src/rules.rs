@@ -0,0 +1,245 @@
+use crate::detector::{PatternId, Severity, Warning};
+use crate::idl::{to_snake_case, DecodedInstruction, IdlRegistry};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+
+/// A config-defined rule matched against instructions decoded with an IDL.
+///
+/// The rule fires when every condition holds; a rule without conditions
+/// fires on every call of the instruction.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IdlRule {
+    pub id: String,
+    /// Program ID the rule applies to
+    pub program: String,
+    /// Instruction name, in snake_case or camelCase
+    pub instruction: String,
+    pub severity: Severity,
+    pub message: String,
+    /// IDL account name reported as the affected account
+    #[serde(default)]
+    pub affected_account: Option<String>,
+    #[serde(default)]
+    pub conditions: Vec<RuleCondition>,
+}
+
+/// A predicate on one decoded argument or account.
+///
+/// `arg` is a dot-separated path into the decoded arguments (e.g.
+/// `params.delay_seconds`); `account` is an IDL account name. Exactly one
+/// of the two must be set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RuleCondition {
+    pub arg: Option<String>,
+    pub account: Option<String>,
+    /// Whether the value, as a pubkey, must (or must not) be a signer of the outer transaction.
+    /// PDAs that sign a CPI with `invoke_signed` are not transaction signers.
+    pub signer: Option<bool>,
+    pub equals: Option<Value>,
+    pub not_equals: Option<Value>,
+    pub greater_than: Option<Value>,
+    pub less_than: Option<Value>,
+    pub one_of: Option<Vec<Value>>,
+    pub none_of: Option<Vec<Value>>,
+}
+
+impl RuleCondition {
+    fn matches(&self, decoded: &DecodedInstruction, signers: &HashSet<Pubkey>) -> bool {
+        let value = match (&self.arg, &self.account) {
+            (Some(path), None) => lookup(&decoded.args, path),
+            (None, Some(name)) => decoded.accounts.get(&to_snake_case(name)),
+            _ => None,
+        };
+        let Some(value) = value else {
+            return false;
+        };
+
+        if let Some(expected) = self.signer {
+            let is_signer = value
+                .as_str()
+                .and_then(|key| key.parse::<Pubkey>().ok())
+                .is_some_and(|key| signers.contains(&key));
+            if is_signer != expected {
+                return false;
+            }
+        }
+        if self.equals.as_ref().is_some_and(|expected| !values_equal(value, expected)) {
+            return false;
+        }
+        if self.not_equals.as_ref().is_some_and(|expected| values_equal(value, expected)) {
+            return false;
+        }
+        if let Some(bound) = &self.greater_than {
+            if !matches!((as_number(value), as_number(bound)), (Some(v), Some(b)) if v > b) {
+                return false;
+            }
+        }
+        if let Some(bound) = &self.less_than {
+            if !matches!((as_number(value), as_number(bound)), (Some(v), Some(b)) if v < b) {
+                return false;
+            }
+        }
+        if let Some(allowed) = &self.one_of {
+            if !allowed.iter().any(|expected| values_equal(value, expected)) {
+                return false;
+            }
+        }
+        if let Some(denied) = &self.none_of {
+            if denied.iter().any(|expected| values_equal(value, expected)) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Checks that every rule is well-formed and refers to an instruction in a loaded IDL
+pub fn validate(rules: &[IdlRule], registry: &IdlRegistry) -> Result<()> {
+    for rule in rules {
+        let idl = registry
+            .get(&rule.program)
+            .ok_or_else(|| anyhow!("Rule {} refers to program {} with no IDL loaded", rule.id, rule.program))?;
+        if !idl.has_instruction(&rule.instruction) {
+            return Err(anyhow!(
+                "Rule {} refers to instruction {} not found in the {} IDL",
+                rule.id,
+                rule.instruction,
+                idl.name
+            ));
+        }
+        for condition in &rule.conditions {
+            if condition.arg.is_some() == condition.account.is_some() {
+                return Err(anyhow!(
+                    "Rule {} has a condition that must set exactly one of `arg` or `account`",
+                    rule.id
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Evaluates the config rules against a decoded instruction (P-401)
+pub fn evaluate(decoded: &DecodedInstruction, signers: &HashSet<Pubkey>, rules: &[IdlRule]) -> Vec<Warning> {
+    rules
+        .iter()
+        .filter(|rule| {
+            rule.program == decoded.program_id
+                && to_snake_case(&rule.instruction) == decoded.name
+                && rule.conditions.iter().all(|condition| condition.matches(decoded, signers))
+        })
+        .map(|rule| Warning {
+            pattern_id: PatternId::P401IdlRule,
+            severity: rule.severity,
+            message: format!("[{}] {} (`{}`)", rule.id, rule.message, decoded.name),
+            affected_account: rule
+                .affected_account
+                .as_ref()
+                .and_then(|name| decoded.accounts.get(&to_snake_case(name)))
+                .and_then(Value::as_str)
+                .and_then(|key| key.parse().ok()),
+        })
+        .collect()
+}
+
+fn lookup<'a>(args: &'a serde_json::Map<String, Value>, path: &str) -> Option<&'a Value> {
+    let mut segments = path.split('.');
+    let mut value = args.get(&to_snake_case(segments.next()?))?;
+    for segment in segments {
+        value = match value {
+            Value::Object(object) => object.get(&to_snake_case(segment))?,
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// Compares values, treating numbers and numeric strings (used for 128-bit integers) alike
+fn values_equal(value: &Value, expected: &Value) -> bool {
+    match (as_number(value), as_number(expected)) {
+        (Some(a), Some(b)) => a == b,
+        _ => value == expected,
+    }
+}
+
+fn as_number(value: &Value) -> Option<i128> {
+    match value {
+        Value::Number(number) => number
+            .as_i64()
+            .map(i128::from)
+            .or_else(|| number.as_u64().map(i128::from)),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decoded(new_admin: &Pubkey, delay: i64) -> DecodedInstruction {
+        DecodedInstruction {
+            program_id: "Prog111111111111111111111111111111111111111".to_string(),
+            name: "set_admin".to_string(),
+            args: json!({ "new_admin": new_admin.to_string(), "params": { "delay_seconds": delay } })
+                .as_object()
+                .unwrap()
+                .clone(),
+            accounts: json!({ "config": Pubkey::new_unique().to_string() })
+                .as_object()
+                .unwrap()
+                .clone(),
+        }
+    }
+
+    fn rule(conditions: Vec<RuleCondition>) -> IdlRule {
+        IdlRule {
+            id: "set-admin".to_string(),
+            program: "Prog111111111111111111111111111111111111111".to_string(),
+            instruction: "setAdmin".to_string(),
+            severity: Severity::Critical,
+            message: "Admin changed".to_string(),
+            affected_account: Some("config".to_string()),
+            conditions,
+        }
+    }
+
+    #[test]
+    fn test_signer_condition() {
+        let new_admin = Pubkey::new_unique();
+        let rules = vec![rule(vec![RuleCondition {
+            arg: Some("new_admin".to_string()),
+            signer: Some(false),
+            ..RuleCondition::default()
+        }])];
+
+        let warnings = evaluate(&decoded(&new_admin, 0), &HashSet::new(), &rules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].pattern_id, PatternId::P401IdlRule);
+        assert!(warnings[0].message.starts_with("[set-admin]"));
+        assert!(warnings[0].affected_account.is_some());
+
+        let signers = HashSet::from([new_admin]);
+        assert!(evaluate(&decoded(&new_admin, 0), &signers, &rules).is_empty());
+    }
+
+    #[test]
+    fn test_nested_numeric_conditions() {
+        let rules = vec![rule(vec![RuleCondition {
+            arg: Some("params.delaySeconds".to_string()),
+            less_than: Some(json!("86400")),
+            ..RuleCondition::default()
+        }])];
+
+        assert_eq!(evaluate(&decoded(&Pubkey::new_unique(), 60), &HashSet::new(), &rules).len(), 1);
+        assert!(evaluate(&decoded(&Pubkey::new_unique(), 172_800), &HashSet::new(), &rules).is_empty());
+    }
+}
@@ -0,0 +1,458 @@
+use crate::detector::{PatternId, Severity, Warning};
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, params_from_iter, types::Type, Connection, OptionalExtension, Row};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS transactions (
+    signature TEXT PRIMARY KEY,
+    target TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS warnings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    signature TEXT NOT NULL REFERENCES transactions(signature),
+    pattern_id TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    message TEXT NOT NULL,
+    affected_account TEXT,
+    detected_at INTEGER NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open',
+    note TEXT,
+    status_updated_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS warnings_signature ON warnings(signature);
+CREATE TABLE IF NOT EXISTS suppressions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pattern_id TEXT,
+    affected_account TEXT,
+    reason TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+";
+
+/// Lifecycle of a stored warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Open,
+    Acknowledged,
+    Resolved,
+}
+
+impl AlertStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AlertStatus::Open => "open",
+            AlertStatus::Acknowledged => "acknowledged",
+            AlertStatus::Resolved => "resolved",
+        }
+    }
+}
+
+/// A warning as stored, with its acknowledgment state
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertRecord {
+    pub id: i64,
+    pub signature: String,
+    pub target: String,
+    pub slot: u64,
+    pub pattern_id: PatternId,
+    pub pattern_name: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub affected_account: Option<String>,
+    pub detected_at: i64,
+    pub status: AlertStatus,
+    pub note: Option<String>,
+    pub status_updated_at: Option<i64>,
+}
+
+/// Hides warnings matching a pattern and/or affected account until it expires.
+///
+/// A suppression with neither set matches every warning.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Suppression {
+    pub id: i64,
+    pub pattern_id: Option<PatternId>,
+    pub affected_account: Option<String>,
+    pub reason: String,
+    pub created_at: i64,
+    pub expires_at: i64,
+}
+
+impl Suppression {
+    fn matches(&self, warning: &Warning) -> bool {
+        self.pattern_id.is_none_or(|pattern| pattern == warning.pattern_id)
+            && self.affected_account.as_ref().is_none_or(|account| {
+                warning.affected_account.map(|key| key.to_string()).as_ref() == Some(account)
+            })
+    }
+}
+
+/// Filters for listing warnings
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AlertQuery {
+    pub status: Option<AlertStatus>,
+    /// Minimum severity
+    pub severity: Option<Severity>,
+    pub pattern_id: Option<PatternId>,
+    pub limit: Option<u32>,
+}
+
+/// Local SQLite store of the warnings guard has raised, their acknowledgments, and suppressions
+pub struct AlertStore {
+    connection: Connection,
+}
+
+impl AlertStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection =
+            Connection::open(path).with_context(|| format!("Failed to open database {}", path.display()))?;
+        Self::init(connection)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Stores a transaction's warnings, dropping those covered by an active suppression.
+    ///
+    /// Returns the warnings that were kept. Transactions without remaining warnings are not stored,
+    /// and a transaction that was already recorded keeps its warnings and returns none.
+    pub fn record(
+        &self,
+        target: &Pubkey,
+        signature: &str,
+        slot: u64,
+        tx: &EncodedConfirmedTransactionWithStatusMeta,
+        warnings: Vec<Warning>,
+    ) -> Result<Vec<Warning>> {
+        let now = unix_now();
+        let suppressions = self.suppressions(true)?;
+        let kept: Vec<Warning> = warnings
+            .into_iter()
+            .filter(|warning| !suppressions.iter().any(|suppression| suppression.matches(warning)))
+            .collect();
+        if kept.is_empty() {
+            return Ok(kept);
+        }
+
+        let inserted = self.connection.execute(
+            "INSERT OR IGNORE INTO transactions (signature, target, slot, body) VALUES (?1, ?2, ?3, ?4)",
+            params![signature, target.to_string(), slot, serde_json::to_string(tx)?],
+        )?;
+        if inserted == 0 {
+            return Ok(Vec::new());
+        }
+        for warning in &kept {
+            self.connection.execute(
+                "INSERT INTO warnings (signature, pattern_id, severity, message, affected_account, detected_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    signature,
+                    warning.pattern_id.code(),
+                    severity_str(warning.severity),
+                    warning.message,
+                    warning.affected_account.map(|key| key.to_string()),
+                    now
+                ],
+            )?;
+        }
+        Ok(kept)
+    }
+
+    /// Most recent warnings first
+    pub fn alerts(&self, query: &AlertQuery) -> Result<Vec<AlertRecord>> {
+        let mut sql = format!("{} WHERE 1 = 1", ALERT_SELECT);
+        let mut values: Vec<String> = Vec::new();
+        if let Some(status) = query.status {
+            values.push(status.as_str().to_string());
+            sql.push_str(&format!(" AND w.status = ?{}", values.len()));
+        }
+        if let Some(pattern_id) = query.pattern_id {
+            values.push(pattern_id.code().to_string());
+            sql.push_str(&format!(" AND w.pattern_id = ?{}", values.len()));
+        }
+        if let Some(min) = query.severity {
+            let allowed: Vec<String> = [Severity::Alert, Severity::Warning, Severity::Critical]
+                .into_iter()
+                .filter(|severity| *severity >= min)
+                .map(|severity| {
+                    values.push(severity_str(severity).to_string());
+                    format!("?{}", values.len())
+                })
+                .collect();
+            sql.push_str(&format!(" AND w.severity IN ({})", allowed.join(", ")));
+        }
+        sql.push_str(&format!(" ORDER BY w.id DESC LIMIT {}", query.limit.unwrap_or(100)));
+
+        let mut statement = self.connection.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(values), alert_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn alert(&self, id: i64) -> Result<Option<AlertRecord>> {
+        Ok(self
+            .connection
+            .query_row(&format!("{} WHERE w.id = ?1", ALERT_SELECT), [id], alert_from_row)
+            .optional()?)
+    }
+
+    /// The transaction a warning was raised on, as fetched from the RPC
+    pub fn transaction(&self, signature: &str) -> Result<Option<Value>> {
+        let body: Option<String> = self
+            .connection
+            .query_row("SELECT body FROM transactions WHERE signature = ?1", [signature], |row| row.get(0))
+            .optional()?;
+        body.map(|body| Ok(serde_json::from_str(&body)?)).transpose()
+    }
+
+    /// Acknowledges or resolves a warning, returning the updated record
+    pub fn set_status(&self, id: i64, status: AlertStatus, note: Option<&str>) -> Result<Option<AlertRecord>> {
+        let updated = self.connection.execute(
+            "UPDATE warnings SET status = ?1, note = COALESCE(?2, note), status_updated_at = ?3 WHERE id = ?4",
+            params![status.as_str(), note, unix_now(), id],
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        self.alert(id)
+    }
+
+    /// Sets the status of a transaction's warnings for a pattern and affected account, returning how
+    /// many were updated. Used where warnings are known by what they flag rather than by ID.
+    pub fn set_warning_status(
+        &self,
+        signature: &str,
+        pattern_id: PatternId,
+        affected_account: Option<&str>,
+        status: AlertStatus,
+    ) -> Result<usize> {
+        Ok(self.connection.execute(
+            "UPDATE warnings SET status = ?1, status_updated_at = ?2
+             WHERE signature = ?3 AND pattern_id = ?4 AND affected_account IS ?5",
+            params![status.as_str(), unix_now(), signature, pattern_id.code(), affected_account],
+        )?)
+    }
+
+    pub fn add_suppression(
+        &self,
+        pattern_id: Option<PatternId>,
+        affected_account: Option<&str>,
+        reason: &str,
+        duration_seconds: u64,
+    ) -> Result<Suppression> {
+        if let Some(account) = affected_account {
+            account
+                .parse::<Pubkey>()
+                .map_err(|e| anyhow!("Invalid affected account {}: {}", account, e))?;
+        }
+        let now = unix_now();
+        self.connection.execute(
+            "INSERT INTO suppressions (pattern_id, affected_account, reason, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                pattern_id.map(|pattern| pattern.code()),
+                affected_account,
+                reason,
+                now,
+                now.saturating_add(duration_seconds as i64)
+            ],
+        )?;
+        Ok(Suppression {
+            id: self.connection.last_insert_rowid(),
+            pattern_id,
+            affected_account: affected_account.map(str::to_string),
+            reason: reason.to_string(),
+            created_at: now,
+            expires_at: now.saturating_add(duration_seconds as i64),
+        })
+    }
+
+    pub fn suppressions(&self, active_only: bool) -> Result<Vec<Suppression>> {
+        let mut statement = self.connection.prepare(
+            "SELECT id, pattern_id, affected_account, reason, created_at, expires_at FROM suppressions
+             WHERE ?1 = 0 OR expires_at > ?2 ORDER BY id DESC",
+        )?;
+        let rows = statement.query_map(params![active_only, unix_now()], suppression_from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Deletes a suppression, returning whether it existed
+    pub fn remove_suppression(&self, id: i64) -> Result<bool> {
+        Ok(self.connection.execute("DELETE FROM suppressions WHERE id = ?1", [id])? > 0)
+    }
+}
+
+const ALERT_SELECT: &str = "SELECT w.id, w.signature, t.target, t.slot, w.pattern_id, w.severity, w.message,
+    w.affected_account, w.detected_at, w.status, w.note, w.status_updated_at
+    FROM warnings w JOIN transactions t ON t.signature = w.signature";
+
+fn alert_from_row(row: &Row) -> rusqlite::Result<AlertRecord> {
+    let pattern_id: PatternId = enum_column(row, 4)?;
+    Ok(AlertRecord {
+        id: row.get(0)?,
+        signature: row.get(1)?,
+        target: row.get(2)?,
+        slot: row.get(3)?,
+        pattern_id,
+        pattern_name: pattern_id.name(),
+        severity: enum_column(row, 5)?,
+        message: row.get(6)?,
+        affected_account: row.get(7)?,
+        detected_at: row.get(8)?,
+        status: enum_column(row, 9)?,
+        note: row.get(10)?,
+        status_updated_at: row.get(11)?,
+    })
+}
+
+fn suppression_from_row(row: &Row) -> rusqlite::Result<Suppression> {
+    let pattern_id: Option<String> = row.get(1)?;
+    Ok(Suppression {
+        id: row.get(0)?,
+        pattern_id: match pattern_id {
+            Some(code) => Some(parse_enum(&code, 1)?),
+            None => None,
+        },
+        affected_account: row.get(2)?,
+        reason: row.get(3)?,
+        created_at: row.get(4)?,
+        expires_at: row.get(5)?,
+    })
+}
+
+/// Reads an enum stored as its serde string form, e.g. `P-101` or `critical`
+fn enum_column<T: DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    parse_enum(&row.get::<_, String>(index)?, index)
+}
+
+fn parse_enum<T: DeserializeOwned>(code: &str, index: usize) -> rusqlite::Result<T> {
+    serde_json::from_value(Value::String(code.to_string()))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+fn severity_str(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Alert => "alert",
+        Severity::Warning => "warning",
+        Severity::Critical => "critical",
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction() -> EncodedConfirmedTransactionWithStatusMeta {
+        crate::fixture::read_transaction(Path::new("tests/fixtures/p104_close_account.json")).unwrap()
+    }
+
+    fn warning(pattern_id: PatternId, account: Pubkey) -> Warning {
+        Warning {
+            pattern_id,
+            severity: Severity::Warning,
+            message: "test".to_string(),
+            affected_account: Some(account),
+        }
+    }
+
+    #[test]
+    fn test_record_and_acknowledge() {
+        let store = AlertStore::open_in_memory().unwrap();
+        let target = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        store
+            .record(&target, "sig", 7, &transaction(), vec![warning(PatternId::P104DangerousClose, account)])
+            .unwrap();
+
+        let alerts = store.alerts(&AlertQuery::default()).unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].status, AlertStatus::Open);
+        assert_eq!(alerts[0].target, target.to_string());
+        assert!(store.transaction("sig").unwrap().is_some());
+
+        let updated = store
+            .set_status(alerts[0].id, AlertStatus::Acknowledged, Some("expected migration"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.status, AlertStatus::Acknowledged);
+        assert_eq!(updated.note.as_deref(), Some("expected migration"));
+
+        let open = AlertQuery {
+            status: Some(AlertStatus::Open),
+            ..AlertQuery::default()
+        };
+        assert!(store.alerts(&open).unwrap().is_empty());
+        let critical = AlertQuery {
+            severity: Some(Severity::Critical),
+            ..AlertQuery::default()
+        };
+        assert!(store.alerts(&critical).unwrap().is_empty());
+        assert!(store.set_status(999, AlertStatus::Resolved, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_record_same_transaction_twice() {
+        let store = AlertStore::open_in_memory().unwrap();
+        let target = Pubkey::new_unique();
+        let warnings = || vec![warning(PatternId::P104DangerousClose, Pubkey::new_unique())];
+
+        assert_eq!(store.record(&target, "sig", 7, &transaction(), warnings()).unwrap().len(), 1);
+        assert!(store.record(&target, "sig", 7, &transaction(), warnings()).unwrap().is_empty());
+        assert_eq!(store.alerts(&AlertQuery::default()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_suppressions() {
+        let store = AlertStore::open_in_memory().unwrap();
+        let account = Pubkey::new_unique();
+        let suppression = store
+            .add_suppression(Some(PatternId::P104DangerousClose), Some(&account.to_string()), "maintenance", 3600)
+            .unwrap();
+        store.add_suppression(None, None, "expired", 0).unwrap();
+        assert_eq!(store.suppressions(true).unwrap(), vec![suppression.clone()]);
+
+        let kept = store
+            .record(
+                &Pubkey::new_unique(),
+                "sig",
+                7,
+                &transaction(),
+                vec![
+                    warning(PatternId::P104DangerousClose, account),
+                    warning(PatternId::P104DangerousClose, Pubkey::new_unique()),
+                    warning(PatternId::P101MintKill, account),
+                ],
+            )
+            .unwrap();
+        assert_eq!(kept.len(), 2);
+
+        assert!(store.remove_suppression(suppression.id).unwrap());
+        assert!(store.suppressions(true).unwrap().is_empty());
+    }
+}
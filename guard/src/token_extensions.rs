@@ -0,0 +1,173 @@
+use crate::detector::Severity;
+use spl_token_2022::{
+    extension::{
+        confidential_transfer::ConfidentialTransferAccount,
+        confidential_transfer_fee::ConfidentialTransferFeeAmount, cpi_guard::CpiGuard,
+        transfer_fee::TransferFeeAmount, transfer_hook::TransferHookAccount,
+        BaseStateWithExtensions, StateWithExtensions,
+    },
+    state::Account,
+};
+
+/// Something in a token account's state that a plain balance check misses
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloseRisk {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Inspects a token account's data, including Token-2022 extensions, for state
+/// that would be lost or block the close.
+///
+/// Data that is not a token account yields no risks.
+pub fn close_risks(data: &[u8]) -> Vec<CloseRisk> {
+    let Ok(account) = StateWithExtensions::<Account>::unpack(data) else {
+        return Vec::new();
+    };
+    let mut risks = Vec::new();
+    let mut risk = |severity, message: String| risks.push(CloseRisk { severity, message });
+
+    if account.base.amount > 0 && !account.base.is_native() {
+        risk(
+            Severity::Critical,
+            format!("Closing account that still holds {} tokens (base units).", account.base.amount),
+        );
+    }
+
+    if let Ok(fee) = account.get_extension::<TransferFeeAmount>() {
+        let withheld = u64::from(fee.withheld_amount);
+        if withheld > 0 {
+            risk(
+                Severity::Critical,
+                format!(
+                    "Closing Token-2022 account with {} withheld transfer fees (base units). Harvest them to the mint before closing.",
+                    withheld
+                ),
+            );
+        }
+    }
+
+    if let Ok(confidential) = account.get_extension::<ConfidentialTransferAccount>() {
+        let pending_credits = u64::from(confidential.pending_balance_credit_counter);
+        if pending_credits > 0 {
+            risk(
+                Severity::Critical,
+                format!(
+                    "Closing Token-2022 account with {} pending confidential transfer credit(s). Apply the pending balance and withdraw it before closing.",
+                    pending_credits
+                ),
+            );
+        } else if confidential.closable().is_err() {
+            risk(
+                Severity::Critical,
+                "Closing Token-2022 account with a non-zero confidential balance. Withdraw it before closing.".to_string(),
+            );
+        }
+    }
+
+    if let Ok(fee) = account.get_extension::<ConfidentialTransferFeeAmount>() {
+        if bytemuck::bytes_of(&fee.withheld_amount).iter().any(|byte| *byte != 0) {
+            risk(
+                Severity::Critical,
+                "Closing Token-2022 account with withheld confidential transfer fees. Harvest them to the mint before closing.".to_string(),
+            );
+        }
+    }
+
+    if let Ok(guard) = account.get_extension::<CpiGuard>() {
+        if bool::from(guard.lock_cpi) {
+            risk(
+                Severity::Warning,
+                "Token-2022 account has CPI Guard enabled: a close through CPI must return the lamports to the owner.".to_string(),
+            );
+        }
+    }
+
+    if let Ok(hook) = account.get_extension::<TransferHookAccount>() {
+        if bool::from(hook.transferring) {
+            risk(
+                Severity::Warning,
+                "Token-2022 account is flagged as mid-transfer by its transfer hook.".to_string(),
+            );
+        }
+    }
+
+    risks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{program_option::COption, program_pack::Pack, pubkey::Pubkey};
+    use spl_token_2022::{
+        extension::{ExtensionType, StateWithExtensionsMut},
+        state::AccountState,
+    };
+
+    fn account_data(amount: u64, extensions: &[ExtensionType], init: impl FnOnce(&mut StateWithExtensionsMut<Account>)) -> Vec<u8> {
+        let len = ExtensionType::try_calculate_account_len::<Account>(extensions).unwrap();
+        let mut data = vec![0u8; len];
+        let mut state = StateWithExtensionsMut::<Account>::unpack_uninitialized(&mut data).unwrap();
+        state.base = Account {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            amount,
+            delegate: COption::None,
+            state: AccountState::Initialized,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        init(&mut state);
+        data
+    }
+
+    #[test]
+    fn test_withheld_fees_and_cpi_guard() {
+        let data = account_data(0, &[ExtensionType::TransferFeeAmount, ExtensionType::CpiGuard], |state| {
+            state.init_extension::<TransferFeeAmount>(true).unwrap().withheld_amount = 500u64.into();
+            state.init_extension::<CpiGuard>(true).unwrap().lock_cpi = true.into();
+        });
+
+        let risks = close_risks(&data);
+        assert_eq!(risks.len(), 2);
+        assert_eq!(risks[0].severity, Severity::Critical);
+        assert!(risks[0].message.contains("500 withheld transfer fees"));
+        assert_eq!(risks[1].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_pending_confidential_balance() {
+        let data = account_data(0, &[ExtensionType::ConfidentialTransferAccount], |state| {
+            state
+                .init_extension::<ConfidentialTransferAccount>(true)
+                .unwrap()
+                .pending_balance_credit_counter = 2u64.into();
+        });
+
+        let risks = close_risks(&data);
+        assert_eq!(risks.len(), 1);
+        assert!(risks[0].message.contains("2 pending confidential"));
+    }
+
+    #[test]
+    fn test_clean_and_legacy_accounts() {
+        assert!(close_risks(&account_data(0, &[ExtensionType::TransferFeeAmount], |state| {
+            state.init_extension::<TransferFeeAmount>(true).unwrap();
+        }))
+        .is_empty());
+
+        // Plain SPL Token layout
+        let mut legacy = vec![0u8; Account::LEN];
+        Account {
+            amount: 7,
+            state: AccountState::Initialized,
+            ..Account::default()
+        }
+        .pack_into_slice(&mut legacy);
+        assert_eq!(close_risks(&legacy).len(), 1);
+        assert!(close_risks(&[1, 2, 3]).is_empty());
+    }
+}
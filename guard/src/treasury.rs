@@ -0,0 +1,400 @@
+use crate::detector::{PatternId, Severity, Warning};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction, UiMessage, UiTransactionTokenBalance,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Mint key used for native SOL in thresholds
+pub const SOL: &str = "SOL";
+
+/// Treasury accounts and their outflow limits
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TreasuryRules {
+    /// Accounts tagged as treasury: wallets (SOL and the token accounts they own) or token accounts
+    pub accounts: Vec<String>,
+    /// Largest outflow per transaction, in base units, keyed by mint (or `SOL` for lamports)
+    pub thresholds: HashMap<String, u64>,
+    /// Largest cumulative outflow per treasury account within `window_seconds`, keyed like `thresholds`
+    pub window_thresholds: HashMap<String, u64>,
+    pub window_seconds: u64,
+    /// File recording every address treasury funds were sent to, relative to the config file;
+    /// unset disables the new-destination check
+    pub first_seen_store: Option<PathBuf>,
+}
+
+impl Default for TreasuryRules {
+    fn default() -> Self {
+        Self {
+            accounts: Vec::new(),
+            thresholds: HashMap::new(),
+            window_thresholds: HashMap::new(),
+            window_seconds: 86_400,
+            first_seen_store: None,
+        }
+    }
+}
+
+/// Funds that left a treasury account in one transaction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outflow {
+    /// The tagged treasury account the funds left
+    pub treasury: Pubkey,
+    /// Mint address, or `SOL`
+    pub mint: String,
+    pub amount: u64,
+    pub decimals: u8,
+    /// Accounts whose balance of the same mint went up, by owner where known
+    pub destinations: Vec<Pubkey>,
+}
+
+impl Outflow {
+    fn display_amount(&self) -> String {
+        let divisor = 10f64.powi(self.decimals as i32);
+        let unit = if self.mint == SOL { "SOL" } else { self.mint.as_str() };
+        format!("{} {}", self.amount as f64 / divisor, unit)
+    }
+}
+
+/// Balance changes per account and mint, from the transaction meta
+struct BalanceChange {
+    /// Token account owner, or the account itself for SOL
+    holder: Pubkey,
+    account: Pubkey,
+    mint: String,
+    decimals: u8,
+    delta: i128,
+}
+
+/// Computes the outflows from tagged treasury accounts, using pre/post lamport and token balances.
+///
+/// The fee is not counted as an outflow of the fee payer.
+pub fn outflows(tx: &EncodedConfirmedTransactionWithStatusMeta, rules: &TreasuryRules) -> Vec<Outflow> {
+    if rules.accounts.is_empty() {
+        return Vec::new();
+    }
+    let Some(meta) = tx.transaction.meta.as_ref() else {
+        return Vec::new();
+    };
+    let keys: Vec<Option<Pubkey>> = match &tx.transaction.transaction {
+        EncodedTransaction::Json(ui_tx) => match &ui_tx.message {
+            UiMessage::Parsed(parsed) => parsed.account_keys.iter().map(|key| key.pubkey.parse().ok()).collect(),
+            UiMessage::Raw(raw) => raw.account_keys.iter().map(|key| key.parse().ok()).collect(),
+        },
+        _ => return Vec::new(),
+    };
+    let treasury: HashSet<Pubkey> = rules.accounts.iter().filter_map(|key| key.parse().ok()).collect();
+
+    let mut changes = Vec::new();
+    for (index, (pre, post)) in meta.pre_balances.iter().zip(&meta.post_balances).enumerate() {
+        let Some(Some(key)) = keys.get(index) else {
+            continue;
+        };
+        let fee = if index == 0 { meta.fee as i128 } else { 0 };
+        changes.push(BalanceChange {
+            holder: *key,
+            account: *key,
+            mint: SOL.to_string(),
+            decimals: 9,
+            delta: *post as i128 - *pre as i128 + fee,
+        });
+    }
+    changes.extend(token_changes(meta.pre_token_balances.as_ref().into(), meta.post_token_balances.as_ref().into(), &keys));
+
+    let mut outflows = Vec::new();
+    for change in changes.iter().filter(|change| change.delta < 0) {
+        let tagged = if treasury.contains(&change.account) {
+            change.account
+        } else if treasury.contains(&change.holder) {
+            change.holder
+        } else {
+            continue;
+        };
+
+        let mut destinations = Vec::new();
+        for other in &changes {
+            let is_destination = other.mint == change.mint && other.delta > 0 && !treasury.contains(&other.holder);
+            if is_destination && !destinations.contains(&other.holder) {
+                destinations.push(other.holder);
+            }
+        }
+
+        outflows.push(Outflow {
+            treasury: tagged,
+            mint: change.mint.clone(),
+            amount: change.delta.unsigned_abs() as u64,
+            decimals: change.decimals,
+            destinations,
+        });
+    }
+    outflows
+}
+
+#[derive(Default)]
+struct TokenBalance {
+    owner: Option<Pubkey>,
+    decimals: u8,
+    pre: i128,
+    post: i128,
+}
+
+fn token_changes(
+    pre: Option<&Vec<UiTransactionTokenBalance>>,
+    post: Option<&Vec<UiTransactionTokenBalance>>,
+    keys: &[Option<Pubkey>],
+) -> Vec<BalanceChange> {
+    // Keyed by (account index, mint); accounts opened or closed in the transaction appear on one side only
+    let mut balances: BTreeMap<(u8, String), TokenBalance> = BTreeMap::new();
+    for (side, is_post) in [(pre, false), (post, true)] {
+        for balance in side.into_iter().flatten() {
+            let amount = balance.ui_token_amount.amount.parse::<i128>().unwrap_or_default();
+            let entry = balances.entry((balance.account_index, balance.mint.clone())).or_default();
+            if let OptionSerializer::Some(owner) = &balance.owner {
+                entry.owner = entry.owner.or(owner.parse().ok());
+            }
+            entry.decimals = balance.ui_token_amount.decimals;
+            if is_post {
+                entry.post = amount;
+            } else {
+                entry.pre = amount;
+            }
+        }
+    }
+
+    balances
+        .into_iter()
+        .filter_map(|((index, mint), balance)| {
+            let account = (*keys.get(index as usize)?)?;
+            Some(BalanceChange {
+                holder: balance.owner.unwrap_or(account),
+                account,
+                mint,
+                decimals: balance.decimals,
+                delta: balance.post - balance.pre,
+            })
+        })
+        .collect()
+}
+
+/// P-108: Treasury Outflow above the per-transaction threshold for its mint
+pub fn analyze(outflows: &[Outflow], rules: &TreasuryRules) -> Vec<Warning> {
+    outflows
+        .iter()
+        .filter_map(|outflow| {
+            let threshold = *rules.thresholds.get(&outflow.mint)?;
+            (outflow.amount > threshold).then(|| Warning {
+                pattern_id: PatternId::P108TreasuryOutflow,
+                severity: Severity::Critical,
+                message: format!(
+                    "Treasury {} sent {}{}, above the {} base unit threshold.",
+                    outflow.treasury,
+                    outflow.display_amount(),
+                    destinations_suffix(&outflow.destinations),
+                    threshold
+                ),
+                affected_account: Some(outflow.treasury),
+            })
+        })
+        .collect()
+}
+
+fn destinations_suffix(destinations: &[Pubkey]) -> String {
+    if destinations.is_empty() {
+        return String::new();
+    }
+    let destinations: Vec<String> = destinations.iter().map(Pubkey::to_string).collect();
+    format!(" to {}", destinations.join(", "))
+}
+
+/// Addresses treasury funds have been sent to, with the unix time each was first seen
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct FirstSeenStore {
+    pub addresses: BTreeMap<String, i64>,
+}
+
+impl FirstSeenStore {
+    /// Loads the store, starting empty if the file does not exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        serde_json::from_str(
+            &fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?,
+        )
+        .with_context(|| format!("Invalid first-seen store {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Stateful treasury checks across the transactions guard sees: outflows to
+/// never-seen destinations and cumulative outflow over a rolling window.
+pub struct TreasuryMonitor {
+    rules: TreasuryRules,
+    first_seen: Option<FirstSeenStore>,
+    /// (treasury, mint) -> (block time, amount) of recent outflows
+    window: HashMap<(Pubkey, String), Vec<(i64, u64)>>,
+}
+
+impl TreasuryMonitor {
+    pub fn new(rules: &TreasuryRules) -> Result<Self> {
+        let first_seen = rules.first_seen_store.as_deref().map(FirstSeenStore::load).transpose()?;
+        Ok(Self {
+            rules: rules.clone(),
+            first_seen,
+            window: HashMap::new(),
+        })
+    }
+
+    /// Records a transaction's treasury outflows and returns the P-108 warnings they trigger
+    pub fn observe_transaction(&mut self, tx: &EncodedConfirmedTransactionWithStatusMeta) -> Result<Vec<Warning>> {
+        let block_time = tx.block_time.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs() as i64)
+                .unwrap_or_default()
+        });
+        self.observe(&outflows(tx, &self.rules), block_time)
+    }
+
+    fn observe(&mut self, outflows: &[Outflow], block_time: i64) -> Result<Vec<Warning>> {
+        let mut warnings = Vec::new();
+
+        if let Some(store) = self.first_seen.as_mut() {
+            let mut changed = false;
+            for outflow in outflows {
+                for destination in &outflow.destinations {
+                    if store.addresses.contains_key(&destination.to_string()) {
+                        continue;
+                    }
+                    store.addresses.insert(destination.to_string(), block_time);
+                    changed = true;
+                    warnings.push(Warning {
+                        pattern_id: PatternId::P108TreasuryOutflow,
+                        severity: Severity::Warning,
+                        message: format!(
+                            "Treasury {} sent {} to {}, an address it has never sent funds to before.",
+                            outflow.treasury,
+                            outflow.display_amount(),
+                            destination
+                        ),
+                        affected_account: Some(outflow.treasury),
+                    });
+                }
+            }
+            if changed {
+                if let Some(path) = &self.rules.first_seen_store {
+                    store.save(path)?;
+                }
+            }
+        }
+
+        for outflow in outflows {
+            let Some(&threshold) = self.rules.window_thresholds.get(&outflow.mint) else {
+                continue;
+            };
+            let entries = self.window.entry((outflow.treasury, outflow.mint.clone())).or_default();
+            let cutoff = block_time - self.rules.window_seconds as i64;
+            entries.retain(|(time, _)| *time > cutoff);
+
+            let before: u64 = entries.iter().map(|(_, amount)| amount).sum();
+            entries.push((block_time, outflow.amount));
+            let total = before.saturating_add(outflow.amount);
+            // Report once when the window total crosses the threshold
+            if before <= threshold && total > threshold {
+                warnings.push(Warning {
+                    pattern_id: PatternId::P108TreasuryOutflow,
+                    severity: Severity::Critical,
+                    message: format!(
+                        "Treasury {} has sent {} base units of {} in the last {}s, above the {} threshold.",
+                        outflow.treasury, total, outflow.mint, self.rules.window_seconds, threshold
+                    ),
+                    affected_account: Some(outflow.treasury),
+                });
+            }
+        }
+
+        Ok(warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outflow(treasury: Pubkey, amount: u64, destination: Pubkey) -> Outflow {
+        Outflow {
+            treasury,
+            mint: SOL.to_string(),
+            amount,
+            decimals: 9,
+            destinations: vec![destination],
+        }
+    }
+
+    #[test]
+    fn test_per_transaction_threshold() {
+        let treasury = Pubkey::new_unique();
+        let rules = TreasuryRules {
+            thresholds: HashMap::from([(SOL.to_string(), 1_000)]),
+            ..TreasuryRules::default()
+        };
+
+        assert!(analyze(&[outflow(treasury, 1_000, Pubkey::new_unique())], &rules).is_empty());
+        let warnings = analyze(&[outflow(treasury, 1_001, Pubkey::new_unique())], &rules);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].pattern_id, PatternId::P108TreasuryOutflow);
+        assert_eq!(warnings[0].affected_account, Some(treasury));
+    }
+
+    #[test]
+    fn test_rolling_window() {
+        let treasury = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+        let mut monitor = TreasuryMonitor::new(&TreasuryRules {
+            window_thresholds: HashMap::from([(SOL.to_string(), 1_000)]),
+            window_seconds: 100,
+            ..TreasuryRules::default()
+        })
+        .unwrap();
+
+        assert!(monitor.observe(&[outflow(treasury, 600, destination)], 0).unwrap().is_empty());
+        // The first outflow has left the window
+        assert!(monitor.observe(&[outflow(treasury, 600, destination)], 100).unwrap().is_empty());
+        let warnings = monitor.observe(&[outflow(treasury, 600, destination)], 150).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].severity, Severity::Critical);
+        // Already over the threshold within the window
+        assert!(monitor.observe(&[outflow(treasury, 600, destination)], 160).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_first_seen_destinations_persist() {
+        let path = std::env::temp_dir().join(format!("guard-first-seen-{}.json", std::process::id()));
+        let rules = TreasuryRules {
+            first_seen_store: Some(path.clone()),
+            ..TreasuryRules::default()
+        };
+        let treasury = Pubkey::new_unique();
+        let destination = Pubkey::new_unique();
+
+        let mut monitor = TreasuryMonitor::new(&rules).unwrap();
+        assert_eq!(monitor.observe(&[outflow(treasury, 1, destination)], 0).unwrap().len(), 1);
+        assert!(monitor.observe(&[outflow(treasury, 1, destination)], 1).unwrap().is_empty());
+
+        let mut reloaded = TreasuryMonitor::new(&rules).unwrap();
+        assert!(reloaded.observe(&[outflow(treasury, 1, destination)], 2).unwrap().is_empty());
+        assert_eq!(reloaded.observe(&[outflow(treasury, 1, Pubkey::new_unique())], 3).unwrap().len(), 1);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
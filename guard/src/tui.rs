@@ -0,0 +1,617 @@
+use crate::api::SharedStore;
+use crate::detector::{PatternId, Severity, Warning};
+use crate::store::{AlertStatus, AlertStore};
+use anyhow::{anyhow, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table},
+    Frame,
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Most recent analyzed transactions kept in the feed
+const FEED_CAPACITY: usize = 200;
+/// Most recent warnings kept in the warnings pane
+const ALERT_CAPACITY: usize = 1_000;
+/// Mutes persisted to the alert store last until lifted
+const MUTE_SECONDS: u64 = i64::MAX as u64;
+const MUTE_REASON: &str = "Muted from the dashboard";
+
+/// A transaction the watch loop fetched and ran through the detector
+#[derive(Debug, Clone)]
+pub struct AnalyzedTransaction {
+    pub target: Pubkey,
+    pub signature: String,
+    pub slot: u64,
+    pub warnings: Vec<Warning>,
+}
+
+/// Progress reported by the watch loop to the dashboard
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    /// A poll of one target finished, successfully or not
+    Polled {
+        target: Pubkey,
+        slot: Option<u64>,
+        error: Option<String>,
+    },
+    Analyzed(AnalyzedTransaction),
+}
+
+/// Delivery status of a notification channel
+#[derive(Debug, Clone)]
+pub struct NotifierHealth {
+    pub name: &'static str,
+    pub healthy: bool,
+    pub status: String,
+}
+
+/// Notification channels for the health pane
+pub fn notifier_health(discord_webhook: bool) -> Vec<NotifierHealth> {
+    vec![
+        NotifierHealth {
+            name: "Terminal",
+            healthy: true,
+            status: "Active".to_string(),
+        },
+        NotifierHealth {
+            name: "Discord",
+            healthy: false,
+            status: if discord_webhook {
+                "Webhook configured, delivery not implemented".to_string()
+            } else {
+                "Not configured".to_string()
+            },
+        },
+    ]
+}
+
+struct TargetStatus {
+    target: Pubkey,
+    last_slot: Option<u64>,
+    /// None until the first poll finishes
+    error: Option<Option<String>>,
+    last_poll: Option<u64>,
+}
+
+struct FeedEntry {
+    received: u64,
+    signature: String,
+    slot: u64,
+    warning_count: usize,
+    highest: Option<Severity>,
+}
+
+struct AlertEntry {
+    received: u64,
+    signature: String,
+    warning: Warning,
+    acknowledged: bool,
+}
+
+/// Alerts are muted by pattern and affected account
+type MuteKey = (PatternId, Option<Pubkey>);
+
+/// Dashboard state, updated from watch events and keybindings
+pub struct App {
+    targets: Vec<TargetStatus>,
+    notifiers: Vec<NotifierHealth>,
+    feed: VecDeque<FeedEntry>,
+    alerts: VecDeque<AlertEntry>,
+    muted: HashSet<MuteKey>,
+    suppressed: usize,
+    /// Acknowledgments and mutes are persisted here when `--db` is set
+    store: Option<SharedStore>,
+    /// Suppressions added by muting this session, lifted by unmute
+    mute_suppressions: Vec<i64>,
+    store_error: Option<String>,
+    min_severity: Option<Severity>,
+    pattern_filter: Option<PatternId>,
+    /// Index into the visible alerts
+    selected: usize,
+}
+
+impl App {
+    pub fn new(targets: &[Pubkey], notifiers: Vec<NotifierHealth>, store: Option<SharedStore>) -> Self {
+        Self {
+            targets: targets
+                .iter()
+                .map(|target| TargetStatus {
+                    target: *target,
+                    last_slot: None,
+                    error: None,
+                    last_poll: None,
+                })
+                .collect(),
+            notifiers,
+            feed: VecDeque::new(),
+            alerts: VecDeque::new(),
+            muted: HashSet::new(),
+            suppressed: 0,
+            store,
+            mute_suppressions: Vec::new(),
+            store_error: None,
+            min_severity: None,
+            pattern_filter: None,
+            selected: 0,
+        }
+    }
+
+    pub fn handle(&mut self, event: WatchEvent) {
+        let now = unix_now();
+        match event {
+            WatchEvent::Polled { target, slot, error } => {
+                if let Some(status) = self.targets.iter_mut().find(|status| status.target == target) {
+                    status.last_slot = slot.max(status.last_slot);
+                    status.error = Some(error);
+                    status.last_poll = Some(now);
+                }
+            }
+            WatchEvent::Analyzed(tx) => {
+                if let Some(status) = self.targets.iter_mut().find(|status| status.target == tx.target) {
+                    status.last_slot = status.last_slot.max(Some(tx.slot));
+                }
+                self.feed.push_front(FeedEntry {
+                    received: now,
+                    signature: tx.signature.clone(),
+                    slot: tx.slot,
+                    warning_count: tx.warnings.len(),
+                    highest: tx.warnings.iter().map(|warning| warning.severity).max(),
+                });
+                self.feed.truncate(FEED_CAPACITY);
+
+                for warning in tx.warnings {
+                    if self.muted.contains(&(warning.pattern_id, warning.affected_account)) {
+                        self.suppressed += 1;
+                        continue;
+                    }
+                    self.alerts.push_front(AlertEntry {
+                        received: now,
+                        signature: tx.signature.clone(),
+                        warning,
+                        acknowledged: false,
+                    });
+                }
+                self.alerts.truncate(ALERT_CAPACITY);
+                self.clamp_selection();
+            }
+        }
+    }
+
+    /// Indices of the alerts passing the severity and pattern filters, newest first
+    fn visible_alerts(&self) -> Vec<usize> {
+        self.alerts
+            .iter()
+            .enumerate()
+            .filter(|(_, alert)| self.min_severity.is_none_or(|min| alert.warning.severity >= min))
+            .filter(|(_, alert)| self.pattern_filter.is_none_or(|pattern| alert.warning.pattern_id == pattern))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn selected_alert(&mut self) -> Option<&mut AlertEntry> {
+        let index = *self.visible_alerts().get(self.selected)?;
+        self.alerts.get_mut(index)
+    }
+
+    fn clamp_selection(&mut self) {
+        self.selected = self.selected.min(self.visible_alerts().len().saturating_sub(1));
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected += 1;
+        self.clamp_selection();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Runs an action against the alert store, if one is configured. A failure is shown in the footer.
+    fn with_store<T>(&mut self, action: impl FnOnce(&AlertStore) -> Result<T>) -> Option<T> {
+        let store = self.store.as_ref()?;
+        let result = store
+            .lock()
+            .map_err(|_| anyhow!("Alert store lock poisoned"))
+            .and_then(|store| action(&store));
+        match result {
+            Ok(value) => {
+                self.store_error = None;
+                Some(value)
+            }
+            Err(e) => {
+                self.store_error = Some(e.to_string());
+                None
+            }
+        }
+    }
+
+    pub fn acknowledge_selected(&mut self) {
+        let Some(alert) = self.selected_alert() else {
+            return;
+        };
+        alert.acknowledged = !alert.acknowledged;
+        let status = if alert.acknowledged { AlertStatus::Acknowledged } else { AlertStatus::Open };
+        let signature = alert.signature.clone();
+        let pattern_id = alert.warning.pattern_id;
+        let account = alert.warning.affected_account.map(|key| key.to_string());
+        self.with_store(|store| store.set_warning_status(&signature, pattern_id, account.as_deref(), status));
+    }
+
+    /// Hides the selected alert, and future alerts for the same pattern and account
+    pub fn mute_selected(&mut self) {
+        let Some(alert) = self.selected_alert() else {
+            return;
+        };
+        let key = (alert.warning.pattern_id, alert.warning.affected_account);
+
+        let before = self.alerts.len();
+        self.alerts
+            .retain(|alert| (alert.warning.pattern_id, alert.warning.affected_account) != key);
+        self.suppressed += before - self.alerts.len();
+        self.muted.insert(key);
+        self.clamp_selection();
+
+        let account = key.1.map(|key| key.to_string());
+        if let Some(suppression) =
+            self.with_store(|store| store.add_suppression(Some(key.0), account.as_deref(), MUTE_REASON, MUTE_SECONDS))
+        {
+            self.mute_suppressions.push(suppression.id);
+        }
+    }
+
+    /// Lifts the mutes made this session, including the suppressions they persisted
+    pub fn unmute_all(&mut self) {
+        self.muted.clear();
+        for id in std::mem::take(&mut self.mute_suppressions) {
+            self.with_store(|store| store.remove_suppression(id));
+        }
+    }
+
+    /// Cycles the minimum severity shown: all, warning and above, critical only
+    pub fn cycle_severity_filter(&mut self) {
+        self.min_severity = match self.min_severity {
+            None => Some(Severity::Warning),
+            Some(Severity::Warning) => Some(Severity::Critical),
+            _ => None,
+        };
+        self.selected = 0;
+    }
+
+    /// Cycles through the patterns present in the warnings pane, then back to all
+    pub fn cycle_pattern_filter(&mut self) {
+        let mut patterns: Vec<PatternId> = self.alerts.iter().map(|alert| alert.warning.pattern_id).collect();
+        patterns.sort_by_key(|pattern| pattern.code());
+        patterns.dedup();
+
+        self.pattern_filter = match self.pattern_filter {
+            None => patterns.first().copied(),
+            Some(current) => patterns.iter().skip_while(|pattern| **pattern != current).nth(1).copied(),
+        };
+        self.selected = 0;
+    }
+}
+
+/// Runs the dashboard until the user quits, applying watch events as they arrive
+pub fn run(mut app: App, mut events: UnboundedReceiver<WatchEvent>) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = (|| -> Result<()> {
+        loop {
+            while let Ok(event) = events.try_recv() {
+                app.handle(event);
+            }
+            terminal.draw(|frame| draw(frame, &app))?;
+
+            if !event::poll(Duration::from_millis(200))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => app.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => app.select_previous(),
+                KeyCode::Char('a') | KeyCode::Enter => app.acknowledge_selected(),
+                KeyCode::Char('m') => app.mute_selected(),
+                KeyCode::Char('u') => app.unmute_all(),
+                KeyCode::Char('s') => app.cycle_severity_filter(),
+                KeyCode::Char('p') => app.cycle_pattern_filter(),
+                _ => {}
+            }
+        }
+    })();
+    ratatui::restore();
+    result
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let [top, feed_area, alerts_area, footer] = Layout::vertical([
+        Constraint::Length(app.targets.len().max(app.notifiers.len()) as u16 + 3),
+        Constraint::Percentage(35),
+        Constraint::Min(5),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [targets_area, notifiers_area] =
+        Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)]).areas(top);
+
+    let target_rows = app.targets.iter().map(|status| {
+        let (label, color) = match &status.error {
+            None => ("○ Waiting".to_string(), Color::DarkGray),
+            Some(None) => ("● OK".to_string(), Color::Green),
+            Some(Some(error)) => (format!("● {}", error), Color::Red),
+        };
+        Row::new(vec![
+            Span::styled(status.target.to_string(), Style::default().fg(Color::Cyan)),
+            Span::raw(status.last_slot.map_or("-".to_string(), |slot| slot.to_string())),
+            Span::raw(status.last_poll.map_or("-".to_string(), clock)),
+            Span::styled(label, Style::default().fg(color)),
+        ])
+    });
+    frame.render_widget(
+        Table::new(
+            target_rows,
+            [Constraint::Length(44), Constraint::Length(12), Constraint::Length(9), Constraint::Fill(1)],
+        )
+        .header(Row::new(vec!["Target", "Last Slot", "Polled", "Status"]).style(bold()))
+        .block(Block::default().borders(Borders::ALL).title(" 📡 Targets ")),
+        targets_area,
+    );
+
+    let notifier_rows = app.notifiers.iter().map(|notifier| {
+        let color = if notifier.healthy { Color::Green } else { Color::Yellow };
+        Row::new(vec![
+            Span::raw(notifier.name),
+            Span::styled(notifier.status.clone(), Style::default().fg(color)),
+        ])
+    });
+    frame.render_widget(
+        Table::new(notifier_rows, [Constraint::Length(10), Constraint::Fill(1)])
+            .block(Block::default().borders(Borders::ALL).title(" 📢 Notifiers ")),
+        notifiers_area,
+    );
+
+    let feed_items: Vec<ListItem> = app
+        .feed
+        .iter()
+        .map(|entry| {
+            let summary = match entry.highest {
+                Some(severity) => Span::styled(
+                    format!("{} warning(s)", entry.warning_count),
+                    severity_style(severity),
+                ),
+                None => Span::styled("✅ clean", Style::default().fg(Color::Green)),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{} ", clock(entry.received)), Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{:<12} ", entry.slot), Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{} ", short(&entry.signature)), Style::default().fg(Color::Blue)),
+                summary,
+            ]))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(feed_items).block(Block::default().borders(Borders::ALL).title(" 🔍 Transactions ")),
+        feed_area,
+    );
+
+    let visible = app.visible_alerts();
+    let alert_items: Vec<ListItem> = visible
+        .iter()
+        .map(|&index| {
+            let alert = &app.alerts[index];
+            let dim = Style::default().fg(Color::DarkGray);
+            let (label_style, text_style) = if alert.acknowledged {
+                (dim, dim)
+            } else {
+                (severity_style(alert.warning.severity), Style::default())
+            };
+            let mut spans = vec![
+                Span::styled(format!("{} ", clock(alert.received)), dim),
+                Span::styled(format!("{} ", alert.warning.severity.label()), label_style),
+                Span::styled(format!("{} ", alert.warning.pattern_id.name()), label_style),
+                Span::styled(alert.warning.message.clone(), text_style),
+                Span::styled(format!(" [{}]", short(&alert.signature)), dim),
+            ];
+            if alert.acknowledged {
+                spans.insert(1, Span::styled("✓ ", Style::default().fg(Color::Green)));
+            }
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let filters = format!(
+        " ⚠️  Warnings ({} shown, {} muted) — severity: {}, pattern: {} ",
+        visible.len(),
+        app.suppressed,
+        app.min_severity
+            .map_or("all".to_string(), |severity| format!("{}+", severity.label().trim())),
+        app.pattern_filter.map_or("all", |pattern| pattern.code()),
+    );
+    let mut list_state = ListState::default().with_selected((!visible.is_empty()).then_some(app.selected));
+    frame.render_stateful_widget(
+        List::new(alert_items)
+            .block(Block::default().borders(Borders::ALL).title(filters))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+        alerts_area,
+        &mut list_state,
+    );
+
+    let footer_text = match &app.store_error {
+        Some(error) => Paragraph::new(format!("❌ Alert store: {}", error)).style(Style::default().fg(Color::Red)),
+        None => Paragraph::new(
+            "q quit · ↑/↓ select · a acknowledge · m mute pattern+account · u unmute all · s severity filter · p pattern filter",
+        )
+        .style(Style::default().fg(Color::DarkGray)),
+    };
+    frame.render_widget(footer_text, footer);
+}
+
+/// Same colors as `Warning::format_terminal`
+fn severity_style(severity: Severity) -> Style {
+    let color = match severity {
+        Severity::Critical => Color::Red,
+        Severity::Warning | Severity::Alert => Color::Yellow,
+    };
+    bold().fg(color)
+}
+
+fn bold() -> Style {
+    Style::default().add_modifier(Modifier::BOLD)
+}
+
+fn short(signature: &str) -> String {
+    if signature.len() <= 16 {
+        return signature.to_string();
+    }
+    format!("{}…{}", &signature[..8], &signature[signature.len() - 8..])
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// UTC wall-clock time, HH:MM:SS
+fn clock(unix: u64) -> String {
+    format!("{:02}:{:02}:{:02}", unix / 3600 % 24, unix / 60 % 60, unix % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::AlertQuery;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    fn warning(pattern_id: PatternId, severity: Severity, account: Pubkey) -> Warning {
+        Warning {
+            pattern_id,
+            severity,
+            message: String::new(),
+            affected_account: Some(account),
+        }
+    }
+
+    fn analyzed(target: Pubkey, warnings: Vec<Warning>) -> WatchEvent {
+        WatchEvent::Analyzed(AnalyzedTransaction {
+            target,
+            signature: "sig".to_string(),
+            slot: 42,
+            warnings,
+        })
+    }
+
+    #[test]
+    fn test_filters() {
+        let target = Pubkey::new_unique();
+        let mut app = App::new(&[target], notifier_health(false), None);
+        app.handle(analyzed(
+            target,
+            vec![
+                warning(PatternId::P101MintKill, Severity::Critical, Pubkey::new_unique()),
+                warning(PatternId::P104DangerousClose, Severity::Warning, Pubkey::new_unique()),
+                warning(PatternId::P107DurableNonce, Severity::Alert, Pubkey::new_unique()),
+            ],
+        ));
+        assert_eq!(app.targets[0].last_slot, Some(42));
+        assert_eq!(app.visible_alerts().len(), 3);
+
+        app.cycle_severity_filter();
+        assert_eq!(app.visible_alerts().len(), 2);
+        app.cycle_severity_filter();
+        assert_eq!(app.visible_alerts().len(), 1);
+        app.cycle_severity_filter();
+
+        app.cycle_pattern_filter();
+        assert_eq!(app.pattern_filter, Some(PatternId::P101MintKill));
+        assert_eq!(app.visible_alerts().len(), 1);
+        app.cycle_pattern_filter();
+        app.cycle_pattern_filter();
+        assert_eq!(app.pattern_filter, Some(PatternId::P107DurableNonce));
+        app.cycle_pattern_filter();
+        assert_eq!(app.pattern_filter, None);
+    }
+
+    #[test]
+    fn test_acknowledge_and_mute() {
+        let target = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let mut app = App::new(&[target], notifier_health(true), None);
+        let close = || warning(PatternId::P104DangerousClose, Severity::Warning, account);
+        app.handle(analyzed(target, vec![close(), close()]));
+
+        app.acknowledge_selected();
+        assert!(app.alerts[0].acknowledged);
+
+        app.mute_selected();
+        assert!(app.visible_alerts().is_empty());
+        app.handle(analyzed(target, vec![close()]));
+        assert!(app.visible_alerts().is_empty());
+        assert_eq!(app.suppressed, 3);
+
+        app.unmute_all();
+        app.handle(analyzed(target, vec![close()]));
+        assert_eq!(app.visible_alerts().len(), 1);
+    }
+
+    #[test]
+    fn test_acknowledge_and_mute_persist() {
+        let store: SharedStore = Arc::new(Mutex::new(AlertStore::open_in_memory().unwrap()));
+        let target = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let close = || warning(PatternId::P104DangerousClose, Severity::Warning, account);
+        let tx = crate::fixture::read_transaction(Path::new("tests/fixtures/p104_close_account.json")).unwrap();
+        store.lock().unwrap().record(&target, "sig", 42, &tx, vec![close()]).unwrap();
+        let mut app = App::new(&[target], notifier_health(false), Some(store.clone()));
+        app.handle(analyzed(target, vec![close()]));
+
+        let status = || store.lock().unwrap().alerts(&AlertQuery::default()).unwrap()[0].status;
+        app.acknowledge_selected();
+        assert_eq!(status(), AlertStatus::Acknowledged);
+        app.acknowledge_selected();
+        assert_eq!(status(), AlertStatus::Open);
+
+        app.mute_selected();
+        let suppressions = store.lock().unwrap().suppressions(true).unwrap();
+        assert_eq!(suppressions.len(), 1);
+        assert_eq!(suppressions[0].pattern_id, Some(PatternId::P104DangerousClose));
+        assert_eq!(suppressions[0].affected_account, Some(account.to_string()));
+
+        app.unmute_all();
+        assert!(store.lock().unwrap().suppressions(true).unwrap().is_empty());
+        assert!(app.store_error.is_none());
+    }
+
+    #[test]
+    fn test_draw() {
+        let target = Pubkey::new_unique();
+        let mut app = App::new(&[target], notifier_health(true), None);
+        app.handle(WatchEvent::Polled {
+            target,
+            slot: Some(7),
+            error: None,
+        });
+        app.handle(analyzed(
+            target,
+            vec![warning(PatternId::P101MintKill, Severity::Critical, Pubkey::new_unique())],
+        ));
+
+        let mut terminal = ratatui::Terminal::new(ratatui::backend::TestBackend::new(160, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &app)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        assert!(screen.contains(&target.to_string()));
+        assert!(screen.contains("Mint Authority Kill"));
+    }
+}
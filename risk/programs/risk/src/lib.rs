@@ -1,4 +1,7 @@
 use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
 
 declare_id!("84zwY58ivSmpGY8gsenAc2c4XpwBUyh1thF9XXx3LhfD");
 
@@ -6,126 +9,2534 @@ declare_id!("84zwY58ivSmpGY8gsenAc2c4XpwBUyh1thF9XXx3LhfD");
 pub mod risk {
     use super::*;
 
-    /// Initialize the payment gateway
-    pub fn initialize(ctx: Context<Initialize>, fee: u64) -> Result<()> {
+    /// Initialize a payment gateway. Each `gateway_id` is a separate gateway with its own
+    /// authority, fee, treasuries and prices, so one deployment can host several API products.
+    ///
+    /// `fee` is in base units of `fee_mint`, which `add_accepted_mint` must accept before
+    /// `DEFAULT_ENDPOINT` payments go through.
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        gateway_id: u64,
+        fee: u64,
+        fee_mint: Pubkey,
+    ) -> Result<()> {
         let gateway = &mut ctx.accounts.gateway;
+        gateway.gateway_id = Some(gateway_id);
         gateway.authority = ctx.accounts.authority.key();
         gateway.fee = fee;
+        gateway.fee_mint = fee_mint;
         gateway.bump = ctx.bumps.gateway;
+        gateway.credit_withdrawal_cooldown = DEFAULT_CREDIT_WITHDRAWAL_COOLDOWN;
+        gateway.fee_timelock = DEFAULT_FEE_TIMELOCK;
         Ok(())
     }
 
-    /// Process a payment for API access
+    /// Process a payment for API access.
+    /// Records a receipt for the nonce, so paying the same gateway twice with the same nonce fails.
+    ///
+    /// `endpoint_id` selects the endpoint's `PriceEntry`; `DEFAULT_ENDPOINT` pays the gateway fee,
+    /// in its fee mint.
+    /// When the instructions sysvar is passed, the fee is instead the amount of a `Voucher`
+    /// signed by the gateway's voucher signer in the preceding ed25519 instruction.
     pub fn process_payment(
         ctx: Context<ProcessPayment>,
         amount: u64,
         nonce: u64,
+        endpoint_id: [u8; 32],
     ) -> Result<()> {
-        let gateway = &ctx.accounts.gateway;
+        let fee = match &ctx.accounts.instructions {
+            Some(instructions) => voucher_fee(
+                instructions,
+                &ctx.accounts.gateway,
+                &ctx.accounts.user.key(),
+                &ctx.accounts.mint.key(),
+                nonce,
+                &endpoint_id,
+            )?,
+            None => endpoint_fee(
+                &ctx.accounts.price_entry,
+                &endpoint_id,
+                &ctx.accounts.mint.key(),
+                ctx.accounts.gateway.fee,
+                &ctx.accounts.gateway.fee_mint,
+            )?,
+        };
         
         // Verify payment amount meets minimum fee
         require!(
-            amount >= gateway.fee,
+            amount >= fee,
             ErrorCode::InsufficientPayment
         );
+        check_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+
+        // Transfer payment from user to gateway treasury
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.treasury_token_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &ctx.accounts.mint,
+            amount,
+            &[],
+        )?;
+
+        record_payment(
+            &mut ctx.accounts.receipt,
+            ctx.accounts.gateway.key(),
+            ctx.accounts.user.key(),
+            ctx.accounts.mint.key(),
+            amount,
+            nonce,
+            endpoint_id,
+            ctx.bumps.receipt,
+        )
+    }
+
+    /// Process a payment for API access in native SOL, into the gateway's SOL treasury.
+    /// Priced by the gateway's SOL fee, by a price entry in `NATIVE_SOL`, or by a voucher for `NATIVE_SOL`.
+    pub fn process_sol_payment(
+        ctx: Context<ProcessSolPayment>,
+        amount: u64,
+        nonce: u64,
+        endpoint_id: [u8; 32],
+    ) -> Result<()> {
+        let sol_fee = ctx.accounts.gateway.sol_fee.ok_or(ErrorCode::SolNotAccepted)?;
+        let fee = match &ctx.accounts.instructions {
+            Some(instructions) => voucher_fee(
+                instructions,
+                &ctx.accounts.gateway,
+                &ctx.accounts.user.key(),
+                &NATIVE_SOL,
+                nonce,
+                &endpoint_id,
+            )?,
+            None => endpoint_fee(&ctx.accounts.price_entry, &endpoint_id, &NATIVE_SOL, sol_fee, &NATIVE_SOL)?,
+        };
+        require!(amount >= fee, ErrorCode::InsufficientPayment);
 
-        // Transfer USDC from user to gateway treasury
-        anchor_spl::token::transfer(
+        anchor_lang::system_program::transfer(
             CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                anchor_spl::token::Transfer {
-                    from: ctx.accounts.user_token_account.to_account_info(),
-                    to: ctx.accounts.treasury_token_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.sol_treasury.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        record_payment(
+            &mut ctx.accounts.receipt,
+            ctx.accounts.gateway.key(),
+            ctx.accounts.user.key(),
+            NATIVE_SOL,
+            amount,
+            nonce,
+            endpoint_id,
+            ctx.bumps.receipt,
+        )
+    }
+
+    /// Refund up to the receipt's amount from the treasury to the payer, for a token payment
+    /// the API failed to serve (authority only). A receipt can only be refunded once.
+    pub fn refund(ctx: Context<Refund>, amount: u64) -> Result<()> {
+        let gateway = &ctx.accounts.gateway;
+        let seeds = gateway.signer_seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&seeds.as_slices()];
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            gateway.to_account_info(),
+            &ctx.accounts.mint,
+            amount,
+            signer_seeds,
+        )?;
+
+        mark_refunded(&mut ctx.accounts.receipt, amount)
+    }
+
+    /// Refund up to the receipt's amount from the SOL treasury to the payer, for a SOL
+    /// payment the API failed to serve (authority only). A receipt can only be refunded once.
+    /// Like `withdraw_sol`, the refund can't dip into the treasury's rent-exempt minimum.
+    pub fn refund_sol(ctx: Context<RefundSol>, amount: u64) -> Result<()> {
+        let available = ctx
+            .accounts
+            .sol_treasury
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(0));
+        require!(amount <= available, ErrorCode::InsufficientTreasury);
+
+        let gateway_key = ctx.accounts.gateway.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"sol_treasury",
+            gateway_key.as_ref(),
+            &[ctx.bumps.sol_treasury],
+        ]];
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.sol_treasury.to_account_info(),
+                    to: ctx.accounts.user.to_account_info(),
                 },
+                signer_seeds,
             ),
             amount,
         )?;
 
-        // Emit payment event
-        emit!(PaymentProcessed {
-            user: ctx.accounts.user.key(),
-            amount,
-            nonce,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
+        mark_refunded(&mut ctx.accounts.receipt, amount)
+    }
+
+    /// Queue a change to the gateway fee and its mint, applied by `apply_fee_change` once the
+    /// fee timelock has passed (authority only). Replaces any change already queued.
+    pub fn update_fee(ctx: Context<UpdateFee>, new_fee: u64, mint: Pubkey) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        require!(
+            gateway.treasury_for(&mint).is_some(),
+            ErrorCode::MintNotAccepted
+        );
+        let effective_at = fee_change_effective_at(gateway)?;
+        gateway.pending_fee = Some(PendingFeeChange {
+            fee: new_fee,
+            mint,
+            effective_at,
+        });
+
+        emit!(FeeChangeQueued {
+            gateway: gateway.key(),
+            current_fee: gateway.fee,
+            new_fee,
+            mint,
+            effective_at,
+        });
+
+        Ok(())
+    }
+
+    /// Apply the queued fee change once its effective time has passed (callable by anyone)
+    pub fn apply_fee_change(ctx: Context<ApplyFeeChange>) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        let pending = gateway.pending_fee.ok_or(ErrorCode::NoPendingFeeChange)?;
+        require!(
+            Clock::get()?.unix_timestamp >= pending.effective_at,
+            ErrorCode::FeeChangeNotReady
+        );
+        require!(
+            gateway.treasury_for(&pending.mint).is_some(),
+            ErrorCode::MintNotAccepted
+        );
+
+        let previous_fee = gateway.fee;
+        gateway.fee = pending.fee;
+        gateway.fee_mint = pending.mint;
+        gateway.pending_fee = None;
+
+        emit!(FeeChanged {
+            gateway: gateway.key(),
+            previous_fee,
+            fee: pending.fee,
+            mint: pending.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Drop the queued fee change (authority only)
+    pub fn cancel_fee_change(ctx: Context<UpdateGateway>) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        require!(gateway.pending_fee.is_some(), ErrorCode::NoPendingFeeChange);
+        gateway.pending_fee = None;
+        Ok(())
+    }
+
+    /// Lengthen the delay between queueing and applying a fee change, up to `MAX_FEE_TIMELOCK`
+    /// (authority only). The timelock can't be shortened, or it would no longer protect
+    /// paying users.
+    pub fn set_fee_timelock(ctx: Context<UpdateGateway>, fee_timelock: i64) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        require!(
+            fee_timelock >= gateway.fee_timelock,
+            ErrorCode::TimelockDecrease
+        );
+        require!(fee_timelock <= MAX_FEE_TIMELOCK, ErrorCode::TimelockTooLong);
+        gateway.fee_timelock = fee_timelock;
+        Ok(())
+    }
+
+    /// Propose a new gateway authority, or withdraw the proposal with `None` (authority only).
+    /// The authority changes once the proposed key calls `accept_authority`.
+    pub fn propose_authority(
+        ctx: Context<UpdateGateway>,
+        new_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        gateway.pending_authority = new_authority;
+
+        if let Some(new_authority) = new_authority {
+            emit!(AuthorityProposed {
+                gateway: gateway.key(),
+                authority: gateway.authority,
+                new_authority,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Become the gateway authority, as the key proposed by `propose_authority`
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        let previous_authority = gateway.authority;
+        gateway.authority = ctx.accounts.new_authority.key();
+        gateway.pending_authority = None;
+
+        emit!(AuthorityTransferred {
+            gateway: gateway.key(),
+            previous_authority,
+            authority: gateway.authority,
+        });
+
+        Ok(())
+    }
+
+    /// Accept payments in `mint`, creating its treasury token account (authority only).
+    /// The treasury is a PDA token account owned by the gateway.
+    ///
+    /// SPL Token and Token-2022 mints are supported, except Token-2022 mints with a
+    /// non-zero transfer fee, a transfer hook program or a permanent delegate. SOL is accepted with `set_sol_fee`:
+    /// `NATIVE_SOL` stands for it, so the wrapped SOL mint can't be added here.
+    pub fn add_accepted_mint(ctx: Context<AddAcceptedMint>) -> Result<()> {
+        let mint = ctx.accounts.mint.key();
+        require!(mint != NATIVE_SOL, ErrorCode::NativeMintNotAccepted);
+        check_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+
+        let gateway = &mut ctx.accounts.gateway;
+        require!(
+            gateway.treasury_for(&mint).is_none(),
+            ErrorCode::MintAlreadyAccepted
+        );
+        require!(
+            gateway.accepted_mints.len() < MAX_ACCEPTED_MINTS,
+            ErrorCode::TooManyMints
+        );
+
+        gateway.accepted_mints.push(AcceptedMint {
+            mint,
+            treasury: ctx.accounts.treasury_token_account.key(),
+        });
+        Ok(())
+    }
+
+    /// Stop accepting payments in `mint` (authority only). Its treasury keeps its balance.
+    pub fn remove_accepted_mint(ctx: Context<UpdateGateway>, mint: Pubkey) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        require!(
+            gateway.treasury_for(&mint).is_some(),
+            ErrorCode::MintNotAccepted
+        );
+
+        gateway.accepted_mints.retain(|accepted| accepted.mint != mint);
+        Ok(())
+    }
+
+    /// Accept native SOL payments for `sol_fee` lamports, or stop accepting them with `None` (authority only).
+    /// Enabling SOL applies at once. Once SOL is accepted, changes are queued behind the fee
+    /// timelock like `update_fee` and applied by `apply_sol_fee_change`.
+    ///
+    /// The authority tops the SOL treasury up to its rent-exempt minimum, so payments of
+    /// any size can land in it.
+    pub fn set_sol_fee(ctx: Context<SetSolFee>, sol_fee: Option<u64>) -> Result<()> {
+        let rent_due = Rent::get()?
+            .minimum_balance(0)
+            .saturating_sub(ctx.accounts.sol_treasury.lamports());
+        if sol_fee.is_some() && rent_due > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: ctx.accounts.sol_treasury.to_account_info(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+
+        let gateway = &mut ctx.accounts.gateway;
+        let Some(current_sol_fee) = gateway.sol_fee else {
+            gateway.sol_fee = sol_fee;
+            return Ok(());
+        };
+        let effective_at = fee_change_effective_at(gateway)?;
+        gateway.pending_sol_fee = Some(PendingSolFeeChange {
+            sol_fee,
+            effective_at,
+        });
+
+        emit!(SolFeeChangeQueued {
+            gateway: gateway.key(),
+            current_sol_fee,
+            new_sol_fee: sol_fee,
+            effective_at,
+        });
+
+        Ok(())
+    }
+
+    /// Apply the queued SOL fee change once its effective time has passed (callable by anyone)
+    pub fn apply_sol_fee_change(ctx: Context<ApplyFeeChange>) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        let pending = gateway.pending_sol_fee.ok_or(ErrorCode::NoPendingFeeChange)?;
+        require!(
+            Clock::get()?.unix_timestamp >= pending.effective_at,
+            ErrorCode::FeeChangeNotReady
+        );
+
+        let previous_sol_fee = gateway.sol_fee;
+        gateway.sol_fee = pending.sol_fee;
+        gateway.pending_sol_fee = None;
+
+        emit!(SolFeeChanged {
+            gateway: gateway.key(),
+            previous_sol_fee,
+            sol_fee: pending.sol_fee,
+        });
+
+        Ok(())
+    }
+
+    /// Drop the queued SOL fee change (authority only)
+    pub fn cancel_sol_fee_change(ctx: Context<UpdateGateway>) -> Result<()> {
+        let gateway = &mut ctx.accounts.gateway;
+        require!(gateway.pending_sol_fee.is_some(), ErrorCode::NoPendingFeeChange);
+        gateway.pending_sol_fee = None;
+        Ok(())
+    }
+
+    /// Set the key the API server signs payment vouchers with, or stop accepting vouchers
+    /// with `None` (authority only)
+    pub fn set_voucher_signer(
+        ctx: Context<UpdateGateway>,
+        voucher_signer: Option<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.gateway.voucher_signer = voucher_signer;
+        Ok(())
+    }
+
+    /// Set how withdrawals are split across recipients (authority only).
+    /// Shares are in basis points and must add up to 10,000.
+    pub fn set_revenue_split(ctx: Context<UpdateGateway>, split: Vec<RevenueShare>) -> Result<()> {
+        require!(
+            split.len() <= MAX_REVENUE_RECIPIENTS,
+            ErrorCode::TooManyRecipients
+        );
+        require!(
+            split.iter().all(|share| share.bps > 0),
+            ErrorCode::InvalidRevenueSplit
+        );
+        let total: u32 = split.iter().map(|share| share.bps as u32).sum();
+        require!(total == BPS_DENOMINATOR as u32, ErrorCode::InvalidRevenueSplit);
+
+        ctx.accounts.gateway.revenue_split = split;
+        Ok(())
+    }
+
+    /// Withdraw `amount` from a treasury, split across the revenue recipients (authority only).
+    ///
+    /// Remaining accounts are the recipients' token accounts for the mint, in split order.
+    /// Rounding dust goes to the last recipient.
+    pub fn withdraw<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Withdraw<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let gateway = &ctx.accounts.gateway;
+        let split = &gateway.revenue_split;
+        require!(!split.is_empty(), ErrorCode::RevenueSplitNotSet);
+        require!(
+            ctx.remaining_accounts.len() == split.len(),
+            ErrorCode::InvalidRecipient
+        );
+        require!(
+            amount <= ctx.accounts.treasury_token_account.amount,
+            ErrorCode::InsufficientTreasury
+        );
+
+        let seeds = gateway.signer_seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&seeds.as_slices()];
+        let amounts = split_amounts(amount, split);
+        for ((share, account), &share_amount) in split.iter().zip(ctx.remaining_accounts).zip(&amounts) {
+            let recipient = InterfaceAccount::<TokenAccount>::try_from(account)?;
+            require!(
+                recipient.owner == share.recipient && recipient.mint == ctx.accounts.mint.key(),
+                ErrorCode::InvalidRecipient
+            );
+
+            transfer_tokens(
+                &ctx.accounts.token_program,
+                ctx.accounts.treasury_token_account.to_account_info(),
+                account.clone(),
+                gateway.to_account_info(),
+                &ctx.accounts.mint,
+                share_amount,
+                signer_seeds,
+            )?;
+        }
+
+        emit!(TreasuryWithdrawn {
+            gateway: gateway.key(),
+            mint: ctx.accounts.mint.key(),
+            amount,
+            recipients: split.iter().map(|share| share.recipient).collect(),
+            amounts,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw `amount` lamports from the SOL treasury, split across the revenue recipients (authority only).
+    ///
+    /// Remaining accounts are the recipients' wallets, writable, in split order.
+    /// The treasury keeps its rent-exempt minimum.
+    pub fn withdraw_sol<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawSol<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        let gateway = &ctx.accounts.gateway;
+        let split = &gateway.revenue_split;
+        require!(!split.is_empty(), ErrorCode::RevenueSplitNotSet);
+        require!(
+            ctx.remaining_accounts.len() == split.len(),
+            ErrorCode::InvalidRecipient
+        );
+        let available = ctx
+            .accounts
+            .sol_treasury
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(0));
+        require!(amount <= available, ErrorCode::InsufficientTreasury);
+
+        let gateway_key = gateway.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"sol_treasury",
+            gateway_key.as_ref(),
+            &[ctx.bumps.sol_treasury],
+        ]];
+        let amounts = split_amounts(amount, split);
+        for ((share, account), &share_amount) in split.iter().zip(ctx.remaining_accounts).zip(&amounts) {
+            require_keys_eq!(account.key(), share.recipient, ErrorCode::InvalidRecipient);
+
+            anchor_lang::system_program::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.sol_treasury.to_account_info(),
+                        to: account.clone(),
+                    },
+                    signer_seeds,
+                ),
+                share_amount,
+            )?;
+        }
+
+        emit!(TreasuryWithdrawn {
+            gateway: gateway.key(),
+            mint: NATIVE_SOL,
+            amount,
+            recipients: split.iter().map(|share| share.recipient).collect(),
+            amounts,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Grow a gateway created with the original 41-byte layout to the current one (authority only).
+    /// The original layout has no fee mint, so the authority names the mint its fee is in.
+    pub fn migrate_gateway(ctx: Context<MigrateGateway>, fee_mint: Pubkey) -> Result<()> {
+        let gateway_info = ctx.accounts.gateway.to_account_info();
+        let legacy = {
+            let data = gateway_info.try_borrow_data()?;
+            require!(
+                data.len() == 8 + LegacyGateway::LEN,
+                ErrorCode::AlreadyMigrated
+            );
+            require!(
+                data[..8] == *Gateway::DISCRIMINATOR,
+                ErrorCode::AlreadyMigrated
+            );
+            LegacyGateway::deserialize(&mut &data[8..])?
+        };
+        require_keys_eq!(
+            legacy.authority,
+            ctx.accounts.authority.key(),
+            ErrorCode::Unauthorized
+        );
+
+        let new_len = 8 + Gateway::LEN;
+        let rent_due = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(gateway_info.lamports());
+        if rent_due > 0 {
+            anchor_lang::system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.authority.to_account_info(),
+                        to: gateway_info.clone(),
+                    },
+                ),
+                rent_due,
+            )?;
+        }
+        gateway_info.resize(new_len)?;
+
+        let gateway = Gateway {
+            gateway_id: None,
+            authority: legacy.authority,
+            fee: legacy.fee,
+            bump: legacy.bump,
+            accepted_mints: Vec::new(),
+            revenue_split: Vec::new(),
+            metering_authority: Pubkey::default(),
+            credit_withdrawal_cooldown: DEFAULT_CREDIT_WITHDRAWAL_COOLDOWN,
+            sol_fee: None,
+            pending_authority: None,
+            pending_fee: None,
+            fee_timelock: DEFAULT_FEE_TIMELOCK,
+            voucher_signer: None,
+            pending_sol_fee: None,
+            fee_mint,
+        };
+        gateway.try_serialize(&mut &mut gateway_info.try_borrow_mut_data()?[..])?;
+        Ok(())
+    }
+
+    /// Price a new endpoint, or activate or deactivate an existing one (authority only).
+    /// `mint` is an accepted mint, or `NATIVE_SOL` for SOL prices.
+    /// `endpoint_id` is the SHA-256 of the route and version, e.g. `sha256("GET /api/v1/data/rwa-risk:v1")`.
+    ///
+    /// An existing endpoint's fee and mint can't change here, even after `remove_price`:
+    /// `queue_price_change` puts the change behind the fee timelock.
+    pub fn set_price(
+        ctx: Context<SetPrice>,
+        endpoint_id: [u8; 32],
+        fee: u64,
+        mint: Pubkey,
+        active: bool,
+    ) -> Result<()> {
+        require!(endpoint_id != DEFAULT_ENDPOINT, ErrorCode::InvalidEndpoint);
+        require!(
+            ctx.accounts.gateway.accepts(&mint),
+            ErrorCode::MintNotAccepted
+        );
+
+        let entry = &mut ctx.accounts.price_entry;
+        if entry.gateway == Pubkey::default() {
+            entry.gateway = ctx.accounts.gateway.key();
+            entry.endpoint_id = endpoint_id;
+            entry.fee = fee;
+            entry.mint = mint;
+            entry.bump = ctx.bumps.price_entry;
+        } else {
+            require!(
+                entry.fee == fee && entry.mint == mint,
+                ErrorCode::PriceChangeTimelocked
+            );
+        }
+        entry.active = active;
+        Ok(())
+    }
+
+    /// Queue a change to an endpoint's price, applied by `apply_price_change` once the fee
+    /// timelock has passed (authority only). Replaces any change already queued.
+    pub fn queue_price_change(ctx: Context<QueuePriceChange>, fee: u64, mint: Pubkey) -> Result<()> {
+        require!(
+            ctx.accounts.gateway.accepts(&mint),
+            ErrorCode::MintNotAccepted
+        );
+        let effective_at = fee_change_effective_at(&ctx.accounts.gateway)?;
+
+        let entry = &ctx.accounts.price_entry;
+        let pending = &mut ctx.accounts.pending_price;
+        pending.price_entry = entry.key();
+        pending.fee = fee;
+        pending.mint = mint;
+        pending.effective_at = effective_at;
+        pending.bump = ctx.bumps.pending_price;
+
+        emit!(PriceChangeQueued {
+            gateway: ctx.accounts.gateway.key(),
+            endpoint_id: entry.endpoint_id,
+            current_fee: entry.fee,
+            new_fee: fee,
+            mint,
+            effective_at,
+        });
+
+        Ok(())
+    }
+
+    /// Apply an endpoint's queued price change once its effective time has passed (callable
+    /// by anyone). The pending change's rent goes back to the gateway authority.
+    pub fn apply_price_change(ctx: Context<ApplyPriceChange>) -> Result<()> {
+        let pending = &ctx.accounts.pending_price;
+        require!(
+            Clock::get()?.unix_timestamp >= pending.effective_at,
+            ErrorCode::FeeChangeNotReady
+        );
+        require!(
+            ctx.accounts.gateway.accepts(&pending.mint),
+            ErrorCode::MintNotAccepted
+        );
+
+        let entry = &mut ctx.accounts.price_entry;
+        let previous_fee = entry.fee;
+        entry.fee = pending.fee;
+        entry.mint = pending.mint;
+
+        emit!(PriceChanged {
+            gateway: ctx.accounts.gateway.key(),
+            endpoint_id: entry.endpoint_id,
+            previous_fee,
+            fee: entry.fee,
+            mint: entry.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Drop an endpoint's queued price change, returning its rent (authority only)
+    pub fn cancel_price_change(_ctx: Context<CancelPriceChange>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Remove an endpoint from sale (authority only). A queued price change must be applied
+    /// or cancelled first.
+    ///
+    /// The entry is kept, inactive, as a tombstone: closing it would let `set_price` re-create
+    /// the endpoint at any price without the fee timelock. Its rent stays with the entry.
+    pub fn remove_price(ctx: Context<RemovePrice>) -> Result<()> {
+        ctx.accounts.price_entry.active = false;
+        Ok(())
+    }
+
+    /// Set the key allowed to debit credit accounts, and how long users wait between
+    /// requesting and making a credit withdrawal (authority only)
+    pub fn set_metering(
+        ctx: Context<UpdateGateway>,
+        metering_authority: Pubkey,
+        credit_withdrawal_cooldown: i64,
+    ) -> Result<()> {
+        require!(credit_withdrawal_cooldown >= 0, ErrorCode::InvalidCooldown);
+
+        let gateway = &mut ctx.accounts.gateway;
+        gateway.metering_authority = metering_authority;
+        gateway.credit_withdrawal_cooldown = credit_withdrawal_cooldown;
+        Ok(())
+    }
+
+    /// Deposit prepaid credit for API usage into the gateway's credit vault
+    pub fn deposit_credit(ctx: Context<DepositCredit>, amount: u64) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidAmount);
+        check_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.credit_vault.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &ctx.accounts.mint,
+            amount,
+            &[],
+        )?;
+
+        let credit = &mut ctx.accounts.credit_account;
+        credit.gateway = ctx.accounts.gateway.key();
+        credit.user = ctx.accounts.user.key();
+        credit.mint = ctx.accounts.mint.key();
+        credit.balance = credit
+            .balance
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        credit.bump = ctx.bumps.credit_account;
+
+        emit!(CreditDeposited {
+            gateway: credit.gateway,
+            user: credit.user,
+            mint: credit.mint,
+            amount,
+            balance: credit.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Debit metered usage from a batch of credit accounts into the treasury (metering authority only).
+    ///
+    /// Remaining accounts are the credit accounts, writable, in the same order as `amounts`.
+    /// The batch fails as a whole if any account's balance is too low.
+    pub fn debit_credits<'info>(
+        ctx: Context<'_, '_, 'info, 'info, DebitCredits<'info>>,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
+        require!(
+            !amounts.is_empty() && ctx.remaining_accounts.len() == amounts.len(),
+            ErrorCode::InvalidCreditAccount
+        );
+
+        let gateway_key = ctx.accounts.gateway.key();
+        let mint = ctx.accounts.mint.key();
+        let mut users = Vec::with_capacity(amounts.len());
+        let mut total: u64 = 0;
+        for (account, &amount) in ctx.remaining_accounts.iter().zip(&amounts) {
+            let mut credit = Account::<CreditAccount>::try_from(account)?;
+            require!(
+                credit.gateway == gateway_key && credit.mint == mint,
+                ErrorCode::InvalidCreditAccount
+            );
+            credit.balance = credit
+                .balance
+                .checked_sub(amount)
+                .ok_or(ErrorCode::InsufficientCredit)?;
+            total = total.checked_add(amount).ok_or(ErrorCode::MathOverflow)?;
+            users.push(credit.user);
+            credit.exit(&crate::ID)?;
+        }
+
+        let gateway = &ctx.accounts.gateway;
+        let seeds = gateway.signer_seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&seeds.as_slices()];
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            ctx.accounts.credit_vault.to_account_info(),
+            ctx.accounts.treasury_token_account.to_account_info(),
+            gateway.to_account_info(),
+            &ctx.accounts.mint,
+            total,
+            signer_seeds,
+        )?;
+
+        emit!(CreditsDebited {
+            gateway: gateway.key(),
+            mint,
+            users,
+            amounts,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Start the cooldown before unused credit can be withdrawn
+    pub fn request_credit_withdrawal(ctx: Context<RequestCreditWithdrawal>) -> Result<()> {
+        ctx.accounts.credit_account.withdrawal_requested_at = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Withdraw unused credit once the cooldown since the request has passed.
+    /// The metering authority can still debit usage during the cooldown.
+    pub fn withdraw_credit(ctx: Context<WithdrawCredit>, amount: u64) -> Result<()> {
+        let gateway = &ctx.accounts.gateway;
+        let credit = &mut ctx.accounts.credit_account;
+        require!(
+            credit.withdrawal_requested_at > 0,
+            ErrorCode::WithdrawalNotRequested
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= credit
+                .withdrawal_requested_at
+                .saturating_add(gateway.credit_withdrawal_cooldown),
+            ErrorCode::CreditCooldownActive
+        );
+        credit.balance = credit
+            .balance
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientCredit)?;
+        credit.withdrawal_requested_at = 0;
+
+        let seeds = gateway.signer_seeds();
+        let signer_seeds: &[&[&[u8]]] = &[&seeds.as_slices()];
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            ctx.accounts.credit_vault.to_account_info(),
+            ctx.accounts.user_token_account.to_account_info(),
+            gateway.to_account_info(),
+            &ctx.accounts.mint,
+            amount,
+            signer_seeds,
+        )?;
+
+        emit!(CreditWithdrawn {
+            gateway: credit.gateway,
+            user: credit.user,
+            mint: credit.mint,
+            amount,
+            balance: credit.balance,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Create a subscription plan priced in `mint`, an accepted token mint (authority only)
+    pub fn create_plan(
+        ctx: Context<CreatePlan>,
+        plan_id: u64,
+        price: u64,
+        period: i64,
+        rate_limit_tier: u8,
+        mint: Pubkey,
+    ) -> Result<()> {
+        require!(period > 0, ErrorCode::InvalidPlanPeriod);
+        require!(
+            ctx.accounts.gateway.treasury_for(&mint).is_some(),
+            ErrorCode::MintNotAccepted
+        );
+
+        let plan = &mut ctx.accounts.plan;
+        plan.gateway = ctx.accounts.gateway.key();
+        plan.plan_id = plan_id;
+        plan.price = price;
+        plan.mint = mint;
+        plan.period = period;
+        plan.rate_limit_tier = rate_limit_tier;
+        plan.active = true;
+        plan.bump = ctx.bumps.plan;
+        Ok(())
+    }
+
+    /// Update a plan's rate limit tier and whether it's on sale (authority only).
+    /// Passes already bought keep their expiry.
+    ///
+    /// `price` and `period` must match the plan's: `queue_plan_change` puts changes to what
+    /// a period costs, or the mint it is paid in, behind the fee timelock.
+    pub fn update_plan(
+        ctx: Context<UpdatePlan>,
+        price: u64,
+        period: i64,
+        rate_limit_tier: u8,
+        active: bool,
+    ) -> Result<()> {
+        let plan = &mut ctx.accounts.plan;
+        require!(
+            plan.price == price && plan.period == period,
+            ErrorCode::PriceChangeTimelocked
+        );
+        plan.rate_limit_tier = rate_limit_tier;
+        plan.active = active;
+        Ok(())
+    }
+
+    /// Queue a change to a plan's price, period and mint, applied by `apply_plan_change` once
+    /// the fee timelock has passed (authority only). Replaces any change already queued.
+    pub fn queue_plan_change(
+        ctx: Context<QueuePlanChange>,
+        price: u64,
+        period: i64,
+        mint: Pubkey,
+    ) -> Result<()> {
+        require!(period > 0, ErrorCode::InvalidPlanPeriod);
+        require!(
+            ctx.accounts.gateway.treasury_for(&mint).is_some(),
+            ErrorCode::MintNotAccepted
+        );
+        let effective_at = fee_change_effective_at(&ctx.accounts.gateway)?;
+
+        let plan = &ctx.accounts.plan;
+        let pending = &mut ctx.accounts.pending_plan;
+        pending.plan = plan.key();
+        pending.price = price;
+        pending.period = period;
+        pending.mint = mint;
+        pending.effective_at = effective_at;
+        pending.bump = ctx.bumps.pending_plan;
+
+        emit!(PlanChangeQueued {
+            gateway: ctx.accounts.gateway.key(),
+            plan: plan.key(),
+            current_price: plan.price,
+            new_price: price,
+            current_period: plan.period,
+            new_period: period,
+            mint,
+            effective_at,
+        });
+
+        Ok(())
+    }
+
+    /// Apply a plan's queued change once its effective time has passed (callable by anyone).
+    /// The pending change's rent goes back to the gateway authority.
+    pub fn apply_plan_change(ctx: Context<ApplyPlanChange>) -> Result<()> {
+        let pending = &ctx.accounts.pending_plan;
+        require!(
+            Clock::get()?.unix_timestamp >= pending.effective_at,
+            ErrorCode::FeeChangeNotReady
+        );
+        require!(
+            ctx.accounts.gateway.treasury_for(&pending.mint).is_some(),
+            ErrorCode::MintNotAccepted
+        );
+
+        let plan = &mut ctx.accounts.plan;
+        plan.price = pending.price;
+        plan.period = pending.period;
+        plan.mint = pending.mint;
+
+        emit!(PlanChanged {
+            gateway: ctx.accounts.gateway.key(),
+            plan: plan.key(),
+            price: plan.price,
+            period: plan.period,
+            mint: plan.mint,
+        });
+
+        Ok(())
+    }
+
+    /// Drop a plan's queued change, returning its rent (authority only)
+    pub fn cancel_plan_change(_ctx: Context<CancelPlanChange>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Buy or renew an access pass for `periods` plan periods.
+    /// Renewals stack on top of any time left on the pass.
+    pub fn purchase_pass(ctx: Context<PurchasePass>, periods: u32) -> Result<()> {
+        let plan = &ctx.accounts.plan;
+        require!(plan.active, ErrorCode::PlanInactive);
+        require!(periods > 0, ErrorCode::InvalidPeriods);
+        check_mint_extensions(&ctx.accounts.mint.to_account_info())?;
+
+        let amount = plan
+            .price
+            .checked_mul(periods as u64)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let duration = plan
+            .period
+            .checked_mul(periods as i64)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let now = Clock::get()?.unix_timestamp;
+
+        let pass = &mut ctx.accounts.access_pass;
+        // Switching plans would silently re-price the remaining time
+        require!(
+            !pass.is_active(now) || pass.plan == plan.key(),
+            ErrorCode::PassOnOtherPlan
+        );
+
+        // Transfer the plan's mint from user to gateway treasury
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            ctx.accounts.user_token_account.to_account_info(),
+            ctx.accounts.treasury_token_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &ctx.accounts.mint,
+            amount,
+            &[],
+        )?;
+
+        pass.user = ctx.accounts.user.key();
+        pass.plan = plan.key();
+        pass.rate_limit_tier = plan.rate_limit_tier;
+        pass.expires_at = pass
+            .expires_at
+            .max(now)
+            .checked_add(duration)
+            .ok_or(ErrorCode::MathOverflow)?;
+        pass.bump = ctx.bumps.access_pass;
+
+        emit!(AccessPassExtended {
+            gateway: ctx.accounts.gateway.key(),
+            user: pass.user,
+            plan: pass.plan,
+            amount,
+            expires_at: pass.expires_at,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+}
+
+/// Moves tokens with `transfer_checked`, which supports both SPL Token and Token-2022 mints
+fn transfer_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, Mint>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from,
+                mint: mint.to_account_info(),
+                to,
+                authority,
+            },
+            signer_seeds,
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+/// When a price change queued now can be applied, per the gateway's fee timelock
+fn fee_change_effective_at(gateway: &Gateway) -> Result<i64> {
+    Ok(Clock::get()?
+        .unix_timestamp
+        .checked_add(gateway.fee_timelock)
+        .ok_or(ErrorCode::MathOverflow)?)
+}
+
+/// Fee due for a payment in `mint`: the endpoint's price entry, or `default_fee` in
+/// `default_mint` for `DEFAULT_ENDPOINT`
+fn endpoint_fee(
+    price_entry: &Option<Account<PriceEntry>>,
+    endpoint_id: &[u8; 32],
+    mint: &Pubkey,
+    default_fee: u64,
+    default_mint: &Pubkey,
+) -> Result<u64> {
+    match price_entry {
+        Some(entry) => {
+            require!(entry.active, ErrorCode::EndpointInactive);
+            require_keys_eq!(entry.mint, *mint, ErrorCode::CurrencyMismatch);
+            Ok(entry.fee)
+        }
+        None => {
+            require!(
+                *endpoint_id == DEFAULT_ENDPOINT,
+                ErrorCode::MissingPriceEntry
+            );
+            require_keys_eq!(*default_mint, *mint, ErrorCode::CurrencyMismatch);
+            Ok(default_fee)
+        }
+    }
+}
+
+/// Fee set by the voucher in the ed25519 signature check right before this instruction,
+/// after checking it was signed by the gateway's voucher signer for exactly this payment
+fn voucher_fee(
+    instructions: &UncheckedAccount,
+    gateway: &Account<Gateway>,
+    user: &Pubkey,
+    mint: &Pubkey,
+    nonce: u64,
+    endpoint_id: &[u8; 32],
+) -> Result<u64> {
+    use anchor_lang::solana_program::{ed25519_program, sysvar::instructions::get_instruction_relative};
+
+    let voucher_signer = gateway.voucher_signer.ok_or(ErrorCode::VouchersNotAccepted)?;
+    let ed25519_ix = get_instruction_relative(-1, &instructions.to_account_info())
+        .map_err(|_| ErrorCode::MissingVoucherSignature)?;
+    require_keys_eq!(
+        ed25519_ix.program_id,
+        ed25519_program::ID,
+        ErrorCode::MissingVoucherSignature
+    );
+    let (signer, message) =
+        parse_ed25519_instruction(&ed25519_ix.data).ok_or(ErrorCode::InvalidVoucherSignature)?;
+    require_keys_eq!(signer, voucher_signer, ErrorCode::InvalidVoucherSigner);
+
+    let voucher = message
+        .strip_prefix(VOUCHER_PREFIX)
+        .and_then(|mut data| Voucher::deserialize(&mut data).ok())
+        .ok_or(ErrorCode::InvalidVoucher)?;
+    require_keys_eq!(voucher.gateway, gateway.key(), ErrorCode::VoucherGatewayMismatch);
+    require_keys_eq!(voucher.user, *user, ErrorCode::VoucherUserMismatch);
+    require_keys_eq!(voucher.mint, *mint, ErrorCode::VoucherMintMismatch);
+    require!(voucher.nonce == nonce, ErrorCode::VoucherNonceMismatch);
+    require!(
+        voucher.endpoint_id == *endpoint_id,
+        ErrorCode::VoucherEndpointMismatch
+    );
+    require!(
+        Clock::get()?.unix_timestamp <= voucher.expires_at,
+        ErrorCode::VoucherExpired
+    );
+    Ok(voucher.amount)
+}
+
+/// The public key and message of an ed25519 program instruction checking one signature,
+/// with all three stored in the instruction itself
+fn parse_ed25519_instruction(data: &[u8]) -> Option<(Pubkey, &[u8])> {
+    // [num_signatures: u8, padding: u8] then one 14-byte offsets struct of u16s:
+    // signature offset and ix index, public key offset and ix index, message offset, size and ix index
+    const HEADER_LEN: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+    if data.len() < HEADER_LEN + OFFSETS_LEN || data[0] != 1 {
+        return None;
+    }
+    let offsets: Vec<u16> = data[HEADER_LEN..HEADER_LEN + OFFSETS_LEN]
+        .chunks_exact(2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect();
+    let (signature_ix, public_key_offset, public_key_ix) = (offsets[1], offsets[2], offsets[3]);
+    let (message_offset, message_size, message_ix) = (offsets[4], offsets[5], offsets[6]);
+    if [signature_ix, public_key_ix, message_ix]
+        .iter()
+        .any(|&index| index != CURRENT_INSTRUCTION)
+    {
+        return None;
+    }
+
+    let public_key = data.get(public_key_offset as usize..public_key_offset as usize + 32)?;
+    let message = data.get(message_offset as usize..message_offset as usize + message_size as usize)?;
+    Some((Pubkey::try_from(public_key).ok()?, message))
+}
+
+/// Fills in the payment's receipt and emits `PaymentProcessed`
+#[allow(clippy::too_many_arguments)]
+fn record_payment(
+    receipt: &mut Account<PaymentReceipt>,
+    gateway: Pubkey,
+    user: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    nonce: u64,
+    endpoint_id: [u8; 32],
+    bump: u8,
+) -> Result<()> {
+    let timestamp = Clock::get()?.unix_timestamp;
+    receipt.gateway = gateway;
+    receipt.user = user;
+    receipt.nonce = nonce;
+    receipt.mint = mint;
+    receipt.amount = amount;
+    receipt.endpoint_id = endpoint_id;
+    receipt.timestamp = timestamp;
+    receipt.bump = bump;
+
+    // Emit payment event
+    emit!(PaymentProcessed {
+        gateway,
+        user,
+        mint,
+        amount,
+        nonce,
+        endpoint_id,
+        timestamp,
+    });
+
+    Ok(())
+}
+
+/// Marks the receipt refunded and emits `PaymentRefunded`, once the refund has been transferred
+fn mark_refunded(receipt: &mut Account<PaymentReceipt>, amount: u64) -> Result<()> {
+    require!(!receipt.refunded, ErrorCode::AlreadyRefunded);
+    require!(
+        amount > 0 && amount <= receipt.amount,
+        ErrorCode::RefundExceedsPayment
+    );
+    receipt.refunded = true;
+
+    emit!(PaymentRefunded {
+        gateway: receipt.gateway,
+        user: receipt.user,
+        mint: receipt.mint,
+        nonce: receipt.nonce,
+        amount,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Each recipient's part of `amount`; the last recipient also gets the rounding dust
+fn split_amounts(amount: u64, split: &[RevenueShare]) -> Vec<u64> {
+    let mut remaining = amount;
+    let mut amounts = Vec::with_capacity(split.len());
+    for (index, share) in split.iter().enumerate() {
+        let share_amount = if index == split.len() - 1 {
+            remaining
+        } else {
+            (amount as u128 * share.bps as u128 / BPS_DENOMINATOR as u128) as u64
+        };
+        remaining -= share_amount;
+        amounts.push(share_amount);
+    }
+    amounts
+}
+
+/// Rejects Token-2022 mints whose transfers would deliver less than the amount paid,
+/// need accounts the payment instructions don't pass, or can be moved out of the
+/// treasuries by a permanent delegate.
+///
+/// Fee and hook authorities can change their extension after the mint is accepted,
+/// so payments into the gateway check again.
+fn check_mint_extensions(mint: &AccountInfo) -> Result<()> {
+    use anchor_spl::token_2022::spl_token_2022::{
+        extension::{
+            permanent_delegate::PermanentDelegate, transfer_fee::TransferFeeConfig,
+            transfer_hook::TransferHook, BaseStateWithExtensions, StateWithExtensions,
+        },
+        state::Mint as MintState,
+    };
+
+    if *mint.owner != anchor_spl::token_2022::ID {
+        return Ok(());
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    if let Ok(fee) = state.get_extension::<TransferFeeConfig>() {
+        require!(
+            u16::from(fee.older_transfer_fee.transfer_fee_basis_points) == 0
+                && u16::from(fee.newer_transfer_fee.transfer_fee_basis_points) == 0,
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    if let Ok(hook) = state.get_extension::<TransferHook>() {
+        require!(
+            Option::<Pubkey>::from(hook.program_id).is_none(),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    if let Ok(permanent_delegate) = state.get_extension::<PermanentDelegate>() {
+        require!(
+            Option::<Pubkey>::from(permanent_delegate.delegate).is_none(),
+            ErrorCode::UnsupportedMintExtension
+        );
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(gateway_id: u64)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Gateway::LEN,
+        seeds = [b"gateway".as_ref(), &gateway_id.to_le_bytes()],
+        bump
+    )]
+    pub gateway: Account<'info, Gateway>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, nonce: u64, endpoint_id: [u8; 32])]
+pub struct ProcessPayment<'info> {
+    pub gateway: Account<'info, Gateway>,
+
+    /// Omitted when paying the gateway fee with `DEFAULT_ENDPOINT`, or with a voucher
+    #[account(
+        seeds = [b"price", gateway.key().as_ref(), endpoint_id.as_ref()],
+        bump = price_entry.bump
+    )]
+    pub price_entry: Option<Account<'info, PriceEntry>>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        constraint = gateway.treasury_for(&mint.key()).is_some() @ ErrorCode::MintNotAccepted
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    
+    #[account(
+        mut,
+        constraint = user_token_account.mint == mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        constraint = gateway.treasury_for(&mint.key()) == Some(treasury_token_account.key()) @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Fails to initialize if the nonce was already paid
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PaymentReceipt::LEN,
+        seeds = [b"receipt", gateway.key().as_ref(), user.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Account<'info, PaymentReceipt>,
+
+    /// CHECK: the instructions sysvar, passed only when paying with a voucher
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+    
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, nonce: u64, endpoint_id: [u8; 32])]
+pub struct ProcessSolPayment<'info> {
+    pub gateway: Account<'info, Gateway>,
+
+    /// Omitted when paying the gateway's SOL fee with `DEFAULT_ENDPOINT`, or with a voucher
+    #[account(
+        seeds = [b"price", gateway.key().as_ref(), endpoint_id.as_ref()],
+        bump = price_entry.bump
+    )]
+    pub price_entry: Option<Account<'info, PriceEntry>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_treasury", gateway.key().as_ref()],
+        bump
+    )]
+    pub sol_treasury: SystemAccount<'info>,
+
+    /// Fails to initialize if the nonce was already paid
+    #[account(
+        init,
+        payer = user,
+        space = 8 + PaymentReceipt::LEN,
+        seeds = [b"receipt", gateway.key().as_ref(), user.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub receipt: Account<'info, PaymentReceipt>,
+
+    /// CHECK: the instructions sysvar, passed only when paying with a voucher
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFee<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyFeeChange<'info> {
+    #[account(mut)]
+    pub gateway: Account<'info, Gateway>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        constraint = gateway.pending_authority == Some(new_authority.key()) @ ErrorCode::NotPendingAuthority
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddAcceptedMint<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"treasury", gateway.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = gateway,
+        token::token_program = token_program
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGateway<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSolFee<'info> {
+    #[account(
+        mut,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_treasury", gateway.key().as_ref()],
+        bump
+    )]
+    pub sol_treasury: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(endpoint_id: [u8; 32])]
+pub struct SetPrice<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + PriceEntry::LEN,
+        seeds = [b"price", gateway.key().as_ref(), endpoint_id.as_ref()],
+        bump
+    )]
+    pub price_entry: Account<'info, PriceEntry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemovePrice<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        mut,
+        seeds = [b"price", gateway.key().as_ref(), price_entry.endpoint_id.as_ref()],
+        bump = price_entry.bump
+    )]
+    pub price_entry: Account<'info, PriceEntry>,
+
+    /// CHECK: must not exist, so a removed endpoint has no price change left to apply
+    #[account(
+        seeds = [b"pending_price", price_entry.key().as_ref()],
+        bump,
+        constraint = pending_price.data_is_empty() @ ErrorCode::PriceChangePending
+    )]
+    pub pending_price: UncheckedAccount<'info>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueuePriceChange<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        seeds = [b"price", gateway.key().as_ref(), price_entry.endpoint_id.as_ref()],
+        bump = price_entry.bump
+    )]
+    pub price_entry: Account<'info, PriceEntry>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + PendingPriceChange::LEN,
+        seeds = [b"pending_price", price_entry.key().as_ref()],
+        bump
+    )]
+    pub pending_price: Account<'info, PendingPriceChange>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyPriceChange<'info> {
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        mut,
+        seeds = [b"price", gateway.key().as_ref(), price_entry.endpoint_id.as_ref()],
+        bump = price_entry.bump
+    )]
+    pub price_entry: Account<'info, PriceEntry>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"pending_price", price_entry.key().as_ref()],
+        bump = pending_price.bump
+    )]
+    pub pending_price: Account<'info, PendingPriceChange>,
+
+    /// Receives the pending change's rent
+    #[account(
+        mut,
+        address = gateway.authority @ ErrorCode::Unauthorized
+    )]
+    pub authority: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPriceChange<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        seeds = [b"price", gateway.key().as_ref(), price_entry.endpoint_id.as_ref()],
+        bump = price_entry.bump
+    )]
+    pub price_entry: Account<'info, PriceEntry>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"pending_price", price_entry.key().as_ref()],
+        bump = pending_price.bump
+    )]
+    pub pending_price: Account<'info, PendingPriceChange>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"treasury", gateway.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        mut,
+        seeds = [b"receipt", gateway.key().as_ref(), receipt.user.as_ref(), &receipt.nonce.to_le_bytes()],
+        bump = receipt.bump,
+        has_one = gateway @ ErrorCode::InvalidReceipt,
+        constraint = receipt.mint == mint.key() @ ErrorCode::CurrencyMismatch
+    )]
+    pub receipt: Account<'info, PaymentReceipt>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = gateway.treasury_for(&mint.key()) == Some(treasury_token_account.key()) @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The payer's token account for the receipt's mint
+    #[account(
+        mut,
+        constraint = user_token_account.mint == mint.key() @ ErrorCode::MintMismatch,
+        constraint = user_token_account.owner == receipt.user @ ErrorCode::InvalidRecipient
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RefundSol<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        mut,
+        seeds = [b"receipt", gateway.key().as_ref(), receipt.user.as_ref(), &receipt.nonce.to_le_bytes()],
+        bump = receipt.bump,
+        has_one = gateway @ ErrorCode::InvalidReceipt,
+        constraint = receipt.mint == NATIVE_SOL @ ErrorCode::CurrencyMismatch
+    )]
+    pub receipt: Account<'info, PaymentReceipt>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_treasury", gateway.key().as_ref()],
+        bump
+    )]
+    pub sol_treasury: SystemAccount<'info>,
+
+    /// The payer's wallet
+    #[account(
+        mut,
+        address = receipt.user @ ErrorCode::InvalidRecipient
+    )]
+    pub user: SystemAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSol<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        mut,
+        seeds = [b"sol_treasury", gateway.key().as_ref()],
+        bump
+    )]
+    pub sol_treasury: SystemAccount<'info>,
+
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateGateway<'info> {
+    /// CHECK: still in the legacy layout, so it can't be deserialized as `Gateway`;
+    /// the discriminator, size and authority are checked by the instruction
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"gateway"],
+        bump
+    )]
+    pub gateway: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositCredit<'info> {
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + CreditAccount::LEN,
+        seeds = [b"credit", gateway.key().as_ref(), user.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub credit_account: Account<'info, CreditAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        constraint = gateway.treasury_for(&mint.key()).is_some() @ ErrorCode::MintNotAccepted
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"credit_vault", gateway.key().as_ref(), mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = gateway,
+        token::token_program = token_program
+    )]
+    pub credit_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DebitCredits<'info> {
+    #[account(
+        constraint = gateway.metering_authority == metering_authority.key() @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    pub metering_authority: Signer<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"credit_vault", gateway.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub credit_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = gateway.treasury_for(&mint.key()) == Some(treasury_token_account.key()) @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RequestCreditWithdrawal<'info> {
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        mut,
+        seeds = [b"credit", gateway.key().as_ref(), user.key().as_ref(), credit_account.mint.as_ref()],
+        bump = credit_account.bump
+    )]
+    pub credit_account: Account<'info, CreditAccount>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCredit<'info> {
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        mut,
+        seeds = [b"credit", gateway.key().as_ref(), user.key().as_ref(), mint.key().as_ref()],
+        bump = credit_account.bump
+    )]
+    pub credit_account: Account<'info, CreditAccount>,
+
+    pub user: Signer<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"credit_vault", gateway.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub credit_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(plan_id: u64)]
+pub struct CreatePlan<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Plan::LEN,
+        seeds = [b"plan", gateway.key().as_ref(), &plan_id.to_le_bytes()],
+        bump
+    )]
+    pub plan: Account<'info, Plan>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePlan<'info> {
+    #[account(
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
 
-        Ok(())
-    }
+    #[account(
+        mut,
+        seeds = [b"plan", gateway.key().as_ref(), &plan.plan_id.to_le_bytes()],
+        bump = plan.bump
+    )]
+    pub plan: Account<'info, Plan>,
 
-    /// Update gateway fee (authority only)
-    pub fn update_fee(ctx: Context<UpdateFee>, new_fee: u64) -> Result<()> {
-        let gateway = &mut ctx.accounts.gateway;
-        gateway.fee = new_fee;
-        Ok(())
-    }
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct QueuePlanChange<'info> {
     #[account(
-        init,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        seeds = [b"plan", gateway.key().as_ref(), &plan.plan_id.to_le_bytes()],
+        bump = plan.bump
+    )]
+    pub plan: Account<'info, Plan>,
+
+    #[account(
+        init_if_needed,
         payer = authority,
-        space = 8 + Gateway::LEN,
-        seeds = [b"gateway"],
+        space = 8 + PendingPlanChange::LEN,
+        seeds = [b"pending_plan", plan.key().as_ref()],
         bump
     )]
-    pub gateway: Account<'info, Gateway>,
-    
+    pub pending_plan: Account<'info, PendingPlanChange>,
+
     #[account(mut)]
     pub authority: Signer<'info>,
-    
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ProcessPayment<'info> {
+pub struct ApplyPlanChange<'info> {
+    pub gateway: Account<'info, Gateway>,
+
     #[account(
-        seeds = [b"gateway"],
-        bump = gateway.bump
+        mut,
+        seeds = [b"plan", gateway.key().as_ref(), &plan.plan_id.to_le_bytes()],
+        bump = plan.bump
     )]
-    pub gateway: Account<'info, Gateway>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    #[account(mut)]
-    pub user_token_account: Account<'info, anchor_spl::token::TokenAccount>,
-    
-    #[account(mut)]
-    pub treasury_token_account: Account<'info, anchor_spl::token::TokenAccount>,
-    
-    pub token_program: Program<'info, anchor_spl::token::Token>,
+    pub plan: Account<'info, Plan>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"pending_plan", plan.key().as_ref()],
+        bump = pending_plan.bump
+    )]
+    pub pending_plan: Account<'info, PendingPlanChange>,
+
+    /// Receives the pending change's rent
+    #[account(
+        mut,
+        address = gateway.authority @ ErrorCode::Unauthorized
+    )]
+    pub authority: SystemAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateFee<'info> {
+pub struct CancelPlanChange<'info> {
     #[account(
-        seeds = [b"gateway"],
-        bump = gateway.bump,
         has_one = authority @ ErrorCode::Unauthorized
     )]
     pub gateway: Account<'info, Gateway>,
-    
+
+    #[account(
+        seeds = [b"plan", gateway.key().as_ref(), &plan.plan_id.to_le_bytes()],
+        bump = plan.bump
+    )]
+    pub plan: Account<'info, Plan>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"pending_plan", plan.key().as_ref()],
+        bump = pending_plan.bump
+    )]
+    pub pending_plan: Account<'info, PendingPlanChange>,
+
+    #[account(mut)]
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct PurchasePass<'info> {
+    pub gateway: Account<'info, Gateway>,
+
+    #[account(
+        seeds = [b"plan", gateway.key().as_ref(), &plan.plan_id.to_le_bytes()],
+        bump = plan.bump
+    )]
+    pub plan: Account<'info, Plan>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + AccessPass::LEN,
+        seeds = [b"access_pass", gateway.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub access_pass: Account<'info, AccessPass>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        constraint = gateway.treasury_for(&mint.key()).is_some() @ ErrorCode::MintNotAccepted,
+        constraint = mint.key() == plan.mint @ ErrorCode::CurrencyMismatch
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == mint.key() @ ErrorCode::MintMismatch
+    )]
+    pub user_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = gateway.treasury_for(&mint.key()) == Some(treasury_token_account.key()) @ ErrorCode::InvalidTreasury
+    )]
+    pub treasury_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Currency of SOL payments in price entries, receipts and events (the wrapped SOL mint)
+pub const NATIVE_SOL: Pubkey = anchor_spl::token::spl_token::native_mint::ID;
+
+/// Most mints a gateway can accept at once
+pub const MAX_ACCEPTED_MINTS: usize = 4;
+
+/// Most recipients a revenue split can have
+pub const MAX_REVENUE_RECIPIENTS: usize = 5;
+
+/// Revenue shares are in basis points of each withdrawal
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Seconds between requesting and making a credit withdrawal, until the authority changes it
+pub const DEFAULT_CREDIT_WITHDRAWAL_COOLDOWN: i64 = 24 * 60 * 60;
+
+/// Seconds between queueing and applying a fee change, until the authority lengthens it
+pub const DEFAULT_FEE_TIMELOCK: i64 = 48 * 60 * 60;
+
+/// Longest fee timelock, so fee changes can't be frozen by a timelock that never expires
+pub const MAX_FEE_TIMELOCK: i64 = 30 * 24 * 60 * 60;
+
 #[account]
 pub struct Gateway {
+    pub gateway_id: Option<u64>, // None for the original singleton gateway at `[b"gateway"]`
     pub authority: Pubkey,
-    pub fee: u64, // Fee in USDC (with 6 decimals)
+    pub fee: u64, // Fee in base units of `fee_mint`
     pub bump: u8,
+    pub accepted_mints: Vec<AcceptedMint>,
+    pub revenue_split: Vec<RevenueShare>,
+    pub metering_authority: Pubkey, // Key allowed to debit credit accounts
+    pub credit_withdrawal_cooldown: i64,
+    pub sol_fee: Option<u64>, // Fee in lamports; None when SOL isn't accepted
+    pub pending_authority: Option<Pubkey>, // Proposed authority, until it accepts
+    pub pending_fee: Option<PendingFeeChange>,
+    pub fee_timelock: i64, // Seconds between queueing and applying a fee change
+    pub voucher_signer: Option<Pubkey>, // API server key that signs payment vouchers
+    pub pending_sol_fee: Option<PendingSolFeeChange>,
+    pub fee_mint: Pubkey, // Currency of `fee`
 }
 
 impl Gateway {
+    pub const LEN: usize = 1 + 8 + // gateway_id
+        32 + // authority
+        8 + // fee
+        1 + // bump
+        4 + MAX_ACCEPTED_MINTS * AcceptedMint::LEN + // accepted_mints
+        4 + MAX_REVENUE_RECIPIENTS * RevenueShare::LEN + // revenue_split
+        32 + // metering_authority
+        8 + // credit_withdrawal_cooldown
+        1 + 8 + // sol_fee
+        1 + 32 + // pending_authority
+        1 + PendingFeeChange::LEN + // pending_fee
+        8 + // fee_timelock
+        1 + 32 + // voucher_signer
+        1 + PendingSolFeeChange::LEN + // pending_sol_fee
+        32; // fee_mint
+
+    /// Seeds the gateway PDA signs with, as the authority of its treasuries and credit vaults
+    pub fn signer_seeds(&self) -> GatewaySeeds {
+        GatewaySeeds {
+            gateway_id: self.gateway_id.map(u64::to_le_bytes),
+            bump: [self.bump],
+        }
+    }
+
+    /// Whether payments in `mint` are accepted, with `NATIVE_SOL` standing for SOL
+    pub fn accepts(&self, mint: &Pubkey) -> bool {
+        if *mint == NATIVE_SOL {
+            self.sol_fee.is_some()
+        } else {
+            self.treasury_for(mint).is_some()
+        }
+    }
+
+    /// Canonical treasury for payments in `mint`, if the mint is accepted
+    pub fn treasury_for(&self, mint: &Pubkey) -> Option<Pubkey> {
+        self.accepted_mints
+            .iter()
+            .find(|accepted| accepted.mint == *mint)
+            .map(|accepted| accepted.treasury)
+    }
+}
+
+/// Owned signer seeds of a gateway: `[b"gateway", gateway_id (u64 LE), bump]`,
+/// or `[b"gateway", bump]` for the original singleton
+pub struct GatewaySeeds {
+    gateway_id: Option<[u8; 8]>,
+    bump: [u8; 1],
+}
+
+impl GatewaySeeds {
+    pub fn as_slices(&self) -> Vec<&[u8]> {
+        let mut seeds: Vec<&[u8]> = vec![b"gateway"];
+        if let Some(gateway_id) = &self.gateway_id {
+            seeds.push(gateway_id);
+        }
+        seeds.push(&self.bump);
+        seeds
+    }
+}
+
+/// A queued gateway fee change
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PendingFeeChange {
+    pub fee: u64,
+    pub mint: Pubkey,
+    pub effective_at: i64, // Earliest time `apply_fee_change` can run
+}
+
+impl PendingFeeChange {
+    pub const LEN: usize = 8 + // fee
+        32 + // mint
+        8; // effective_at
+}
+
+/// A queued SOL fee change
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PendingSolFeeChange {
+    pub sol_fee: Option<u64>, // None stops accepting SOL
+    pub effective_at: i64, // Earliest time `apply_sol_fee_change` can run
+}
+
+impl PendingSolFeeChange {
+    pub const LEN: usize = 1 + 8 + // sol_fee
+        8; // effective_at
+}
+
+/// A mint the gateway takes payment in, and the treasury those payments go to
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct AcceptedMint {
+    pub mint: Pubkey,
+    pub treasury: Pubkey,
+}
+
+impl AcceptedMint {
+    pub const LEN: usize = 32 + 32;
+}
+
+/// A recipient's share of each treasury withdrawal
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RevenueShare {
+    pub recipient: Pubkey, // Owner of the token account that receives the share
+    pub bps: u16,
+}
+
+impl RevenueShare {
+    pub const LEN: usize = 32 + 2;
+}
+
+/// Gateway layout before accepted mints and revenue splits, read by `migrate_gateway`
+#[derive(AnchorDeserialize)]
+pub struct LegacyGateway {
+    pub authority: Pubkey,
+    pub fee: u64,
+    pub bump: u8,
+}
+
+impl LegacyGateway {
     pub const LEN: usize = 32 + 8 + 1;
 }
 
+/// On-chain record of a paid nonce, readable for receipt verification
+#[account]
+pub struct PaymentReceipt {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub nonce: u64,
+    pub mint: Pubkey, // `NATIVE_SOL` for SOL payments
+    pub amount: u64,
+    pub endpoint_id: [u8; 32],
+    pub timestamp: i64,
+    pub bump: u8,
+    pub refunded: bool,
+}
+
+impl PaymentReceipt {
+    pub const LEN: usize = 32 + // gateway
+        32 + // user
+        8 + // nonce
+        32 + // mint
+        8 + // amount
+        32 + // endpoint_id
+        8 + // timestamp
+        1 + // bump
+        1; // refunded
+}
+
+/// Domain separator signed in front of every serialized `Voucher`
+pub const VOUCHER_PREFIX: &[u8] = b"risk:voucher:v1";
+
+/// An off-chain price quote from the API server for one payment. The server signs
+/// `VOUCHER_PREFIX` followed by the Borsh-serialized voucher with its voucher signer key.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct Voucher {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey, // `NATIVE_SOL` for SOL payments
+    pub amount: u64,
+    pub endpoint_id: [u8; 32],
+    pub expires_at: i64,
+    pub nonce: u64,
+}
+
+impl Voucher {
+    /// The bytes the voucher signer signs
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = VOUCHER_PREFIX.to_vec();
+        message.extend_from_slice(&self.try_to_vec().expect("voucher serializes"));
+        message
+    }
+}
+
+/// Endpoint ID for payments priced by the gateway fee rather than a `PriceEntry`
+pub const DEFAULT_ENDPOINT: [u8; 32] = [0; 32];
+
+/// Fee for one API endpoint, keyed by the hash of its route and version
+#[account]
+pub struct PriceEntry {
+    pub gateway: Pubkey,
+    pub endpoint_id: [u8; 32],
+    pub fee: u64,      // Fee in base units of `mint`
+    pub mint: Pubkey,  // Currency the endpoint is priced in
+    pub active: bool,  // Inactive endpoints can't be paid for
+    pub bump: u8,
+}
+
+impl PriceEntry {
+    pub const LEN: usize = 32 + // gateway
+        32 + // endpoint_id
+        8 + // fee
+        32 + // mint
+        1 + // active
+        1; // bump
+}
+
+/// A queued change to an endpoint's price
+#[account]
+pub struct PendingPriceChange {
+    pub price_entry: Pubkey,
+    pub fee: u64,
+    pub mint: Pubkey,
+    pub effective_at: i64, // Earliest time `apply_price_change` can run
+    pub bump: u8,
+}
+
+impl PendingPriceChange {
+    pub const LEN: usize = 32 + // price_entry
+        8 + // fee
+        32 + // mint
+        8 + // effective_at
+        1; // bump
+}
+
+/// A user's prepaid credit in one mint, held in the gateway's credit vault
+#[account]
+pub struct CreditAccount {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub balance: u64,
+    pub withdrawal_requested_at: i64, // 0 when no withdrawal is pending
+    pub bump: u8,
+}
+
+impl CreditAccount {
+    pub const LEN: usize = 32 + // gateway
+        32 + // user
+        32 + // mint
+        8 + // balance
+        8 + // withdrawal_requested_at
+        1; // bump
+}
+
+/// Subscription plan sold by the gateway
+#[account]
+pub struct Plan {
+    pub gateway: Pubkey,
+    pub plan_id: u64,
+    pub price: u64,        // Price per period in base units of `mint`
+    pub mint: Pubkey,      // Currency of `price`
+    pub period: i64,       // Period length in seconds
+    pub rate_limit_tier: u8,
+    pub active: bool,      // Inactive plans can't be bought or renewed
+    pub bump: u8,
+}
+
+impl Plan {
+    pub const LEN: usize = 32 + // gateway
+        8 + // plan_id
+        8 + // price
+        32 + // mint
+        8 + // period
+        1 + // rate_limit_tier
+        1 + // active
+        1; // bump
+}
+
+/// A queued change to a plan's price, period and mint
+#[account]
+pub struct PendingPlanChange {
+    pub plan: Pubkey,
+    pub price: u64,
+    pub period: i64,
+    pub mint: Pubkey,
+    pub effective_at: i64, // Earliest time `apply_plan_change` can run
+    pub bump: u8,
+}
+
+impl PendingPlanChange {
+    pub const LEN: usize = 32 + // plan
+        8 + // price
+        8 + // period
+        32 + // mint
+        8 + // effective_at
+        1; // bump
+}
+
+/// A user's paid access, extended by each pass purchase
+#[account]
+pub struct AccessPass {
+    pub user: Pubkey,
+    pub plan: Pubkey,
+    pub rate_limit_tier: u8,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+impl AccessPass {
+    pub const LEN: usize = 32 + // user
+        32 + // plan
+        1 + // rate_limit_tier
+        8 + // expires_at
+        1; // bump
+
+    pub fn is_active(&self, now: i64) -> bool {
+        self.expires_at > now
+    }
+}
+
 #[event]
 pub struct PaymentProcessed {
+    pub gateway: Pubkey,
     pub user: Pubkey,
+    pub mint: Pubkey,
     pub amount: u64,
     pub nonce: u64,
+    pub endpoint_id: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentRefunded {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeChangeQueued {
+    pub gateway: Pubkey,
+    pub current_fee: u64,
+    pub new_fee: u64,
+    pub mint: Pubkey,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct FeeChanged {
+    pub gateway: Pubkey,
+    pub previous_fee: u64,
+    pub fee: u64,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct SolFeeChangeQueued {
+    pub gateway: Pubkey,
+    pub current_sol_fee: u64,
+    pub new_sol_fee: Option<u64>,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct SolFeeChanged {
+    pub gateway: Pubkey,
+    pub previous_sol_fee: Option<u64>,
+    pub sol_fee: Option<u64>,
+}
+
+#[event]
+pub struct PriceChangeQueued {
+    pub gateway: Pubkey,
+    pub endpoint_id: [u8; 32],
+    pub current_fee: u64,
+    pub new_fee: u64,
+    pub mint: Pubkey,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct PriceChanged {
+    pub gateway: Pubkey,
+    pub endpoint_id: [u8; 32],
+    pub previous_fee: u64,
+    pub fee: u64,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct PlanChangeQueued {
+    pub gateway: Pubkey,
+    pub plan: Pubkey,
+    pub current_price: u64,
+    pub new_price: u64,
+    pub current_period: i64,
+    pub new_period: i64,
+    pub mint: Pubkey,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct PlanChanged {
+    pub gateway: Pubkey,
+    pub plan: Pubkey,
+    pub price: u64,
+    pub period: i64,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct AuthorityProposed {
+    pub gateway: Pubkey,
+    pub authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub gateway: Pubkey,
+    pub previous_authority: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub gateway: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub recipients: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CreditDeposited {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CreditsDebited {
+    pub gateway: Pubkey,
+    pub mint: Pubkey,
+    pub users: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CreditWithdrawn {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccessPassExtended {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub plan: Pubkey,
+    pub amount: u64,
+    pub expires_at: i64,
     pub timestamp: i64,
 }
 
@@ -135,5 +2546,105 @@ pub enum ErrorCode {
     InsufficientPayment,
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Plan period must be positive")]
+    InvalidPlanPeriod,
+    #[msg("Plan is not active")]
+    PlanInactive,
+    #[msg("At least one period must be purchased")]
+    InvalidPeriods,
+    #[msg("Access pass is still active on another plan")]
+    PassOnOtherPlan,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Mint is not accepted by this gateway")]
+    MintNotAccepted,
+    #[msg("Token account mint does not match the payment mint")]
+    MintMismatch,
+    #[msg("Treasury is not the gateway's treasury for this mint")]
+    InvalidTreasury,
+    #[msg("Mint is already accepted")]
+    MintAlreadyAccepted,
+    #[msg("Gateway accepts too many mints")]
+    TooManyMints,
+    #[msg("Revenue split has too many recipients")]
+    TooManyRecipients,
+    #[msg("Revenue shares must be positive and add up to 10,000 bps")]
+    InvalidRevenueSplit,
+    #[msg("No revenue split configured")]
+    RevenueSplitNotSet,
+    #[msg("Recipient token accounts must match the revenue split and mint")]
+    InvalidRecipient,
+    #[msg("Treasury balance is too low")]
+    InsufficientTreasury,
+    #[msg("Gateway already uses the current layout")]
+    AlreadyMigrated,
+    #[msg("Endpoint has no price entry")]
+    MissingPriceEntry,
+    #[msg("Endpoint is not active")]
+    EndpointInactive,
+    #[msg("Payment mint is not the currency of the fee or price")]
+    CurrencyMismatch,
+    #[msg("The default endpoint is priced by the gateway fee")]
+    InvalidEndpoint,
+    #[msg("Cooldown can't be negative")]
+    InvalidCooldown,
+    #[msg("Amount must be positive")]
+    InvalidAmount,
+    #[msg("Credit accounts must match the debit amounts, gateway and mint")]
+    InvalidCreditAccount,
+    #[msg("Credit balance is too low")]
+    InsufficientCredit,
+    #[msg("No credit withdrawal has been requested")]
+    WithdrawalNotRequested,
+    #[msg("Credit withdrawal cooldown has not passed")]
+    CreditCooldownActive,
+    #[msg("Gateway does not accept SOL payments")]
+    SolNotAccepted,
+    #[msg("Token-2022 mints with transfer fees, transfer hooks or a permanent delegate are not supported")]
+    UnsupportedMintExtension,
+    #[msg("No fee change is queued")]
+    NoPendingFeeChange,
+    #[msg("Fee change timelock has not passed")]
+    FeeChangeNotReady,
+    #[msg("Fee timelock can only be lengthened")]
+    TimelockDecrease,
+    #[msg("Fee timelock can be at most 30 days")]
+    TimelockTooLong,
+    #[msg("Signer is not the proposed authority")]
+    NotPendingAuthority,
+    #[msg("Payment has already been refunded")]
+    AlreadyRefunded,
+    #[msg("Refund must be non-zero and at most the amount paid")]
+    RefundExceedsPayment,
+    #[msg("Receipt belongs to another gateway")]
+    InvalidReceipt,
+    #[msg("Gateway does not accept vouchers")]
+    VouchersNotAccepted,
+    #[msg("Voucher payments must follow an ed25519 signature check")]
+    MissingVoucherSignature,
+    #[msg("Ed25519 instruction must check one signature stored in the instruction")]
+    InvalidVoucherSignature,
+    #[msg("Voucher is not signed by the gateway's voucher signer")]
+    InvalidVoucherSigner,
+    #[msg("Signed message is not a voucher")]
+    InvalidVoucher,
+    #[msg("Voucher has expired")]
+    VoucherExpired,
+    #[msg("Voucher is for another gateway")]
+    VoucherGatewayMismatch,
+    #[msg("Voucher is for another user")]
+    VoucherUserMismatch,
+    #[msg("Voucher is for another currency")]
+    VoucherMintMismatch,
+    #[msg("Voucher is for another nonce")]
+    VoucherNonceMismatch,
+    #[msg("Voucher is for another endpoint")]
+    VoucherEndpointMismatch,
+    #[msg("Price changes must be queued behind the fee timelock")]
+    PriceChangeTimelocked,
+    #[msg("Endpoint has a queued price change")]
+    PriceChangePending,
+    #[msg("SOL is accepted with set_sol_fee, not as a token mint")]
+    NativeMintNotAccepted,
 }
 
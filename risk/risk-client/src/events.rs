@@ -0,0 +1,200 @@
+use crate::{discriminator, ID};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use solana_sdk::pubkey::Pubkey;
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+/// An event of the risk program, logged as `Program data: ` followed by the base64 of its
+/// 8-byte Anchor discriminator and Borsh data
+pub trait Event: BorshDeserialize {
+    /// Name of the event struct in the program
+    const NAME: &'static str;
+}
+
+/// Emitted by the risk program for every recorded payment
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct PaymentProcessed {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey, // `NATIVE_SOL` for SOL payments
+    pub amount: u64,
+    pub nonce: u64,
+    pub endpoint_id: [u8; 32],
+    pub timestamp: i64,
+}
+
+impl Event for PaymentProcessed {
+    const NAME: &'static str = "PaymentProcessed";
+}
+
+/// Emitted by the risk program when a payment's receipt is refunded
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct PaymentRefunded {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub nonce: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+impl Event for PaymentRefunded {
+    const NAME: &'static str = "PaymentRefunded";
+}
+
+/// Decodes the `PaymentProcessed` events in a transaction's log messages
+pub fn parse_payment_processed(logs: &[String]) -> Vec<PaymentProcessed> {
+    parse_events(logs)
+}
+
+/// Decodes the `PaymentRefunded` events in a transaction's log messages
+pub fn parse_payment_refunded(logs: &[String]) -> Vec<PaymentRefunded> {
+    parse_events(logs)
+}
+
+/// Decodes the events of one type in a transaction's log messages.
+///
+/// Only data logged while the risk program itself is executing is decoded, so another
+/// program can't forge an event by logging the same bytes.
+pub fn parse_events<T: Event>(logs: &[String]) -> Vec<T> {
+    let event_discriminator = discriminator("event", T::NAME);
+    let risk_program = ID.to_string();
+
+    let mut invocations: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    for log in logs {
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA) {
+            if invocations.last() != Some(&risk_program.as_str()) {
+                continue;
+            }
+            let Ok(bytes) = STANDARD.decode(data) else {
+                continue;
+            };
+            if bytes.len() >= 8 && bytes[..8] == event_discriminator {
+                if let Ok(event) = T::deserialize(&mut &bytes[8..]) {
+                    events.push(event);
+                }
+            }
+        } else if let Some(program) = invoked_program(log) {
+            invocations.push(program);
+        } else if invocations.last().is_some_and(|program| is_program_exit(log, program)) {
+            invocations.pop();
+        }
+    }
+    events
+}
+
+/// Program of a `Program <id> invoke [<depth>]` line
+fn invoked_program(log: &str) -> Option<&str> {
+    let rest = log.strip_prefix("Program ")?;
+    let (program, depth) = rest.split_once(" invoke [")?;
+    depth.ends_with(']').then_some(program)
+}
+
+/// `Program <program> success` or `Program <program> failed: <error>`, for the program
+/// currently executing. Lines a program logs itself are prefixed `Program log: ` and never match.
+fn is_program_exit(log: &str, program: &str) -> bool {
+    log.strip_prefix("Program ")
+        .and_then(|rest| rest.strip_prefix(program))
+        .is_some_and(|outcome| outcome == " success" || outcome.starts_with(" failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_log(event: &PaymentProcessed) -> String {
+        let mut data = discriminator("event", "PaymentProcessed").to_vec();
+        data.extend_from_slice(event.gateway.as_ref());
+        data.extend_from_slice(event.user.as_ref());
+        data.extend_from_slice(event.mint.as_ref());
+        data.extend_from_slice(&event.amount.to_le_bytes());
+        data.extend_from_slice(&event.nonce.to_le_bytes());
+        data.extend_from_slice(&event.endpoint_id);
+        data.extend_from_slice(&event.timestamp.to_le_bytes());
+        format!("{}{}", PROGRAM_DATA, STANDARD.encode(data))
+    }
+
+    #[test]
+    fn test_parse_payment_processed() {
+        let event = PaymentProcessed {
+            gateway: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            amount: 1_000_000,
+            nonce: 7,
+            endpoint_id: crate::DEFAULT_ENDPOINT,
+            timestamp: 1_700_000_000,
+        };
+        let token_program = spl_token::id();
+        let other_program = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {} invoke [1]", ID),
+            "Program log: Instruction: ProcessPayment".to_string(),
+            format!("Program {} invoke [2]", token_program),
+            format!("Program {} success", token_program),
+            event_log(&event),
+            format!("Program {} success", ID),
+            // Same bytes logged by another program
+            format!("Program {} invoke [1]", other_program),
+            event_log(&event),
+            format!("Program {} success", other_program),
+        ];
+
+        assert_eq!(parse_payment_processed(&logs), vec![event]);
+    }
+
+    #[test]
+    fn test_parse_payment_refunded() {
+        let refund = PaymentRefunded {
+            gateway: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            nonce: 7,
+            amount: 500_000,
+            timestamp: 1_700_000_000,
+        };
+        let mut data = discriminator("event", "PaymentRefunded").to_vec();
+        data.extend_from_slice(refund.gateway.as_ref());
+        data.extend_from_slice(refund.user.as_ref());
+        data.extend_from_slice(refund.mint.as_ref());
+        data.extend_from_slice(&refund.nonce.to_le_bytes());
+        data.extend_from_slice(&refund.amount.to_le_bytes());
+        data.extend_from_slice(&refund.timestamp.to_le_bytes());
+        let logs = vec![
+            format!("Program {} invoke [1]", ID),
+            "Program log: Instruction: Refund".to_string(),
+            format!("{}{}", PROGRAM_DATA, STANDARD.encode(data)),
+            format!("Program {} success", ID),
+        ];
+
+        assert_eq!(parse_payment_refunded(&logs), vec![refund]);
+        assert!(parse_payment_processed(&logs).is_empty());
+    }
+
+    #[test]
+    fn test_logged_exit_lines_do_not_end_an_invocation() {
+        let event = PaymentProcessed {
+            gateway: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            amount: 1_000_000,
+            nonce: 7,
+            endpoint_id: crate::DEFAULT_ENDPOINT,
+            timestamp: 1_700_000_000,
+        };
+        let other_program = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {} invoke [1]", ID),
+            format!("Program {} invoke [2]", other_program),
+            // A CPI'd program logging this must not look like its exit back to the risk program
+            "Program log: success".to_string(),
+            event_log(&event),
+            format!("Program {} success", other_program),
+            format!("Program {} success", ID),
+        ];
+
+        assert!(parse_payment_processed(&logs).is_empty());
+    }
+}
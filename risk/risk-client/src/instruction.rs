@@ -0,0 +1,186 @@
+use crate::{discriminator, pda, DEFAULT_ENDPOINT, ID};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+/// `initialize`: creates gateway `gateway_id` with `authority` as its authority and payer,
+/// charging `fee` in `fee_mint` for the default endpoint
+pub fn initialize(authority: &Pubkey, gateway_id: u64, fee: u64, fee_mint: &Pubkey) -> Instruction {
+    let (gateway, _) = pda::gateway(gateway_id);
+    Instruction {
+        program_id: ID,
+        accounts: vec![
+            AccountMeta::new(gateway, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: data("initialize", &[&gateway_id.to_le_bytes(), &fee.to_le_bytes(), fee_mint.as_ref()]),
+    }
+}
+
+/// `update_fee`: queues a change to `gateway`'s fee and fee mint, signed by its authority.
+/// `mint` must be an accepted mint.
+pub fn update_fee(gateway: &Pubkey, authority: &Pubkey, new_fee: u64, mint: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: vec![
+            AccountMeta::new(*gateway, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data: data("update_fee", &[&new_fee.to_le_bytes(), mint.as_ref()]),
+    }
+}
+
+/// `add_accepted_mint`: accepts payments in `mint` on `gateway`, creating its treasury.
+/// `token_program` is the program that owns `mint`.
+pub fn add_accepted_mint(gateway: &Pubkey, authority: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Instruction {
+    let (treasury, _) = pda::treasury(gateway, mint);
+    Instruction {
+        program_id: ID,
+        accounts: vec![
+            AccountMeta::new(*gateway, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(treasury, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: data("add_accepted_mint", &[]),
+    }
+}
+
+/// Builder for `process_payment`. By default the payment is priced by the gateway fee
+/// and goes through SPL Token; the treasury and receipt addresses are derived.
+#[derive(Debug, Clone)]
+pub struct ProcessPayment {
+    gateway: Pubkey,
+    user: Pubkey,
+    mint: Pubkey,
+    user_token_account: Pubkey,
+    amount: u64,
+    nonce: u64,
+    endpoint_id: [u8; 32],
+    price_entry: Option<Pubkey>,
+    voucher: bool,
+    token_program: Pubkey,
+}
+
+impl ProcessPayment {
+    pub fn new(gateway: Pubkey, user: Pubkey, mint: Pubkey, user_token_account: Pubkey, amount: u64, nonce: u64) -> Self {
+        Self {
+            gateway,
+            user,
+            mint,
+            user_token_account,
+            amount,
+            nonce,
+            endpoint_id: DEFAULT_ENDPOINT,
+            price_entry: None,
+            voucher: false,
+            token_program: spl_token::id(),
+        }
+    }
+
+    /// Pays for `endpoint_id` at the price in its price entry
+    pub fn endpoint(mut self, endpoint_id: [u8; 32]) -> Self {
+        self.endpoint_id = endpoint_id;
+        self.price_entry = Some(pda::price_entry(&self.gateway, &endpoint_id).0);
+        self
+    }
+
+    /// Pays for `endpoint_id` at the price of a signed voucher. The transaction must put the
+    /// ed25519 instruction checking the voucher signature right before this one.
+    pub fn voucher(mut self, endpoint_id: [u8; 32]) -> Self {
+        self.endpoint_id = endpoint_id;
+        self.price_entry = None;
+        self.voucher = true;
+        self
+    }
+
+    /// Token program that owns `mint`, for Token-2022 mints
+    pub fn token_program(mut self, token_program: Pubkey) -> Self {
+        self.token_program = token_program;
+        self
+    }
+
+    pub fn instruction(&self) -> Instruction {
+        let (treasury, _) = pda::treasury(&self.gateway, &self.mint);
+        let (receipt, _) = pda::receipt(&self.gateway, &self.user, self.nonce);
+        Instruction {
+            program_id: ID,
+            accounts: vec![
+                AccountMeta::new_readonly(self.gateway, false),
+                optional(self.price_entry),
+                AccountMeta::new(self.user, true),
+                AccountMeta::new_readonly(self.mint, false),
+                AccountMeta::new(self.user_token_account, false),
+                AccountMeta::new(treasury, false),
+                AccountMeta::new(receipt, false),
+                optional(self.voucher.then(sysvar::instructions::id)),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            data: data(
+                "process_payment",
+                &[&self.amount.to_le_bytes(), &self.nonce.to_le_bytes(), &self.endpoint_id],
+            ),
+        }
+    }
+}
+
+/// Anchor passes the program ID in place of an omitted optional account
+fn optional(account: Option<Pubkey>) -> AccountMeta {
+    AccountMeta::new_readonly(account.unwrap_or(ID), false)
+}
+
+fn data(name: &str, args: &[&[u8]]) -> Vec<u8> {
+    let mut data = discriminator("global", name).to_vec();
+    for arg in args {
+        data.extend_from_slice(arg);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_payment_accounts() {
+        let (gateway, _) = pda::gateway(7);
+        let user = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let payment = ProcessPayment::new(gateway, user, mint, Pubkey::new_unique(), 1_000_000, 42);
+
+        let ix = payment.instruction();
+        assert_eq!(ix.accounts.len(), 10);
+        assert_eq!(ix.accounts[1].pubkey, ID);
+        assert!(ix.accounts[2].is_signer && ix.accounts[2].is_writable);
+        assert_eq!(ix.accounts[5].pubkey, pda::treasury(&gateway, &mint).0);
+        assert_eq!(ix.accounts[6].pubkey, pda::receipt(&gateway, &user, 42).0);
+        assert_eq!(ix.accounts[7].pubkey, ID);
+        assert_eq!(ix.data.len(), 8 + 8 + 8 + 32);
+        assert_eq!(&ix.data[8..16], &1_000_000u64.to_le_bytes());
+
+        let endpoint_id = [3; 32];
+        let priced = payment.clone().endpoint(endpoint_id).instruction();
+        assert_eq!(priced.accounts[1].pubkey, pda::price_entry(&gateway, &endpoint_id).0);
+        assert_eq!(&priced.data[24..], &endpoint_id);
+
+        let vouchered = payment.voucher(endpoint_id).instruction();
+        assert_eq!(vouchered.accounts[1].pubkey, ID);
+        assert_eq!(vouchered.accounts[7].pubkey, sysvar::instructions::id());
+    }
+
+    #[test]
+    fn test_update_fee_data() {
+        let mint = Pubkey::new_unique();
+        let ix = update_fee(&Pubkey::new_unique(), &Pubkey::new_unique(), 42, &mint);
+        assert_eq!(&ix.data[..8], &[232, 253, 195, 247, 148, 212, 73, 222]);
+        assert_eq!(&ix.data[8..16], &42u64.to_le_bytes());
+        assert_eq!(&ix.data[16..], mint.as_ref());
+        assert!(ix.accounts[0].is_writable && ix.accounts[1].is_signer);
+    }
+}
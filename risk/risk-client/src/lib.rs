@@ -0,0 +1,36 @@
+//! Off-chain client for the risk payment gateway program: instruction builders,
+//! PDA derivation, account fetching and event parsing.
+
+pub mod events;
+pub mod instruction;
+pub mod pda;
+pub mod state;
+
+use solana_sdk::{hash::hash, pubkey, pubkey::Pubkey};
+
+/// Address of the deployed risk program
+pub const ID: Pubkey = pubkey!("84zwY58ivSmpGY8gsenAc2c4XpwBUyh1thF9XXx3LhfD");
+
+/// Endpoint ID for payments priced by the gateway fee rather than a price entry
+pub const DEFAULT_ENDPOINT: [u8; 32] = [0; 32];
+
+/// Currency of SOL payments in receipts and events (the wrapped SOL mint)
+pub const NATIVE_SOL: Pubkey = spl_token::native_mint::ID;
+
+/// Anchor discriminator: the first 8 bytes of `sha256("<namespace>:<name>")`
+fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("{}:{}", namespace, name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discriminator_matches_anchor() {
+        // Discriminator Anchor generates for risk::update_fee
+        assert_eq!(discriminator("global", "update_fee"), [232, 253, 195, 247, 148, 212, 73, 222]);
+    }
+}
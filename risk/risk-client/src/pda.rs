@@ -0,0 +1,51 @@
+use crate::ID;
+use solana_sdk::pubkey::Pubkey;
+
+/// Gateway created by `initialize` with `gateway_id`
+pub fn gateway(gateway_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"gateway", &gateway_id.to_le_bytes()], &ID)
+}
+
+/// The original singleton gateway, from before gateways had IDs
+pub fn legacy_gateway() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"gateway"], &ID)
+}
+
+/// Receipt recording that `user` paid `nonce` to `gateway`
+pub fn receipt(gateway: &Pubkey, user: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"receipt", gateway.as_ref(), user.as_ref(), &nonce.to_le_bytes()], &ID)
+}
+
+/// Gateway's treasury token account for payments in `mint`
+pub fn treasury(gateway: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"treasury", gateway.as_ref(), mint.as_ref()], &ID)
+}
+
+/// Gateway's treasury for SOL payments
+pub fn sol_treasury(gateway: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"sol_treasury", gateway.as_ref()], &ID)
+}
+
+/// Price entry of an endpoint
+pub fn price_entry(gateway: &Pubkey, endpoint_id: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"price", gateway.as_ref(), endpoint_id], &ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gateways_are_keyed_by_id() {
+        let (first, _) = gateway(1);
+        assert_eq!(gateway(1).0, first);
+        assert_ne!(gateway(2).0, first);
+        assert_ne!(legacy_gateway().0, first);
+    }
+
+    #[test]
+    fn test_receipts_are_per_gateway() {
+        let user = Pubkey::new_unique();
+        assert_ne!(receipt(&gateway(1).0, &user, 7).0, receipt(&gateway(2).0, &user, 7).0);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::discriminator;
+use anyhow::{bail, Context, Result};
+use borsh::BorshDeserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+/// An account type of the risk program, stored after its 8-byte Anchor discriminator
+pub trait AccountState: BorshDeserialize {
+    /// Name of the account struct in the program
+    const NAME: &'static str;
+
+    /// Deserializes account data, checking the discriminator. Trailing space the
+    /// program reserved for growth is ignored.
+    fn from_account_data(data: &[u8]) -> Result<Self> {
+        if data.len() < 8 || data[..8] != discriminator("account", Self::NAME) {
+            bail!("Account is not a {}", Self::NAME);
+        }
+        Self::deserialize(&mut &data[8..]).with_context(|| format!("Invalid {} account data", Self::NAME))
+    }
+}
+
+/// Fetches and deserializes an account of the risk program
+pub fn fetch<T: AccountState>(rpc_client: &RpcClient, address: &Pubkey) -> Result<T> {
+    let account = rpc_client
+        .get_account(address)
+        .with_context(|| format!("Failed to fetch {} {}", T::NAME, address))?;
+    if account.owner != crate::ID {
+        bail!("{} is not owned by the risk program", address);
+    }
+    T::from_account_data(&account.data)
+}
+
+/// Fetches a payment receipt, or None if `user` hasn't paid `nonce` to `gateway`
+pub fn fetch_receipt(
+    rpc_client: &RpcClient,
+    gateway: &Pubkey,
+    user: &Pubkey,
+    nonce: u64,
+) -> Result<Option<PaymentReceipt>> {
+    let (address, _) = crate::pda::receipt(gateway, user, nonce);
+    let account = rpc_client
+        .get_account_with_commitment(&address, rpc_client.commitment())
+        .with_context(|| format!("Failed to fetch receipt {}", address))?
+        .value;
+    account.map(|account| PaymentReceipt::from_account_data(&account.data)).transpose()
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Gateway {
+    pub gateway_id: Option<u64>,
+    pub authority: Pubkey,
+    pub fee: u64,
+    pub bump: u8,
+    pub accepted_mints: Vec<AcceptedMint>,
+    pub revenue_split: Vec<RevenueShare>,
+    pub metering_authority: Pubkey,
+    pub credit_withdrawal_cooldown: i64,
+    pub sol_fee: Option<u64>,
+    pub pending_authority: Option<Pubkey>,
+    pub pending_fee: Option<PendingFeeChange>,
+    pub fee_timelock: i64,
+    pub voucher_signer: Option<Pubkey>,
+    pub pending_sol_fee: Option<PendingSolFeeChange>,
+    pub fee_mint: Pubkey,
+}
+
+impl AccountState for Gateway {
+    const NAME: &'static str = "Gateway";
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct AcceptedMint {
+    pub mint: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RevenueShare {
+    pub recipient: Pubkey,
+    pub bps: u16,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PendingFeeChange {
+    pub fee: u64,
+    pub mint: Pubkey,
+    pub effective_at: i64,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PendingSolFeeChange {
+    pub sol_fee: Option<u64>,
+    pub effective_at: i64,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct PaymentReceipt {
+    pub gateway: Pubkey,
+    pub user: Pubkey,
+    pub nonce: u64,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub endpoint_id: [u8; 32],
+    pub timestamp: i64,
+    pub bump: u8,
+    pub refunded: bool,
+}
+
+impl AccountState for PaymentReceipt {
+    const NAME: &'static str = "PaymentReceipt";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_from_account_data() {
+        let gateway = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let mut data = discriminator("account", "PaymentReceipt").to_vec();
+        data.extend_from_slice(gateway.as_ref());
+        data.extend_from_slice(user.as_ref());
+        data.extend_from_slice(&42u64.to_le_bytes());
+        data.extend_from_slice(crate::NATIVE_SOL.as_ref());
+        data.extend_from_slice(&5_000u64.to_le_bytes());
+        data.extend_from_slice(&[9; 32]);
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.extend_from_slice(&[254, 0]);
+
+        let receipt = PaymentReceipt::from_account_data(&data).unwrap();
+        assert_eq!(receipt.gateway, gateway);
+        assert_eq!(receipt.user, user);
+        assert_eq!(receipt.nonce, 42);
+        assert_eq!(receipt.mint, crate::NATIVE_SOL);
+        assert_eq!(receipt.amount, 5_000);
+        assert!(!receipt.refunded);
+
+        data[0] ^= 1;
+        assert!(PaymentReceipt::from_account_data(&data).is_err());
+        assert!(Gateway::from_account_data(&data).is_err());
+    }
+}
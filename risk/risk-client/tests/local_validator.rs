@@ -0,0 +1,75 @@
+//! Runs the builders against a local validator with the risk program deployed, e.g.
+//! `solana-test-validator --bpf-program 84zwY58ivSmpGY8gsenAc2c4XpwBUyh1thF9XXx3LhfD target/deploy/risk.so`,
+//! then `cargo test -- --ignored`. `RISK_RPC_URL` overrides the default localhost URL.
+
+use risk_client::{instruction, pda, state};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, instruction::Instruction, native_token::LAMPORTS_PER_SOL, program_pack::Pack,
+    signature::Keypair, signer::Signer, system_instruction, transaction::Transaction,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[test]
+#[ignore = "needs a local validator with the risk program deployed"]
+fn test_initialize_and_queue_fee_change() {
+    let url = std::env::var("RISK_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+    let rpc_client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+
+    let authority = Keypair::new();
+    let airdrop = rpc_client.request_airdrop(&authority.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    while !rpc_client.confirm_transaction(&airdrop).unwrap() {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+
+    let send = |instructions: &[Instruction], signers: &[&Keypair]| {
+        let blockhash = rpc_client.get_latest_blockhash().unwrap();
+        let mut all_signers = vec![&authority];
+        all_signers.extend_from_slice(signers);
+        let tx = Transaction::new_signed_with_payer(instructions, Some(&authority.pubkey()), &all_signers, blockhash);
+        rpc_client.send_and_confirm_transaction(&tx).unwrap();
+    };
+
+    let mint = Keypair::new();
+    let mint_rent = rpc_client
+        .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)
+        .unwrap();
+    send(
+        &[
+            system_instruction::create_account(
+                &authority.pubkey(),
+                &mint.pubkey(),
+                mint_rent,
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint2(&spl_token::id(), &mint.pubkey(), &authority.pubkey(), None, 6)
+                .unwrap(),
+        ],
+        &[&mint],
+    );
+
+    let gateway_id = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+    let (gateway, bump) = pda::gateway(gateway_id);
+    send(
+        &[
+            instruction::initialize(&authority.pubkey(), gateway_id, 1_000_000, &mint.pubkey()),
+            instruction::add_accepted_mint(&gateway, &authority.pubkey(), &mint.pubkey(), &spl_token::id()),
+        ],
+        &[],
+    );
+
+    let account: state::Gateway = state::fetch(&rpc_client, &gateway).unwrap();
+    assert_eq!(account.gateway_id, Some(gateway_id));
+    assert_eq!(account.authority, authority.pubkey());
+    assert_eq!(account.fee, 1_000_000);
+    assert_eq!(account.bump, bump);
+    assert_eq!(account.fee_mint, mint.pubkey());
+
+    send(&[instruction::update_fee(&gateway, &authority.pubkey(), 2_000_000, &mint.pubkey())], &[]);
+    let account: state::Gateway = state::fetch(&rpc_client, &gateway).unwrap();
+    assert_eq!(account.fee, 1_000_000);
+    assert_eq!(account.pending_fee.map(|pending| pending.fee), Some(2_000_000));
+
+    assert!(state::fetch_receipt(&rpc_client, &gateway, &authority.pubkey(), 1).unwrap().is_none());
+}
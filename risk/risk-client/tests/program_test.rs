@@ -0,0 +1,157 @@
+//! Runs the builders against the compiled program in `solana-program-test`'s BanksClient.
+//!
+//! Needs `risk.so`, built by `anchor build` into `target/deploy`. `SBF_OUT_DIR` overrides
+//! where it is loaded from.
+
+use risk_client::state::{AccountState, Gateway, PaymentReceipt};
+use risk_client::{events, instruction, pda, DEFAULT_ENDPOINT, ID};
+use solana_program_test::{BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash, instruction::Instruction, program_pack::Pack, pubkey::Pubkey, signature::Keypair, signer::Signer,
+    system_instruction, transaction::Transaction,
+};
+use std::path::PathBuf;
+
+const FEE: u64 = 1_000_000;
+
+fn program_test() -> ProgramTest {
+    let out_dir = std::env::var("SBF_OUT_DIR").map(PathBuf::from).unwrap_or_else(|_| {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../target/deploy")
+    });
+    assert!(
+        out_dir.join("risk.so").exists(),
+        "{} not found: run `anchor build` in the risk workspace first",
+        out_dir.join("risk.so").display()
+    );
+    std::env::set_var("SBF_OUT_DIR", &out_dir);
+
+    let mut program_test = ProgramTest::new("risk", ID, None);
+    program_test.prefer_bpf(true);
+    program_test
+}
+
+async fn send(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    signers: &[&Keypair],
+    instructions: &[Instruction],
+    blockhash: Hash,
+) -> Result<Vec<String>, String> {
+    let mut all_signers = vec![payer];
+    all_signers.extend_from_slice(signers);
+    let tx = Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &all_signers, blockhash);
+    let processed = banks_client
+        .process_transaction_with_metadata(tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    processed.result.map_err(|e| e.to_string())?;
+    Ok(processed.metadata.map(|metadata| metadata.log_messages).unwrap_or_default())
+}
+
+/// Creates a mint and a token account of `owner` holding `amount` of it
+async fn create_funded_mint(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    owner: &Pubkey,
+    amount: u64,
+    blockhash: Hash,
+) -> (Pubkey, Pubkey) {
+    let rent = banks_client.get_rent().await.unwrap();
+    let mint = Keypair::new();
+    let token_account = Keypair::new();
+    let instructions = [
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint2(&spl_token::id(), &mint.pubkey(), &payer.pubkey(), None, 6).unwrap(),
+        system_instruction::create_account(
+            &payer.pubkey(),
+            &token_account.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_account3(&spl_token::id(), &token_account.pubkey(), &mint.pubkey(), owner)
+            .unwrap(),
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &mint.pubkey(),
+            &token_account.pubkey(),
+            &payer.pubkey(),
+            &[],
+            amount,
+        )
+        .unwrap(),
+    ];
+    send(banks_client, payer, &[&mint, &token_account], &instructions, blockhash).await.unwrap();
+    (mint.pubkey(), token_account.pubkey())
+}
+
+async fn account<T: AccountState>(banks_client: &mut BanksClient, address: &Pubkey) -> Option<T> {
+    let account = banks_client.get_account(*address).await.unwrap()?;
+    assert_eq!(account.owner, ID);
+    Some(T::from_account_data(&account.data).unwrap())
+}
+
+#[tokio::test]
+async fn test_pay_and_replay_per_gateway() {
+    let mut context = program_test().start_with_context().await;
+    let payer = context.payer.insecure_clone();
+    let blockhash = context.last_blockhash;
+    let banks_client = &mut context.banks_client;
+    let (mint, user_token_account) = create_funded_mint(banks_client, &payer, &payer.pubkey(), 3 * FEE, blockhash).await;
+
+    let gateways: Vec<Pubkey> = [1, 2].iter().map(|gateway_id| pda::gateway(*gateway_id).0).collect();
+    for (gateway_id, gateway) in [1, 2].into_iter().zip(&gateways) {
+        let instructions = [
+            instruction::initialize(&payer.pubkey(), gateway_id, FEE, &mint),
+            instruction::add_accepted_mint(gateway, &payer.pubkey(), &mint, &spl_token::id()),
+        ];
+        send(banks_client, &payer, &[], &instructions, blockhash).await.unwrap();
+
+        let account: Gateway = account(banks_client, gateway).await.unwrap();
+        assert_eq!(account.gateway_id, Some(gateway_id));
+        assert_eq!(account.fee, FEE);
+        assert_eq!(account.fee_mint, mint);
+        assert_eq!(account.accepted_mints[0].treasury, pda::treasury(gateway, &mint).0);
+    }
+
+    let pay = |gateway: Pubkey| {
+        instruction::ProcessPayment::new(gateway, payer.pubkey(), mint, user_token_account, FEE, 7).instruction()
+    };
+    let logs = send(banks_client, &payer, &[], &[pay(gateways[0])], blockhash).await.unwrap();
+    let payments = events::parse_payment_processed(&logs);
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0].gateway, gateways[0]);
+    assert_eq!(payments[0].nonce, 7);
+    assert_eq!(payments[0].amount, FEE);
+
+    let (receipt, _) = pda::receipt(&gateways[0], &payer.pubkey(), 7);
+    let receipt: PaymentReceipt = account(banks_client, &receipt).await.unwrap();
+    assert_eq!(receipt.mint, mint);
+    assert_eq!(receipt.endpoint_id, DEFAULT_ENDPOINT);
+    assert!(!receipt.refunded);
+
+    // Receipts are per gateway: the same nonce pays another gateway, but not the same one twice
+    send(banks_client, &payer, &[], &[pay(gateways[1])], blockhash).await.unwrap();
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let banks_client = &mut context.banks_client;
+    assert!(send(banks_client, &payer, &[], &[pay(gateways[0])], blockhash).await.is_err());
+
+    let (treasury, _) = pda::treasury(&gateways[0], &mint);
+    let treasury = banks_client.get_account(treasury).await.unwrap().unwrap();
+    assert_eq!(spl_token::state::Account::unpack(&treasury.data).unwrap().amount, FEE);
+
+    // The gateway fee is only due in its fee mint, even when another mint is accepted
+    let (other_mint, other_token_account) =
+        create_funded_mint(banks_client, &payer, &payer.pubkey(), FEE, blockhash).await;
+    let accept = instruction::add_accepted_mint(&gateways[0], &payer.pubkey(), &other_mint, &spl_token::id());
+    send(banks_client, &payer, &[], &[accept], blockhash).await.unwrap();
+    let pay_other =
+        instruction::ProcessPayment::new(gateways[0], payer.pubkey(), other_mint, other_token_account, FEE, 8).instruction();
+    assert!(send(banks_client, &payer, &[], &[pay_other], blockhash).await.is_err());
+}
@@ -0,0 +1,172 @@
+use crate::store::PaymentStore;
+use anyhow::Result;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+use solana_sdk::pubkey::Pubkey;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+pub type SharedStore = Arc<Mutex<PaymentStore>>;
+
+/// HTTP API the paid-request middleware calls before serving:
+///
+/// - `GET /verify?gateway=&user=&nonce=&mint=&endpoint=&minAmount=` 200 with the payment if
+///   `user` paid `nonce` to `gateway` in `mint`, it wasn't refunded and it matches every
+///   optional constraint, 402 otherwise
+/// - `GET /health`
+pub fn router(store: SharedStore) -> Router {
+    Router::new()
+        .route("/verify", get(verify))
+        .route("/health", get(|| async { Json(json!({ "status": "ok" })) }))
+        .with_state(store)
+}
+
+pub async fn serve(address: SocketAddr, store: SharedStore) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to bind API on {}: {}", address, e))?;
+    axum::serve(listener, router(store)).await?;
+    Ok(())
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+    }
+}
+
+fn lock(store: &SharedStore) -> std::sync::MutexGuard<'_, PaymentStore> {
+    // A panic while holding the lock cannot leave SQLite in a partial state
+    store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyQuery {
+    gateway: String,
+    user: String,
+    nonce: u64,
+    mint: String, // `NATIVE_SOL` for SOL payments
+    endpoint: Option<String>, // Hex endpoint ID
+    min_amount: Option<u64>, // In `mint`'s base units
+}
+
+fn pubkey(name: &str, value: &str) -> Result<Pubkey, ApiError> {
+    Pubkey::from_str(value).map_err(|_| ApiError(StatusCode::BAD_REQUEST, format!("Invalid {} {}", name, value)))
+}
+
+async fn verify(State(store): State<SharedStore>, Query(query): Query<VerifyQuery>) -> Result<Response, ApiError> {
+    let gateway = pubkey("gateway", &query.gateway)?;
+    let user = pubkey("user", &query.user)?;
+    let mint = pubkey("mint", &query.mint)?;
+    let Some(payment) = lock(&store).payment(&gateway, &user, query.nonce)? else {
+        return Ok(unverified("Payment not found"));
+    };
+
+    if payment.refund_signature.is_some() {
+        return Ok(unverified("Payment was refunded"));
+    }
+    if mint.to_string() != payment.mint {
+        return Ok(unverified("Payment was made in another mint"));
+    }
+    if query
+        .endpoint
+        .as_ref()
+        .is_some_and(|endpoint| !endpoint.eq_ignore_ascii_case(&payment.endpoint_id))
+    {
+        return Ok(unverified("Payment was made for another endpoint"));
+    }
+    if query.min_amount.is_some_and(|min_amount| payment.amount < min_amount) {
+        return Ok(unverified("Payment amount is too low"));
+    }
+
+    Ok(Json(json!({ "verified": true, "payment": payment })).into_response())
+}
+
+fn unverified(reason: &str) -> Response {
+    (StatusCode::PAYMENT_REQUIRED, Json(json!({ "verified": false, "reason": reason }))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::tests::{payment, refund};
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    async fn get_status(store: &SharedStore, uri: &str) -> StatusCode {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        router(store.clone()).oneshot(request).await.unwrap().status()
+    }
+
+    async fn get(store: &SharedStore, uri: &str) -> (StatusCode, Value) {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = router(store.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_verify() {
+        let store = PaymentStore::open_in_memory().unwrap();
+        let user = Pubkey::new_unique();
+        let event = payment(user, 7);
+        store.record("sig1", 10, &event).unwrap();
+        let store: SharedStore = Arc::new(Mutex::new(store));
+        let verify = |gateway: Pubkey, nonce: u64, mint: Pubkey| {
+            format!("/verify?gateway={}&user={}&nonce={}&mint={}", gateway, user, nonce, mint)
+        };
+
+        let (status, body) = get(&store, &verify(event.gateway, 7, event.mint)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["verified"], true);
+        assert_eq!(body["payment"]["signature"], "sig1");
+        assert_eq!(body["payment"]["amount"], 1_000_000);
+
+        let uri = format!("{}&endpoint={}&minAmount=1000000", verify(event.gateway, 7, event.mint), "AB".repeat(32));
+        assert_eq!(get(&store, &uri).await.0, StatusCode::OK);
+
+        let (status, body) = get(&store, &verify(event.gateway, 8, event.mint)).await;
+        assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(body["verified"], false);
+
+        let uri = verify(Pubkey::new_unique(), 7, event.mint);
+        assert_eq!(get(&store, &uri).await.0, StatusCode::PAYMENT_REQUIRED);
+        // A minAmount the payment meets, but in units of another mint
+        let uri = format!("{}&minAmount=1", verify(event.gateway, 7, Pubkey::new_unique()));
+        assert_eq!(get(&store, &uri).await.0, StatusCode::PAYMENT_REQUIRED);
+        let uri = format!("{}&endpoint={}", verify(event.gateway, 7, event.mint), "00".repeat(32));
+        assert_eq!(get(&store, &uri).await.0, StatusCode::PAYMENT_REQUIRED);
+        let uri = format!("{}&minAmount=1000001", verify(event.gateway, 7, event.mint));
+        assert_eq!(get(&store, &uri).await.0, StatusCode::PAYMENT_REQUIRED);
+
+        let uri = format!("/verify?user={}&nonce=7&mint={}", user, event.mint);
+        assert_eq!(get_status(&store, &uri).await, StatusCode::BAD_REQUEST);
+        let uri = format!("/verify?gateway={}&user=nope&nonce=7&mint={}", event.gateway, event.mint);
+        assert_eq!(get(&store, &uri).await.0, StatusCode::BAD_REQUEST);
+
+        lock(&store).record_refund("sig2", 11, &refund(&event)).unwrap();
+        let (status, body) = get(&store, &verify(event.gateway, 7, event.mint)).await;
+        assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(body["reason"], "Payment was refunded");
+    }
+}
@@ -0,0 +1,114 @@
+use crate::api::SharedStore;
+use anyhow::Result;
+use futures::StreamExt;
+use log::{info, warn};
+use risk_client::events::{parse_payment_processed, parse_payment_refunded};
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+};
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+use std::str::FromStr;
+
+/// Indexes the payments and refunds in one transaction's logs. Returns how many were new.
+pub fn index_logs(store: &SharedStore, signature: &str, slot: u64, logs: &[String]) -> Result<usize> {
+    let store = store.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut indexed = 0;
+    for event in parse_payment_processed(logs) {
+        if store.record(signature, slot, &event)? {
+            info!("💰 {} paid nonce {} ({} of {})", event.user, event.nonce, event.amount, event.mint);
+            indexed += 1;
+        }
+    }
+    for event in parse_payment_refunded(logs) {
+        if store.record_refund(signature, slot, &event)? {
+            info!("↩️  {} was refunded nonce {} ({} of {})", event.user, event.nonce, event.amount, event.mint);
+            indexed += 1;
+        }
+    }
+    Ok(indexed)
+}
+
+/// Indexes every risk transaction since the last indexed payment or refund, oldest first,
+/// so those made while the indexer was down are not missed.
+pub async fn backfill(rpc_client: &RpcClient, store: &SharedStore) -> Result<usize> {
+    let until = {
+        let store = store.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        store.last_signature()?.map(|signature| Signature::from_str(&signature)).transpose()?
+    };
+
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let page = rpc_client
+            .get_signatures_for_address_with_config(
+                &risk_client::ID,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: None,
+                    commitment: Some(rpc_client.commitment()),
+                },
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("getSignaturesForAddress failed: {}", e))?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        signatures.extend(page.into_iter().filter(|status| status.err.is_none()));
+    }
+
+    let mut indexed = 0;
+    for status in signatures.iter().rev() {
+        let signature = Signature::from_str(&status.signature)?;
+        let transaction = rpc_client
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(rpc_client.commitment()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("getTransaction failed for {}: {}", signature, e))?;
+        let logs: Option<Vec<String>> = transaction.transaction.meta.and_then(|meta| meta.log_messages.into());
+        indexed += index_logs(store, &status.signature, transaction.slot, &logs.unwrap_or_default())?;
+    }
+    Ok(indexed)
+}
+
+/// Follows the risk program's logs until the subscription drops. The backfill runs once
+/// subscribed, so nothing sent while (re)connecting is missed.
+pub async fn follow(rpc_client: &RpcClient, ws_url: &str, store: &SharedStore) -> Result<()> {
+    let pubsub_client = PubsubClient::new(ws_url)
+        .await
+        .map_err(|e| anyhow::anyhow!("WebSocket connection failed: {}", e))?;
+    let (mut notifications, _unsubscribe) = pubsub_client
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![risk_client::ID.to_string()]),
+            RpcTransactionLogsConfig { commitment: Some(rpc_client.commitment()) },
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("logsSubscribe failed: {}", e))?;
+    info!("🔌 Subscribed to risk program logs");
+
+    match backfill(rpc_client, store).await {
+        Ok(indexed) => info!("📥 Backfilled {} payment(s) and refund(s)", indexed),
+        Err(e) => warn!("⚠️  Backfill failed: {}", e),
+    }
+
+    while let Some(notification) = notifications.next().await {
+        let logs = notification.value;
+        if logs.err.is_some() {
+            continue;
+        }
+        if let Err(e) = index_logs(store, &logs.signature, notification.context.slot, &logs.logs) {
+            warn!("⚠️  Failed to index {}: {}", logs.signature, e);
+        }
+    }
+    Ok(())
+}
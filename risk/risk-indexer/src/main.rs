@@ -0,0 +1,64 @@
+mod api;
+mod follower;
+mod store;
+
+use anyhow::Result;
+use clap::Parser;
+use log::{error, info, warn};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[derive(Parser)]
+#[command(name = "risk-indexer")]
+#[command(author, version, about = "Indexes risk payments and verifies them for paid APIs", long_about = None)]
+struct Cli {
+    /// RPC endpoint used to backfill missed transactions
+    #[arg(long, value_name = "URL", default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+
+    /// WebSocket endpoint the program logs are followed on
+    #[arg(long, value_name = "URL", default_value = "ws://127.0.0.1:8900")]
+    ws_url: String,
+
+    /// SQLite database payments are indexed into
+    #[arg(long, value_name = "PATH", default_value = "risk-payments.db")]
+    db: PathBuf,
+
+    /// Address the verify API listens on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8787")]
+    listen: SocketAddr,
+
+    /// Seconds to wait before resubscribing after the WebSocket drops
+    #[arg(long, value_name = "SECS", default_value_t = 5)]
+    retry_interval: u64,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let cli = Cli::parse();
+
+    let store: api::SharedStore = Arc::new(Mutex::new(store::PaymentStore::open(&cli.db)?));
+    info!("🗄️  Indexing payments into {}", cli.db.display());
+
+    let api_store = store.clone();
+    let listen = cli.listen;
+    tokio::spawn(async move {
+        if let Err(e) = api::serve(listen, api_store).await {
+            error!("❌ Verify API stopped: {}", e);
+        }
+    });
+    info!("🌐 Verify API listening on http://{}", cli.listen);
+
+    let rpc_client = RpcClient::new_with_commitment(cli.rpc_url.clone(), CommitmentConfig::confirmed());
+    loop {
+        match follower::follow(&rpc_client, &cli.ws_url, &store).await {
+            Ok(()) => warn!("⚠️  Log subscription closed"),
+            Err(e) => warn!("⚠️  {}", e),
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(cli.retry_interval)).await;
+    }
+}
@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use log::warn;
+use risk_client::events::{PaymentProcessed, PaymentRefunded};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+
+/// Bumped when the schema changes. The index is rebuilt from the chain rather than migrated.
+const SCHEMA_VERSION: i64 = 1;
+
+// u64 amounts and nonces are stored bit-for-bit in SQLite's signed INTEGER
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS payments (
+    gateway TEXT NOT NULL,
+    user TEXT NOT NULL,
+    nonce INTEGER NOT NULL,
+    mint TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    endpoint_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    refund_signature TEXT,
+    refund_slot INTEGER,
+    PRIMARY KEY (gateway, user, nonce)
+);
+CREATE INDEX IF NOT EXISTS payments_slot ON payments(slot);
+CREATE INDEX IF NOT EXISTS payments_refund_slot ON payments(refund_slot);
+";
+
+/// A `PaymentProcessed` event as indexed, with the transaction it came from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRecord {
+    pub gateway: String,
+    pub user: String,
+    pub nonce: u64,
+    pub mint: String,
+    pub amount: u64,
+    pub endpoint_id: String, // Hex
+    pub timestamp: i64,
+    pub signature: String,
+    pub slot: u64,
+    /// Transaction that refunded the payment, if any
+    pub refund_signature: Option<String>,
+}
+
+/// SQLite store of indexed payments, keyed by (gateway, user, nonce) like the program's receipts
+pub struct PaymentStore {
+    connection: Connection,
+}
+
+impl PaymentStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection =
+            Connection::open(path).with_context(|| format!("Failed to open database {}", path.display()))?;
+        Self::init(connection)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(connection: Connection) -> Result<Self> {
+        let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version < SCHEMA_VERSION {
+            let existing: i64 = connection.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'payments'",
+                [],
+                |row| row.get(0),
+            )?;
+            if existing > 0 {
+                warn!("⚠️  Payment index is from an older version; it will be rebuilt from the chain");
+                connection.execute_batch("DROP TABLE payments;")?;
+            }
+        }
+        connection.execute_batch(SCHEMA)?;
+        connection.execute_batch(&format!("PRAGMA user_version = {};", SCHEMA_VERSION))?;
+        Ok(Self { connection })
+    }
+
+    /// Stores a payment. Returns false if its (gateway, user, nonce) was already indexed.
+    pub fn record(&self, signature: &str, slot: u64, event: &PaymentProcessed) -> Result<bool> {
+        let inserted = self.connection.execute(
+            "INSERT OR IGNORE INTO payments
+                (gateway, user, nonce, mint, amount, endpoint_id, timestamp, signature, slot)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                event.gateway.to_string(),
+                event.user.to_string(),
+                event.nonce as i64,
+                event.mint.to_string(),
+                event.amount as i64,
+                hex(&event.endpoint_id),
+                event.timestamp,
+                signature,
+                slot as i64,
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Marks a payment refunded. Returns false if it isn't indexed or was already marked.
+    pub fn record_refund(&self, signature: &str, slot: u64, event: &PaymentRefunded) -> Result<bool> {
+        let updated = self.connection.execute(
+            "UPDATE payments SET refund_signature = ?1, refund_slot = ?2
+             WHERE gateway = ?3 AND user = ?4 AND nonce = ?5 AND refund_signature IS NULL",
+            params![
+                signature,
+                slot as i64,
+                event.gateway.to_string(),
+                event.user.to_string(),
+                event.nonce as i64,
+            ],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn payment(&self, gateway: &Pubkey, user: &Pubkey, nonce: u64) -> Result<Option<PaymentRecord>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT gateway, user, nonce, mint, amount, endpoint_id, timestamp, signature, slot, refund_signature
+                 FROM payments WHERE gateway = ?1 AND user = ?2 AND nonce = ?3",
+                params![gateway.to_string(), user.to_string(), nonce as i64],
+                payment_from_row,
+            )
+            .optional()?)
+    }
+
+    /// Signature of the most recently indexed payment or refund, where a backfill can stop
+    pub fn last_signature(&self) -> Result<Option<String>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT signature, slot FROM payments
+                 UNION ALL
+                 SELECT refund_signature, refund_slot FROM payments WHERE refund_signature IS NOT NULL
+                 ORDER BY slot DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+}
+
+fn payment_from_row(row: &Row) -> rusqlite::Result<PaymentRecord> {
+    Ok(PaymentRecord {
+        gateway: row.get(0)?,
+        user: row.get(1)?,
+        nonce: row.get::<_, i64>(2)? as u64,
+        mint: row.get(3)?,
+        amount: row.get::<_, i64>(4)? as u64,
+        endpoint_id: row.get(5)?,
+        timestamp: row.get(6)?,
+        signature: row.get(7)?,
+        slot: row.get::<_, i64>(8)? as u64,
+        refund_signature: row.get(9)?,
+    })
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn payment(user: Pubkey, nonce: u64) -> PaymentProcessed {
+        PaymentProcessed {
+            gateway: Pubkey::new_unique(),
+            user,
+            mint: Pubkey::new_unique(),
+            amount: 1_000_000,
+            nonce,
+            endpoint_id: [0xab; 32],
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    pub fn refund(payment: &PaymentProcessed) -> PaymentRefunded {
+        PaymentRefunded {
+            gateway: payment.gateway,
+            user: payment.user,
+            mint: payment.mint,
+            nonce: payment.nonce,
+            amount: payment.amount,
+            timestamp: payment.timestamp + 60,
+        }
+    }
+
+    #[test]
+    fn test_record_keyed_by_gateway_user_and_nonce() {
+        let store = PaymentStore::open_in_memory().unwrap();
+        let user = Pubkey::new_unique();
+        let event = payment(user, u64::MAX);
+
+        assert!(store.record("sig1", 10, &event).unwrap());
+        assert!(!store.record("sig2", 11, &event).unwrap());
+        assert!(store.record("sig3", 12, &payment(user, 1)).unwrap());
+        // The same nonce paid to another gateway
+        let other_gateway = payment(user, u64::MAX);
+        assert!(store.record("sig4", 13, &other_gateway).unwrap());
+
+        let record = store.payment(&event.gateway, &user, u64::MAX).unwrap().unwrap();
+        assert_eq!(record.nonce, u64::MAX);
+        assert_eq!(record.signature, "sig1");
+        assert_eq!(record.endpoint_id, "ab".repeat(32));
+        assert_eq!(record.refund_signature, None);
+        let record = store.payment(&other_gateway.gateway, &user, u64::MAX).unwrap().unwrap();
+        assert_eq!(record.signature, "sig4");
+        assert!(store.payment(&event.gateway, &user, 2).unwrap().is_none());
+        assert_eq!(store.last_signature().unwrap().as_deref(), Some("sig4"));
+    }
+
+    #[test]
+    fn test_record_refund() {
+        let store = PaymentStore::open_in_memory().unwrap();
+        let event = payment(Pubkey::new_unique(), 7);
+        assert!(!store.record_refund("sig0", 9, &refund(&event)).unwrap());
+        store.record("sig1", 10, &event).unwrap();
+
+        assert!(store.record_refund("sig2", 11, &refund(&event)).unwrap());
+        assert!(!store.record_refund("sig3", 12, &refund(&event)).unwrap());
+        let record = store.payment(&event.gateway, &event.user, 7).unwrap().unwrap();
+        assert_eq!(record.refund_signature.as_deref(), Some("sig2"));
+        assert_eq!(store.last_signature().unwrap().as_deref(), Some("sig2"));
+    }
+
+    #[test]
+    fn test_rebuilds_legacy_index() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch("CREATE TABLE payments (user TEXT NOT NULL, nonce INTEGER NOT NULL, PRIMARY KEY (user, nonce));")
+            .unwrap();
+        let store = PaymentStore::init(connection).unwrap();
+        assert!(store.record("sig1", 10, &payment(Pubkey::new_unique(), 7)).unwrap());
+
+        let connection = store.connection;
+        let store = PaymentStore::init(connection).unwrap();
+        assert_eq!(store.last_signature().unwrap().as_deref(), Some("sig1"));
+    }
+}
@@ -7,7 +7,12 @@ import {
     createMint,
     createAccount,
     mintTo,
-    createAssociatedTokenAccountInstruction,
+    NATIVE_MINT,
+    TOKEN_2022_PROGRAM_ID,
+    ExtensionType,
+    getMintLen,
+    createInitializeMintInstruction,
+    createInitializePermanentDelegateInstruction,
 } from "@solana/spl-token";
 import {
     PublicKey,
@@ -15,8 +20,19 @@ import {
     SystemProgram,
     LAMPORTS_PER_SOL,
     Connection,
+    Ed25519Program,
+    SYSVAR_INSTRUCTIONS_PUBKEY,
+    Transaction,
+    sendAndConfirmTransaction,
 } from "@solana/web3.js";
 import { expect } from "chai";
+import { createHash } from "crypto";
+
+// Pays the gateway fee instead of a per-endpoint price
+const DEFAULT_ENDPOINT = Array(32).fill(0);
+
+// Gateways are keyed by ID, so each test run gets its own
+const GATEWAY_ID = new anchor.BN(Date.now());
 
 describe("risk", () => {
     const provider = anchor.AnchorProvider.env();
@@ -71,15 +87,14 @@ describe("risk", () => {
 
         // Derive gateway PDA
         [gateway] = PublicKey.findProgramAddressSync(
-            [Buffer.from("gateway")],
+            [Buffer.from("gateway"), GATEWAY_ID.toArrayLike(Buffer, "le", 8)],
             program.programId
         );
 
-        // Create treasury token account (associated token account for PDA)
-        treasuryTokenAccount = await getAssociatedTokenAddress(
-            usdcMint,
-            gateway,
-            true
+        // Derive the gateway's treasury token account for the mint
+        [treasuryTokenAccount] = PublicKey.findProgramAddressSync(
+            [Buffer.from("treasury"), gateway.toBuffer(), usdcMint.toBuffer()],
+            program.programId
         );
 
         // Create user token account
@@ -96,9 +111,6 @@ describe("risk", () => {
             user.publicKey
         );
 
-        // Note: Treasury token account will be created automatically when first transfer happens
-        // But we can create it explicitly if needed
-
         // Mint USDC to user
         await mintTo(
             provider.connection,
@@ -127,7 +139,7 @@ describe("risk", () => {
         } catch (e) {
             // Gateway doesn't exist, initialize it
             const tx = await program.methods
-                .initialize(fee)
+                .initialize(GATEWAY_ID, fee, usdcMint)
                 .accounts({
                     gateway,
                     authority: authority.publicKey,
@@ -155,7 +167,7 @@ describe("risk", () => {
             // Initialize if not exists
             const fee = new anchor.BN(1000000);
             await program.methods
-                .initialize(fee)
+                .initialize(GATEWAY_ID, fee, usdcMint)
                 .accounts({
                     gateway,
                     authority: authority.publicKey,
@@ -165,33 +177,43 @@ describe("risk", () => {
                 .rpc();
         }
 
-        // Create treasury token account if it doesn't exist
-        const treasuryInfo = await provider.connection.getAccountInfo(
-            treasuryTokenAccount
-        );
-
-        if (!treasuryInfo) {
-            // Create the associated token account for the treasury PDA
-            const createATAInstruction =
-                createAssociatedTokenAccountInstruction(
-                    authority.publicKey, // payer
-                    treasuryTokenAccount, // ata
-                    gateway, // owner (PDA)
-                    usdcMint // mint
-                );
+        // Accept the mint, creating the gateway's treasury for it
+        await program.methods
+            .addAcceptedMint()
+            .accounts({
+                gateway,
+                mint: usdcMint,
+                treasuryTokenAccount,
+                authority: authority.publicKey,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
 
-            const tx = new anchor.web3.Transaction().add(createATAInstruction);
-            await provider.sendAndConfirm(tx, [authority]);
-        }
+        const [receipt] = PublicKey.findProgramAddressSync(
+            [
+                Buffer.from("receipt"),
+                gateway.toBuffer(),
+                user.publicKey.toBuffer(),
+                nonce.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+        );
 
         const tx = await program.methods
-            .processPayment(amount, nonce)
+            .processPayment(amount, nonce, DEFAULT_ENDPOINT)
             .accounts({
                 gateway,
+                priceEntry: null,
                 user: user.publicKey,
+                mint: usdcMint,
                 userTokenAccount,
                 treasuryTokenAccount,
+                receipt,
+                instructions: null,
                 tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
             })
             .signers([user])
             .rpc();
@@ -205,5 +227,1067 @@ describe("risk", () => {
         expect(parseInt(treasuryBalance.value.amount)).to.be.at.least(
             amount.toNumber()
         );
+
+        // The receipt records the nonce
+        const receiptAccount = await program.account.paymentReceipt.fetch(
+            receipt
+        );
+        expect(receiptAccount.user.toString()).to.equal(
+            user.publicKey.toString()
+        );
+        expect(receiptAccount.nonce.toNumber()).to.equal(nonce.toNumber());
+        expect(receiptAccount.mint.toString()).to.equal(usdcMint.toString());
+        expect(receiptAccount.amount.toNumber()).to.equal(amount.toNumber());
+
+        // Paying again with the same nonce fails
+        let replayed = true;
+        try {
+            await program.methods
+                .processPayment(amount, nonce, DEFAULT_ENDPOINT)
+                .accounts({
+                    gateway,
+                    priceEntry: null,
+                    user: user.publicKey,
+                    mint: usdcMint,
+                    userTokenAccount,
+                    treasuryTokenAccount,
+                    receipt,
+                    instructions: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([user])
+                .rpc();
+        } catch (e) {
+            replayed = false;
+        }
+        expect(replayed).to.equal(false);
+
+        // The authority refunds the payment once
+        await program.methods
+            .refund(amount)
+            .accounts({
+                gateway,
+                receipt,
+                mint: usdcMint,
+                treasuryTokenAccount,
+                userTokenAccount,
+                authority: authority.publicKey,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([authority])
+            .rpc();
+        const refundedReceipt = await program.account.paymentReceipt.fetch(
+            receipt
+        );
+        expect(refundedReceipt.refunded).to.equal(true);
+
+        let refundedTwice = true;
+        try {
+            await program.methods
+                .refund(amount)
+                .accounts({
+                    gateway,
+                    receipt,
+                    mint: usdcMint,
+                    treasuryTokenAccount,
+                    userTokenAccount,
+                    authority: authority.publicKey,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                })
+                .signers([authority])
+                .rpc();
+        } catch (e) {
+            refundedTwice = false;
+        }
+        expect(refundedTwice).to.equal(false);
+    });
+
+    it("Rejects payments into a treasury the gateway does not own", async () => {
+        // A token account for the accepted mint, owned by the user instead of the gateway
+        const userOwnedTreasury = await createAccount(
+            provider.connection,
+            user,
+            usdcMint,
+            user.publicKey,
+            Keypair.generate()
+        );
+        const nonce = new anchor.BN(Date.now());
+        const [receipt] = PublicKey.findProgramAddressSync(
+            [
+                Buffer.from("receipt"),
+                gateway.toBuffer(),
+                user.publicKey.toBuffer(),
+                nonce.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+        );
+
+        try {
+            await program.methods
+                .processPayment(new anchor.BN(1000000), nonce, DEFAULT_ENDPOINT)
+                .accounts({
+                    gateway,
+                    priceEntry: null,
+                    user: user.publicKey,
+                    mint: usdcMint,
+                    userTokenAccount,
+                    treasuryTokenAccount: userOwnedTreasury,
+                    receipt,
+                    instructions: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([user])
+                .rpc();
+            expect.fail("Payment into a foreign treasury should be rejected");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("InvalidTreasury");
+        }
+    });
+
+    it("Charges the gateway fee only in its fee mint", async () => {
+        // A second accepted mint, e.g. with other decimals, can't pay the USDC fee
+        const otherMint = await createMint(
+            provider.connection,
+            authority,
+            authority.publicKey,
+            null,
+            9
+        );
+        const [otherTreasury] = PublicKey.findProgramAddressSync(
+            [Buffer.from("treasury"), gateway.toBuffer(), otherMint.toBuffer()],
+            program.programId
+        );
+        await program.methods
+            .addAcceptedMint()
+            .accounts({
+                gateway,
+                mint: otherMint,
+                treasuryTokenAccount: otherTreasury,
+                authority: authority.publicKey,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+        const otherTokenAccount = await createAccount(
+            provider.connection,
+            user,
+            otherMint,
+            user.publicKey
+        );
+        await mintTo(
+            provider.connection,
+            authority,
+            otherMint,
+            otherTokenAccount,
+            authority,
+            1e9
+        );
+
+        const gatewayAccount = await program.account.gateway.fetch(gateway);
+        expect(gatewayAccount.feeMint.toString()).to.equal(usdcMint.toString());
+
+        const nonce = new anchor.BN(Date.now());
+        const [receipt] = PublicKey.findProgramAddressSync(
+            [
+                Buffer.from("receipt"),
+                gateway.toBuffer(),
+                user.publicKey.toBuffer(),
+                nonce.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+        );
+        try {
+            await program.methods
+                .processPayment(gatewayAccount.fee, nonce, DEFAULT_ENDPOINT)
+                .accounts({
+                    gateway,
+                    priceEntry: null,
+                    user: user.publicKey,
+                    mint: otherMint,
+                    userTokenAccount: otherTokenAccount,
+                    treasuryTokenAccount: otherTreasury,
+                    receipt,
+                    instructions: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([user])
+                .rpc();
+            expect.fail("The gateway fee should only be paid in its fee mint");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("CurrencyMismatch");
+        }
+    });
+
+    it("Sells a plan and stacks access pass renewals", async () => {
+        const planId = new anchor.BN(Date.now());
+        const price = new anchor.BN(5000000); // 5 USDC
+        const period = new anchor.BN(30 * 24 * 60 * 60); // 30 days
+
+        const [plan] = PublicKey.findProgramAddressSync(
+            [
+                Buffer.from("plan"),
+                gateway.toBuffer(),
+                planId.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+        );
+        const [accessPass] = PublicKey.findProgramAddressSync(
+            [Buffer.from("access_pass"), gateway.toBuffer(), user.publicKey.toBuffer()],
+            program.programId
+        );
+
+        await program.methods
+            .createPlan(planId, price, period, 2, usdcMint)
+            .accounts({
+                gateway,
+                plan,
+                authority: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+
+        const purchase = (periods: number) =>
+            program.methods
+                .purchasePass(periods)
+                .accounts({
+                    gateway,
+                    plan,
+                    accessPass,
+                    user: user.publicKey,
+                    mint: usdcMint,
+                    userTokenAccount,
+                    treasuryTokenAccount,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([user])
+                .rpc();
+
+        await purchase(1);
+        const first = await program.account.accessPass.fetch(accessPass);
+        expect(first.plan.toString()).to.equal(plan.toString());
+        expect(first.rateLimitTier).to.equal(2);
+
+        // Renewing before expiry extends from the current expiry, not from now
+        await purchase(2);
+        const renewed = await program.account.accessPass.fetch(accessPass);
+        expect(renewed.expiresAt.sub(first.expiresAt).toNumber()).to.equal(
+            period.toNumber() * 2
+        );
+
+        // A price rise is queued, so buyers keep paying the current price meanwhile
+        const [pendingPlan] = PublicKey.findProgramAddressSync(
+            [Buffer.from("pending_plan"), plan.toBuffer()],
+            program.programId
+        );
+        await program.methods
+            .queuePlanChange(new anchor.BN(50000000), period, usdcMint)
+            .accounts({
+                gateway,
+                plan,
+                pendingPlan,
+                authority: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+        const pending = await program.account.pendingPlanChange.fetch(
+            pendingPlan
+        );
+        expect(pending.price.toNumber()).to.equal(50000000);
+        const planAccount = await program.account.plan.fetch(plan);
+        expect(planAccount.price.toNumber()).to.equal(price.toNumber());
+
+        try {
+            await program.methods
+                .updatePlan(new anchor.BN(50000000), period, 2, true)
+                .accounts({ gateway, plan, authority: authority.publicKey })
+                .signers([authority])
+                .rpc();
+            expect.fail("update_plan should not change the price");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("PriceChangeTimelocked");
+        }
+
+        await program.methods
+            .cancelPlanChange()
+            .accounts({
+                gateway,
+                plan,
+                pendingPlan,
+                authority: authority.publicKey,
+            })
+            .signers([authority])
+            .rpc();
+    });
+
+    it("Withdraws the treasury across the revenue split", async () => {
+        const team = Keypair.generate();
+        const teamTokenAccount = await createAccount(
+            provider.connection,
+            authority,
+            usdcMint,
+            team.publicKey
+        );
+        const protocolTokenAccount = await createAccount(
+            provider.connection,
+            authority,
+            usdcMint,
+            authority.publicKey,
+            Keypair.generate()
+        );
+
+        await program.methods
+            .setRevenueSplit([
+                { recipient: team.publicKey, bps: 7000 },
+                { recipient: authority.publicKey, bps: 3000 },
+            ])
+            .accounts({ gateway, authority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+
+        const amount = new anchor.BN(1000001);
+        await program.methods
+            .withdraw(amount)
+            .accounts({
+                gateway,
+                mint: usdcMint,
+                treasuryTokenAccount,
+                authority: authority.publicKey,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .remainingAccounts([
+                { pubkey: teamTokenAccount, isWritable: true, isSigner: false },
+                { pubkey: protocolTokenAccount, isWritable: true, isSigner: false },
+            ])
+            .signers([authority])
+            .rpc();
+
+        const teamBalance = await provider.connection.getTokenAccountBalance(
+            teamTokenAccount
+        );
+        const protocolBalance =
+            await provider.connection.getTokenAccountBalance(
+                protocolTokenAccount
+            );
+        expect(teamBalance.value.amount).to.equal("700000");
+        // The last recipient receives the rounding dust
+        expect(protocolBalance.value.amount).to.equal("300001");
+    });
+
+    it("Charges the endpoint's price for priced endpoints", async () => {
+        const endpointId = [
+            ...createHash("sha256")
+                .update("GET /api/v1/data/liquidation-params:v1")
+                .digest(),
+        ];
+        const [priceEntry] = PublicKey.findProgramAddressSync(
+            [Buffer.from("price"), gateway.toBuffer(), Buffer.from(endpointId)],
+            program.programId
+        );
+
+        await program.methods
+            .setPrice(endpointId, new anchor.BN(3000000), usdcMint, true)
+            .accounts({
+                gateway,
+                priceEntry,
+                authority: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+
+        const pay = async (amount: number) => {
+            const nonce = new anchor.BN(Date.now());
+            const [receipt] = PublicKey.findProgramAddressSync(
+                [
+                    Buffer.from("receipt"),
+                    gateway.toBuffer(),
+                    user.publicKey.toBuffer(),
+                    nonce.toArrayLike(Buffer, "le", 8),
+                ],
+                program.programId
+            );
+            await program.methods
+                .processPayment(new anchor.BN(amount), nonce, endpointId)
+                .accounts({
+                    gateway,
+                    priceEntry,
+                    user: user.publicKey,
+                    mint: usdcMint,
+                    userTokenAccount,
+                    treasuryTokenAccount,
+                    receipt,
+                    instructions: null,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([user])
+                .rpc();
+            return receipt;
+        };
+
+        // The gateway fee (1 USDC) is below this endpoint's price
+        try {
+            await pay(1000000);
+            expect.fail("Underpaying the endpoint should be rejected");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("InsufficientPayment");
+        }
+
+        const receipt = await pay(3000000);
+        const receiptAccount = await program.account.paymentReceipt.fetch(
+            receipt
+        );
+        expect(receiptAccount.endpointId).to.deep.equal(endpointId);
+
+        // Repricing an existing endpoint goes through the fee timelock
+        try {
+            await program.methods
+                .setPrice(endpointId, new anchor.BN(9000000), usdcMint, true)
+                .accounts({
+                    gateway,
+                    priceEntry,
+                    authority: authority.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([authority])
+                .rpc();
+            expect.fail("Existing prices should only change through the timelock");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("PriceChangeTimelocked");
+        }
+
+        const [pendingPrice] = PublicKey.findProgramAddressSync(
+            [Buffer.from("pending_price"), priceEntry.toBuffer()],
+            program.programId
+        );
+        await program.methods
+            .queuePriceChange(new anchor.BN(9000000), usdcMint)
+            .accounts({
+                gateway,
+                priceEntry,
+                pendingPrice,
+                authority: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+        try {
+            await program.methods
+                .applyPriceChange()
+                .accounts({
+                    gateway,
+                    priceEntry,
+                    pendingPrice,
+                    authority: authority.publicKey,
+                })
+                .rpc();
+            expect.fail("Price changes should wait for the timelock");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("FeeChangeNotReady");
+        }
+        await program.methods
+            .cancelPriceChange()
+            .accounts({
+                gateway,
+                priceEntry,
+                pendingPrice,
+                authority: authority.publicKey,
+            })
+            .signers([authority])
+            .rpc();
+
+        const entry = await program.account.priceEntry.fetch(priceEntry);
+        expect(entry.fee.toNumber()).to.equal(3000000);
+        expect(await provider.connection.getAccountInfo(pendingPrice)).to.equal(
+            null
+        );
+
+        // Removing keeps the entry, so re-pricing the endpoint still needs the timelock
+        await program.methods
+            .removePrice()
+            .accounts({
+                gateway,
+                priceEntry,
+                pendingPrice,
+                authority: authority.publicKey,
+            })
+            .signers([authority])
+            .rpc();
+        const removed = await program.account.priceEntry.fetch(priceEntry);
+        expect(removed.active).to.equal(false);
+        try {
+            await program.methods
+                .setPrice(endpointId, new anchor.BN(1), usdcMint, true)
+                .accounts({
+                    gateway,
+                    priceEntry,
+                    authority: authority.publicKey,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([authority])
+                .rpc();
+            expect.fail("A removed endpoint should not be re-priced at once");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("PriceChangeTimelocked");
+        }
+    });
+
+    it("Meters usage against prepaid credit", async () => {
+        const metering = Keypair.generate();
+        const [creditAccount] = PublicKey.findProgramAddressSync(
+            [
+                Buffer.from("credit"),
+                gateway.toBuffer(),
+                user.publicKey.toBuffer(),
+                usdcMint.toBuffer(),
+            ],
+            program.programId
+        );
+        const [creditVault] = PublicKey.findProgramAddressSync(
+            [Buffer.from("credit_vault"), gateway.toBuffer(), usdcMint.toBuffer()],
+            program.programId
+        );
+
+        // No cooldown so the test can withdraw right after requesting
+        await program.methods
+            .setMetering(metering.publicKey, new anchor.BN(0))
+            .accounts({ gateway, authority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+
+        await program.methods
+            .depositCredit(new anchor.BN(2000000))
+            .accounts({
+                gateway,
+                creditAccount,
+                user: user.publicKey,
+                mint: usdcMint,
+                userTokenAccount,
+                creditVault,
+                tokenProgram: TOKEN_PROGRAM_ID,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+
+        await program.methods
+            .debitCredits([new anchor.BN(250000)])
+            .accounts({
+                gateway,
+                meteringAuthority: metering.publicKey,
+                mint: usdcMint,
+                creditVault,
+                treasuryTokenAccount,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .remainingAccounts([
+                { pubkey: creditAccount, isWritable: true, isSigner: false },
+            ])
+            .signers([metering])
+            .rpc();
+
+        let credit = await program.account.creditAccount.fetch(creditAccount);
+        expect(credit.balance.toNumber()).to.equal(1750000);
+
+        await program.methods
+            .requestCreditWithdrawal()
+            .accounts({ gateway, creditAccount, user: user.publicKey })
+            .signers([user])
+            .rpc();
+        await program.methods
+            .withdrawCredit(new anchor.BN(1750000))
+            .accounts({
+                gateway,
+                creditAccount,
+                user: user.publicKey,
+                mint: usdcMint,
+                userTokenAccount,
+                creditVault,
+                tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([user])
+            .rpc();
+
+        credit = await program.account.creditAccount.fetch(creditAccount);
+        expect(credit.balance.toNumber()).to.equal(0);
+    });
+
+    it("Processes a native SOL payment", async () => {
+        const solFee = new anchor.BN(LAMPORTS_PER_SOL / 100);
+        const [solTreasury] = PublicKey.findProgramAddressSync(
+            [Buffer.from("sol_treasury"), gateway.toBuffer()],
+            program.programId
+        );
+        await program.methods
+            .setSolFee(solFee)
+            .accounts({
+                gateway,
+                solTreasury,
+                authority: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+
+        const nonce = new anchor.BN(Date.now() + 3);
+        const [receipt] = PublicKey.findProgramAddressSync(
+            [
+                Buffer.from("receipt"),
+                gateway.toBuffer(),
+                user.publicKey.toBuffer(),
+                nonce.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+        );
+
+        await program.methods
+            .processSolPayment(solFee, nonce, DEFAULT_ENDPOINT)
+            .accounts({
+                gateway,
+                priceEntry: null,
+                user: user.publicKey,
+                solTreasury,
+                receipt,
+                instructions: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+
+        const treasuryBalance = await provider.connection.getBalance(
+            solTreasury
+        );
+        expect(treasuryBalance).to.be.at.least(solFee.toNumber());
+
+        // Once SOL is accepted, fee changes are queued
+        await program.methods
+            .setSolFee(solFee.muln(10))
+            .accounts({
+                gateway,
+                solTreasury,
+                authority: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+        const queued = await program.account.gateway.fetch(gateway);
+        expect(queued.solFee.toNumber()).to.equal(solFee.toNumber());
+        expect(queued.pendingSolFee.solFee.toNumber()).to.equal(
+            solFee.toNumber() * 10
+        );
+        await program.methods
+            .cancelSolFeeChange()
+            .accounts({ gateway, authority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+
+        // SOL receipts record the native mint as their currency
+        const receiptAccount = await program.account.paymentReceipt.fetch(
+            receipt
+        );
+        expect(receiptAccount.mint.toString()).to.equal(
+            NATIVE_MINT.toString()
+        );
+    });
+
+    it("Rejects the wrapped SOL mint as an accepted mint", async () => {
+        const [wsolTreasury] = PublicKey.findProgramAddressSync(
+            [Buffer.from("treasury"), gateway.toBuffer(), NATIVE_MINT.toBuffer()],
+            program.programId
+        );
+        try {
+            await program.methods
+                .addAcceptedMint()
+                .accounts({
+                    gateway,
+                    mint: NATIVE_MINT,
+                    treasuryTokenAccount: wsolTreasury,
+                    authority: authority.publicKey,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([authority])
+                .rpc();
+            expect.fail("SOL should only be accepted through set_sol_fee");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("NativeMintNotAccepted");
+        }
+    });
+
+    it("Rejects Token-2022 mints with a permanent delegate", async () => {
+        // The delegate could move funds out of the treasuries
+        const mint = Keypair.generate();
+        const mintLen = getMintLen([ExtensionType.PermanentDelegate]);
+        await sendAndConfirmTransaction(
+            provider.connection,
+            new Transaction().add(
+                SystemProgram.createAccount({
+                    fromPubkey: authority.publicKey,
+                    newAccountPubkey: mint.publicKey,
+                    space: mintLen,
+                    lamports:
+                        await provider.connection.getMinimumBalanceForRentExemption(
+                            mintLen
+                        ),
+                    programId: TOKEN_2022_PROGRAM_ID,
+                }),
+                createInitializePermanentDelegateInstruction(
+                    mint.publicKey,
+                    authority.publicKey,
+                    TOKEN_2022_PROGRAM_ID
+                ),
+                createInitializeMintInstruction(
+                    mint.publicKey,
+                    6,
+                    authority.publicKey,
+                    null,
+                    TOKEN_2022_PROGRAM_ID
+                )
+            ),
+            [authority, mint]
+        );
+
+        const [delegatedTreasury] = PublicKey.findProgramAddressSync(
+            [Buffer.from("treasury"), gateway.toBuffer(), mint.publicKey.toBuffer()],
+            program.programId
+        );
+        try {
+            await program.methods
+                .addAcceptedMint()
+                .accounts({
+                    gateway,
+                    mint: mint.publicKey,
+                    treasuryTokenAccount: delegatedTreasury,
+                    authority: authority.publicKey,
+                    tokenProgram: TOKEN_2022_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                })
+                .signers([authority])
+                .rpc();
+            expect.fail("Mints with a permanent delegate should be rejected");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("UnsupportedMintExtension");
+        }
+    });
+
+    it("Accepts SOL fees below the rent-exempt minimum", async () => {
+        // A fresh gateway, so its SOL treasury starts empty
+        const smallId = GATEWAY_ID.addn(2);
+        const [smallGateway] = PublicKey.findProgramAddressSync(
+            [Buffer.from("gateway"), smallId.toArrayLike(Buffer, "le", 8)],
+            program.programId
+        );
+        const [solTreasury] = PublicKey.findProgramAddressSync(
+            [Buffer.from("sol_treasury"), smallGateway.toBuffer()],
+            program.programId
+        );
+        await program.methods
+            .initialize(smallId, new anchor.BN(1000000), usdcMint)
+            .accounts({
+                gateway: smallGateway,
+                authority: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+
+        const solFee = new anchor.BN(5000);
+        await program.methods
+            .setSolFee(solFee)
+            .accounts({
+                gateway: smallGateway,
+                solTreasury,
+                authority: authority.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([authority])
+            .rpc();
+        const rentExempt =
+            await provider.connection.getMinimumBalanceForRentExemption(0);
+        expect(await provider.connection.getBalance(solTreasury)).to.equal(
+            rentExempt
+        );
+
+        const nonce = new anchor.BN(Date.now() + 4);
+        const [receipt] = PublicKey.findProgramAddressSync(
+            [
+                Buffer.from("receipt"),
+                smallGateway.toBuffer(),
+                user.publicKey.toBuffer(),
+                nonce.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+        );
+        await program.methods
+            .processSolPayment(solFee, nonce, DEFAULT_ENDPOINT)
+            .accounts({
+                gateway: smallGateway,
+                priceEntry: null,
+                user: user.publicKey,
+                solTreasury,
+                receipt,
+                instructions: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+
+        expect(await provider.connection.getBalance(solTreasury)).to.equal(
+            rentExempt + solFee.toNumber()
+        );
+
+        // Receipts are per gateway, so the same nonce can still pay the main gateway
+        const mainFee = (await program.account.gateway.fetch(gateway)).solFee;
+        const [mainTreasury] = PublicKey.findProgramAddressSync(
+            [Buffer.from("sol_treasury"), gateway.toBuffer()],
+            program.programId
+        );
+        const [mainReceipt] = PublicKey.findProgramAddressSync(
+            [
+                Buffer.from("receipt"),
+                gateway.toBuffer(),
+                user.publicKey.toBuffer(),
+                nonce.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+        );
+        await program.methods
+            .processSolPayment(mainFee, nonce, DEFAULT_ENDPOINT)
+            .accounts({
+                gateway,
+                priceEntry: null,
+                user: user.publicKey,
+                solTreasury: mainTreasury,
+                receipt: mainReceipt,
+                instructions: null,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+        const mainReceiptAccount = await program.account.paymentReceipt.fetch(
+            mainReceipt
+        );
+        expect(mainReceiptAccount.gateway.toString()).to.equal(
+            gateway.toString()
+        );
+    });
+
+    it("Queues fee changes behind the timelock", async () => {
+        const gatewayBefore = await program.account.gateway.fetch(gateway);
+
+        await program.methods
+            .updateFee(new anchor.BN(2000000), usdcMint)
+            .accounts({ gateway, authority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+
+        const gatewayAccount = await program.account.gateway.fetch(gateway);
+        expect(gatewayAccount.fee.toNumber()).to.equal(
+            gatewayBefore.fee.toNumber()
+        );
+        expect(gatewayAccount.pendingFee.fee.toNumber()).to.equal(2000000);
+
+        // Applying before the timelock has passed fails
+        let applied = true;
+        try {
+            await program.methods
+                .applyFeeChange()
+                .accounts({ gateway })
+                .rpc();
+        } catch (e) {
+            applied = false;
+        }
+        expect(applied).to.equal(false);
+
+        await program.methods
+            .cancelFeeChange()
+            .accounts({ gateway, authority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+        const cancelled = await program.account.gateway.fetch(gateway);
+        expect(cancelled.pendingFee).to.equal(null);
+
+        // A timelock longer than 30 days would freeze fee changes
+        try {
+            await program.methods
+                .setFeeTimelock(new anchor.BN(31 * 24 * 60 * 60))
+                .accounts({ gateway, authority: authority.publicKey })
+                .signers([authority])
+                .rpc();
+            expect.fail("Timelocks over 30 days should be rejected");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("TimelockTooLong");
+        }
+    });
+
+    it("Transfers the authority in two steps", async () => {
+        const newAuthority = Keypair.generate();
+
+        await program.methods
+            .proposeAuthority(newAuthority.publicKey)
+            .accounts({ gateway, authority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+
+        // Only the proposed key can accept
+        let accepted = true;
+        try {
+            await program.methods
+                .acceptAuthority()
+                .accounts({ gateway, newAuthority: user.publicKey })
+                .signers([user])
+                .rpc();
+        } catch (e) {
+            accepted = false;
+        }
+        expect(accepted).to.equal(false);
+
+        await program.methods
+            .acceptAuthority()
+            .accounts({ gateway, newAuthority: newAuthority.publicKey })
+            .signers([newAuthority])
+            .rpc();
+        let gatewayAccount = await program.account.gateway.fetch(gateway);
+        expect(gatewayAccount.authority.toString()).to.equal(
+            newAuthority.publicKey.toString()
+        );
+
+        // Hand it back so later tests keep the original authority
+        await program.methods
+            .proposeAuthority(authority.publicKey)
+            .accounts({ gateway, authority: newAuthority.publicKey })
+            .signers([newAuthority])
+            .rpc();
+        await program.methods
+            .acceptAuthority()
+            .accounts({ gateway, newAuthority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+        gatewayAccount = await program.account.gateway.fetch(gateway);
+        expect(gatewayAccount.authority.toString()).to.equal(
+            authority.publicKey.toString()
+        );
+    });
+
+    it("Hosts several gateways in one deployment", async () => {
+        const otherId = GATEWAY_ID.addn(1);
+        const [otherGateway] = PublicKey.findProgramAddressSync(
+            [Buffer.from("gateway"), otherId.toArrayLike(Buffer, "le", 8)],
+            program.programId
+        );
+
+        await program.methods
+            .initialize(otherId, new anchor.BN(3000000), usdcMint)
+            .accounts({
+                gateway: otherGateway,
+                authority: user.publicKey,
+                systemProgram: SystemProgram.programId,
+            })
+            .signers([user])
+            .rpc();
+
+        const other = await program.account.gateway.fetch(otherGateway);
+        const original = await program.account.gateway.fetch(gateway);
+        expect(other.gatewayId.toString()).to.equal(otherId.toString());
+        expect(other.authority.toString()).to.equal(user.publicKey.toString());
+        expect(other.fee.toNumber()).to.equal(3000000);
+        expect(original.authority.toString()).to.equal(
+            authority.publicKey.toString()
+        );
+
+        // The other gateway's authority can't change this one
+        let updated = true;
+        try {
+            await program.methods
+                .updateFee(new anchor.BN(1), usdcMint)
+                .accounts({ gateway, authority: user.publicKey })
+                .signers([user])
+                .rpc();
+        } catch (e) {
+            updated = false;
+        }
+        expect(updated).to.equal(false);
+    });
+
+    it("Settles payments against signed vouchers", async () => {
+        const voucherSigner = Keypair.generate();
+        await program.methods
+            .setVoucherSigner(voucherSigner.publicKey)
+            .accounts({ gateway, authority: authority.publicKey })
+            .signers([authority])
+            .rpc();
+
+        const endpointId = [
+            ...createHash("sha256").update("POST /api/v1/quote:v1").digest(),
+        ];
+        const pay = async (voucherAmount: number, expiresAt: number) => {
+            const nonce = new anchor.BN(Date.now());
+            const [receipt] = PublicKey.findProgramAddressSync(
+                [
+                    Buffer.from("receipt"),
+                    gateway.toBuffer(),
+                    user.publicKey.toBuffer(),
+                    nonce.toArrayLike(Buffer, "le", 8),
+                ],
+                program.programId
+            );
+
+            // VOUCHER_PREFIX followed by the Borsh-serialized Voucher
+            const message = Buffer.concat([
+                Buffer.from("risk:voucher:v1"),
+                gateway.toBuffer(),
+                user.publicKey.toBuffer(),
+                usdcMint.toBuffer(),
+                new anchor.BN(voucherAmount).toArrayLike(Buffer, "le", 8),
+                Buffer.from(endpointId),
+                new anchor.BN(expiresAt).toArrayLike(Buffer, "le", 8),
+                nonce.toArrayLike(Buffer, "le", 8),
+            ]);
+            const sigverify = Ed25519Program.createInstructionWithPrivateKey({
+                privateKey: voucherSigner.secretKey,
+                message,
+            });
+
+            await program.methods
+                .processPayment(new anchor.BN(voucherAmount), nonce, endpointId)
+                .accounts({
+                    gateway,
+                    priceEntry: null,
+                    user: user.publicKey,
+                    mint: usdcMint,
+                    userTokenAccount,
+                    treasuryTokenAccount,
+                    receipt,
+                    instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+                    tokenProgram: TOKEN_PROGRAM_ID,
+                    systemProgram: SystemProgram.programId,
+                })
+                .preInstructions([sigverify])
+                .signers([user])
+                .rpc();
+            return receipt;
+        };
+
+        // A quoted price below the gateway fee is honoured
+        const now = Math.floor(Date.now() / 1000);
+        const receipt = await pay(250000, now + 300);
+        const receiptAccount = await program.account.paymentReceipt.fetch(
+            receipt
+        );
+        expect(receiptAccount.amount.toNumber()).to.equal(250000);
+
+        try {
+            await pay(250000, now - 300);
+            expect.fail("Expired vouchers should be rejected");
+        } catch (e) {
+            expect(e.error?.errorCode?.code).to.equal("VoucherExpired");
+        }
     });
 });